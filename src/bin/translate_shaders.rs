@@ -0,0 +1,107 @@
+/*  File: bin/translate_shaders.rs
+*   Author: Vicix
+*
+*   This is the offline shader translation command. It needs the shader_tools feature:
+*       cargo run --features shader_tools --bin translate_shaders <gl33 | gl46 | gles30> [shader directory] [output directory]
+*   It translates every shader of the directory (src/shaders by default) to the target and writes it in the output directory (target/shaders/<target> by default).
+*   The glsl targets keep the file name (vert.glsl, or vert.es.glsl for gles30), the spir-v target writes vert.spv.
+*   The stage is found from the file name like in validate_shaders, and the files without a main function are include files and are skipped.
+*   The command exits with 1 if a shader cannot be translated.
+*/
+
+use open_gl_take_2::renderer::errors::ShaderError;
+use open_gl_take_2::renderer::preprocessor::Preprocessor;
+use open_gl_take_2::renderer::glslFrontend;
+use open_gl_take_2::renderer::shaderTranslator::{self, ShaderTarget, TranslatedShader};
+use open_gl_take_2::renderer::spirv;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let target_name = env::args().nth(1).unwrap_or_default();
+    let target = match target_name.as_str() {
+        "gl33" => ShaderTarget::Gl33,
+        "gl46" => ShaderTarget::Gl46Spirv,
+        "gles30" => ShaderTarget::Gles30,
+        _ => {
+            println!("Usage: translate_shaders <gl33 | gl46 | gles30> [shader directory] [output directory]");
+            process::exit(1);
+        },
+    };
+    let directory = env::args().nth(2).unwrap_or_else(|| "src/shaders".to_string());
+    let output_directory = env::args().nth(3).unwrap_or_else(|| format!("target/shaders/{}", target_name));
+
+    let mut paths: Vec::<_> = match fs::read_dir(&directory) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "glsl"))
+            .collect(),
+        Err(error) => {
+            println!("Failed to read the shader directory {}: {}", directory, error);
+            process::exit(1);
+        },
+    };
+    paths.sort();
+
+    if let Err(error) = fs::create_dir_all(&output_directory) {
+        println!("Failed to create the output directory {}: {}", output_directory, error);
+        process::exit(1);
+    }
+
+    let preprocessor = Preprocessor::new();
+    let mut error_count = 0;
+    for path in &paths {
+        let path_string = path.to_string_lossy().into_owned();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        let processed = match preprocessor.process_file(&path_string) {
+            Ok(processed) => processed,
+            Err(error) => {
+                println!("{}", error);
+                error_count += 1;
+                continue;
+            },
+        };
+        if !glslFrontend::has_entry_point(&processed) {
+            continue;
+        }
+
+        let stage = match glslFrontend::get_file_stage(&file_name) {
+            Some(stage) => stage,
+            None => {
+                println!("Skipping {}: the stage cannot be found from the file name.", path_string);
+                continue;
+            },
+        };
+
+        let bytes = match shaderTranslator::translate(stage, &processed, target) {
+            Ok(TranslatedShader::Glsl(code)) => code.into_bytes(),
+            Ok(TranslatedShader::Spirv(words)) => spirv::words_to_bytes(&words),
+            Err(ShaderError::InvalidStages(message)) => {
+                println!("Skipping {}: {}", path_string, message);
+                continue;
+            },
+            Err(error) => {
+                println!("{}", error);
+                error_count += 1;
+                continue;
+            },
+        };
+
+        let output_path = Path::new(&output_directory).join(format!("{}.{}", file_name.trim_end_matches(".glsl"), target.get_extension()));
+        match fs::write(&output_path, bytes) {
+            Ok(_) => println!("Translated {} shader {} to {}.", stage, path_string, output_path.display()),
+            Err(error) => {
+                println!("Failed to write {}: {}", output_path.display(), error);
+                error_count += 1;
+            },
+        }
+    }
+
+    if error_count > 0 {
+        println!("{} error(s) found.", error_count);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,166 @@
+/*  File: bin/validate_shaders.rs
+*   Author: Vicix
+*
+*   This is the offline shader validation command. It needs the shader_tools feature:
+*       cargo run --features shader_tools --bin validate_shaders [shader directory]
+*   It preprocesses, parses and validates every shader of the directory (src/shaders by default) without creating a window.
+*   The stage is found from the file name (vert, frag, comp). The files without a main function are include files and are skipped.
+*   The vertex shaders are also compared with the layouts in vertex_layouts.txt, if the file exists.
+*   The command exits with 1 if a shader has an error, so it can be used in a script or in the ci.
+*/
+
+extern crate gl;
+
+use open_gl_take_2::renderer::shader::ShaderStage;
+use open_gl_take_2::renderer::errors::ShaderError;
+use open_gl_take_2::renderer::preprocessor::Preprocessor;
+use open_gl_take_2::renderer::glslFrontend;
+use open_gl_take_2::renderer::vertexInput;
+use open_gl_take_2::renderer::vaoLayoutElement::VaoLayoutElement;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+/*  This is the name of the file with the expected vertex layouts.
+*   Every line is the file name of a vertex shader followed by its layout elements, in location order:
+*       vert.glsl: FLOAT 2, FLOAT 3, FLOAT 2
+*   A - is an unused location. The lines starting with # are comments.
+*/
+const LAYOUTS_FILE: &str = "vertex_layouts.txt";
+
+fn main() {
+    let directory = env::args().nth(1).unwrap_or_else(|| "src/shaders".to_string());
+
+    let mut paths: Vec::<_> = match fs::read_dir(&directory) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "glsl"))
+            .collect(),
+        Err(error) => {
+            println!("Failed to read the shader directory {}: {}", directory, error);
+            process::exit(1);
+        },
+    };
+    paths.sort();
+
+    let layouts = match load_layouts(&Path::new(&directory).join(LAYOUTS_FILE)) {
+        Ok(layouts) => layouts,
+        Err(message) => {
+            println!("{}", message);
+            process::exit(1);
+        },
+    };
+
+    let preprocessor = Preprocessor::new();
+    let mut error_count = 0;
+    for path in &paths {
+        let path_string = path.to_string_lossy().into_owned();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        let processed = match preprocessor.process_file(&path_string) {
+            Ok(processed) => processed,
+            Err(error) => {
+                println!("{}", error);
+                error_count += 1;
+                continue;
+            },
+        };
+        if !glslFrontend::has_entry_point(&processed) {
+            continue;
+        }
+
+        let stage = match glslFrontend::get_file_stage(&file_name) {
+            Some(stage) => stage,
+            None => {
+                println!("Skipping {}: the stage cannot be found from the file name.", path_string);
+                continue;
+            },
+        };
+
+        let module = match glslFrontend::parse_module(stage, &processed) {
+            Ok(module) => module,
+            Err(ShaderError::InvalidStages(message)) => {
+                println!("Skipping {}: {}", path_string, message);
+                continue;
+            },
+            Err(error) => {
+                println!("{}", error);
+                error_count += 1;
+                continue;
+            },
+        };
+
+        let mut has_layout_errors = false;
+        if let (ShaderStage::Vertex, Some(layout)) = (stage, layouts.get(&file_name)) {
+            let inputs = glslFrontend::get_vertex_inputs(&module);
+            for mismatch in vertexInput::check_vertex_layout(&inputs, layout) {
+                if mismatch.is_error() {
+                    println!("Error in {}: {}", path_string, mismatch);
+                    error_count += 1;
+                    has_layout_errors = true;
+                } else {
+                    println!("Warning in {}: {}", path_string, mismatch);
+                }
+            }
+        }
+
+        if !has_layout_errors {
+            println!("Validated {} shader {}.", stage, path_string);
+        }
+    }
+
+    if error_count > 0 {
+        println!("{} error(s) found.", error_count);
+        process::exit(1);
+    }
+}
+
+/*  This function loads the expected vertex layouts. A missing file means that there are no layouts to check.
+*/
+fn load_layouts(path: &Path) -> Result<HashMap::<String, Vec::<VaoLayoutElement>>, String> {
+    let mut layouts = HashMap::<String, Vec::<VaoLayoutElement>>::new();
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return Ok(layouts),
+    };
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: &str| format!("{}:{}: {}", path.display(), i + 1, message);
+        let mut parts = line.splitn(2, ':');
+        let file_name = parts.next().unwrap_or("").trim().to_string();
+        let elements = parts.next().ok_or_else(|| error("Expected \"file: elements\"."))?;
+
+        let mut layout = Vec::<VaoLayoutElement>::new();
+        for element in elements.split(',').map(|element| element.trim()) {
+            if element == "-" {
+                layout.push(VaoLayoutElement { used: false, element_type: gl::FLOAT, normalized: gl::FALSE, element_count: 0 });
+                continue;
+            }
+
+            let mut words = element.split_whitespace();
+            let element_type = match words.next() {
+                Some("FLOAT") => gl::FLOAT,
+                Some("INT") => gl::INT,
+                Some("UNSIGNED_INT") => gl::UNSIGNED_INT,
+                Some("BYTE") => gl::BYTE,
+                Some("UNSIGNED_BYTE") => gl::UNSIGNED_BYTE,
+                _ => return Err(error(&format!("Unknown element type in \"{}\".", element))),
+            };
+            let element_count = words.next().and_then(|count| count.parse().ok())
+                .ok_or_else(|| error(&format!("Expected an element count in \"{}\".", element)))?;
+
+            layout.push(VaoLayoutElement { used: true, element_type, normalized: gl::FALSE, element_count });
+        }
+
+        layouts.insert(file_name, layout);
+    }
+
+    Ok(layouts)
+}
@@ -0,0 +1,7 @@
+/*  File: lib.rs
+*   Author: Vicix
+*
+*   This file exposes the renderer, so it can be used by the main testground and by the tools in src/bin.
+*/
+
+pub mod renderer;
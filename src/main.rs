@@ -16,19 +16,20 @@ use std::os::raw::c_void;
 use std::ffi::CStr;
 use std::time::Instant;
 
-mod renderer;
-
-use crate::renderer::traits::vaoTrait::{VaoTrait, VaoLayoutTrait};
-use crate::renderer::traits::eboTrait::{EboTrait, OptionalEboTrait};
-use crate::renderer::traits::shaderTrait::ShaderTrait;
-use crate::renderer::traits::textureTrait::TextureTrait;
-use crate::renderer::traits::glObjectTrait::GlObjectTrait;
-
-use crate::renderer::shader;
-use crate::renderer::texture;
-use crate::renderer::constants;
-use crate::renderer::globject;
-use crate::renderer::material;
+use open_gl_take_2::renderer::traits::vaoTrait::{VaoTrait, VaoLayoutTrait};
+use open_gl_take_2::renderer::traits::eboTrait::{EboTrait, OptionalEboTrait};
+use open_gl_take_2::renderer::traits::shaderTrait::ShaderTrait;
+use open_gl_take_2::renderer::traits::textureTrait::TextureTrait;
+use open_gl_take_2::renderer::traits::glObjectTrait::GlObjectTrait;
+
+use open_gl_take_2::renderer::shader;
+use open_gl_take_2::renderer::shaderBuilder;
+use open_gl_take_2::renderer::shaderVariants;
+use open_gl_take_2::renderer::texture;
+use open_gl_take_2::renderer::constants;
+use open_gl_take_2::renderer::globject;
+use open_gl_take_2::renderer::material;
+use open_gl_take_2::renderer::spirv;
 
 // settings
 const SCR_WIDTH: u32 = 600;
@@ -58,6 +59,7 @@ pub fn main() {
     // gl: load all OpenGL function pointers
     // ---------------------------------------
     gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+    spirv::load_with(|symbol| window.get_proc_address(symbol) as *const _);
 
     unsafe {
         println!("Opengl Version: {}", CStr::from_ptr(gl::GetString(gl::VERSION) as *const i8).to_str().unwrap());
@@ -86,8 +88,14 @@ pub fn main() {
         1, 2, 4,
     ];
 
-    let shader = shader::Shader::new("./src/shaders/vert2.glsl", "./src/shaders/frag2.glsl");
-    let albedo_shader = shader::Shader::new("./src/shaders/albedo_vert.glsl", "./src/shaders/albedo_frag.glsl");
+    let shader_variants = shaderVariants::ShaderVariants::new(shaderBuilder::ShaderBuilder::new()
+            .with_file(shader::ShaderStage::Vertex, "./src/shaders/vert2.glsl")
+            .with_file(shader::ShaderStage::Fragment, "./src/shaders/frag.glsl"))
+        .with_keyword("USE_VERTEX_COLOR");
+    let albedo_shader = shader::Shader::new("./src/shaders/albedo_vert.glsl", "./src/shaders/albedo_frag.glsl")
+        .unwrap_or_else(|error| panic!("{}", error));
+    shader_variants.watch();
+    albedo_shader.watch();
 
     let mut globj = globject::GlObject::with_ebo();
     globj.add_vertex_data::<GLfloat>(vertices.len(), &vertices[0] as *const f32 as *const c_void, gl::STATIC_DRAW);
@@ -107,22 +115,44 @@ pub fn main() {
     globj2.set_property(constants::EBO_TYPE_PROPERTY, gl::UNSIGNED_INT);
     globj2.write_layout();
 
-    let mut texture1 = texture::Texture::new(gl::TEXTURE_2D, "./src/resources/wall.jpg", gl::RGB, gl::RGB, 0);
+    let mut texture1 = texture::Texture::from_file(gl::TEXTURE_2D, "./src/resources/wall.jpg", 0)
+        .unwrap_or_else(|error| {
+            println!("{}", error);
+            texture::Texture::missing(gl::TEXTURE_2D, 0)
+        });
     texture1.set_gl_property(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
     texture1.set_gl_property(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
-    texture1.gen_texture();
+    if let Err(error) = texture1.gen_texture() {
+        println!("{}", error);
+        texture1.gen_placeholder();
+    }
 
-    let mut texture2 = texture::Texture::new(gl::TEXTURE_2D, "./src/resources/awesomeface.png", gl::RGB, gl::RGBA, 1);
+    let mut texture2 = texture::Texture::from_file(gl::TEXTURE_2D, "./src/resources/awesomeface.png", 1)
+        .unwrap_or_else(|error| {
+            println!("{}", error);
+            texture::Texture::missing(gl::TEXTURE_2D, 1)
+        });
     texture2.set_gl_property(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
     texture2.set_gl_property(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
     texture2.set_image_property(constants::FLIP_V_PROPERTY, 1);
-    texture2.gen_texture();
+    if let Err(error) = texture2.gen_texture() {
+        println!("{}", error);
+        texture2.gen_placeholder();
+    }
 
-    let mut texture3 = texture::Texture::new(gl::TEXTURE_2D, "./src/resources/cat.jpeg", gl::RGB, gl::RGB, 2);
+    let mut texture3 = texture::Texture::from_file(gl::TEXTURE_2D, "./src/resources/cat.jpeg", 2)
+        .unwrap_or_else(|error| {
+            println!("{}", error);
+            texture::Texture::missing(gl::TEXTURE_2D, 2)
+        });
     texture3.set_image_property(constants::FLIP_V_PROPERTY, 1);
-    texture3.gen_texture();
+    if let Err(error) = texture3.gen_texture() {
+        println!("{}", error);
+        texture3.gen_placeholder();
+    }
 
-    let mut material = material::Material::new(&shader);
+    let mut material = material::Material::from_variants(&shader_variants, &[])
+        .unwrap_or_else(|error| panic!("{}", error));
     material.push_texture(&texture1, "texture1");
     material.push_texture(&texture2, "texture2");
     material.push_texture(&texture3, "texture3");
@@ -138,6 +168,13 @@ pub fn main() {
         // -----
         process_events(&mut window, &events);
 
+        if let Err(error) = shader_variants.reload_if_changed() {
+            println!("{}", error);
+        }
+        if let Err(error) = albedo_shader.reload_if_changed() {
+            println!("{}", error);
+        }
+
         unsafe {
             gl::ClearColor(0.2, 0.5, 0.8, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
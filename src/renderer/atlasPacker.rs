@@ -0,0 +1,214 @@
+/*  File: renderer/atlasPacker.rs
+*   Author: Vicix
+*
+*   This file contains the rectangle packer used by the texture atlases (see renderer::textureAtlas).
+*   It is a skyline packer: the top of the already placed rectangles is kept as a list of horizontal segments
+*   and every rectangle is placed where its bottom is the lowest (then the leftmost), from the tallest rectangle to the shortest.
+*   The packer only works on sizes, it doesn't use opengl or the pixels.
+*/
+
+/*  This struct is the position and the size of a packed rectangle, in pixels from the top left corner of the atlas.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackedRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/*  This struct is the result of a packing: the size of the atlas and the rectangles, in the order of the sizes given to pack.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packing {
+    pub width: u32,
+    pub height: u32,
+    pub rects: Vec::<PackedRect>,
+}
+
+/*  This struct is a segment of the skyline: the top of the rectangles from x to x + width is at y.
+*/
+#[derive(Debug, Clone, Copy)]
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/*  This function packs rectangles of some sizes (width, height) in the smallest power of two atlas that fits them, up to max_size x max_size.
+*   The atlas starts from the smallest power of two with enough area and grows doubling the width and the height in turn.
+*   It returns None if the rectangles don't fit in max_size x max_size.
+*/
+pub fn pack(sizes: &[(u32, u32)], max_size: u32) -> Option<Packing> {
+    let area = sizes.iter().map(|(width, height)| *width as u64 * *height as u64).sum::<u64>();
+    let widest = sizes.iter().map(|(width, _)| *width).max().unwrap_or(1).max(1);
+    let tallest = sizes.iter().map(|(_, height)| *height).max().unwrap_or(1).max(1);
+
+    let mut width = widest.next_power_of_two();
+    let mut height = tallest.next_power_of_two();
+    while (width as u64 * height as u64) < area {
+        if width <= height { width *= 2 } else { height *= 2 }
+    }
+
+    while width <= max_size && height <= max_size {
+        if let Some(rects) = pack_in(sizes, width, height) {
+            return Some(Packing {
+                width,
+                height,
+                rects,
+            });
+        }
+
+        if width <= height { width *= 2 } else { height *= 2 }
+    }
+
+    None
+}
+
+/*  This function packs rectangles of some sizes in an atlas of a fixed size.
+*   It returns the rectangles in the order of the sizes, or None if they don't fit.
+*/
+pub fn pack_in(sizes: &[(u32, u32)], width: u32, height: u32) -> Option<Vec::<PackedRect>> {
+    //the tallest rectangles are placed first, the order of the equal ones is kept so the packing is always the same.
+    let mut order = (0..sizes.len()).collect::<Vec::<usize>>();
+    order.sort_by(|first, second| sizes[*second].1.cmp(&sizes[*first].1).then(sizes[*second].0.cmp(&sizes[*first].0)));
+
+    let mut skyline = vec![SkylineSegment { x: 0, y: 0, width }];
+    let mut rects = vec![PackedRect::default(); sizes.len()];
+    for index in order {
+        let (rect_width, rect_height) = sizes[index];
+        let (segment, x, y) = find_position(&skyline, rect_width, rect_height, width, height)?;
+        add_to_skyline(&mut skyline, segment, x, y + rect_height, rect_width);
+
+        rects[index] = PackedRect {
+            x,
+            y,
+            width: rect_width,
+            height: rect_height,
+        };
+    }
+
+    Some(rects)
+}
+
+/*  This function finds where a rectangle has the lowest bottom (then the leftmost position).
+*   It returns the index of the first segment under the rectangle and the position of the rectangle.
+*/
+fn find_position(skyline: &[SkylineSegment], width: u32, height: u32, atlas_width: u32, atlas_height: u32) -> Option<(usize, u32, u32)> {
+    let mut best: Option<(usize, u32, u32)> = None;
+    for (index, segment) in skyline.iter().enumerate() {
+        if segment.x + width > atlas_width {
+            break;
+        }
+
+        //the rectangle lies on the highest segment under it.
+        let mut y = 0;
+        let mut covered = 0;
+        for next in &skyline[index..] {
+            if covered >= width {
+                break;
+            }
+            y = y.max(next.y);
+            covered += next.width;
+        }
+
+        let is_better = match best {
+            Some((_, _, best_y)) => y < best_y,
+            None => true,
+        };
+        if y + height <= atlas_height && is_better {
+            best = Some((index, segment.x, y));
+        }
+    }
+
+    best
+}
+
+/*  This function adds the top of a placed rectangle to the skyline.
+*   The segments under the rectangle are removed or shortened and the segments at the same height are merged.
+*/
+fn add_to_skyline(skyline: &mut Vec::<SkylineSegment>, index: usize, x: u32, y: u32, width: u32) {
+    skyline.insert(index, SkylineSegment { x, y, width });
+
+    let right = x + width;
+    while index + 1 < skyline.len() && skyline[index + 1].x < right {
+        let next = &mut skyline[index + 1];
+        let next_right = next.x + next.width;
+        if next_right <= right {
+            skyline.remove(index + 1);
+        } else {
+            next.width = next_right - right;
+            next.x = right;
+        }
+    }
+
+    let mut merged = 0;
+    while merged + 1 < skyline.len() {
+        if skyline[merged].y == skyline[merged + 1].y {
+            skyline[merged].width += skyline[merged + 1].width;
+            skyline.remove(merged + 1);
+        } else {
+            merged += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*  This function checks that the rectangles have their size, are inside the atlas and don't overlap.
+    */
+    fn check_rects(sizes: &[(u32, u32)], rects: &[PackedRect], width: u32, height: u32) {
+        assert_eq!(sizes.len(), rects.len());
+        for (index, rect) in rects.iter().enumerate() {
+            assert_eq!((rect.width, rect.height), sizes[index]);
+            assert!(rect.x + rect.width <= width && rect.y + rect.height <= height, "{:?} is outside the {}x{} atlas.", rect, width, height);
+
+            for other in &rects[index + 1..] {
+                let separate = rect.x + rect.width <= other.x || other.x + other.width <= rect.x || rect.y + rect.height <= other.y || other.y + other.height <= rect.y;
+                assert!(separate, "{:?} overlaps {:?}.", rect, other);
+            }
+        }
+    }
+
+    #[test]
+    fn rects_do_not_overlap() {
+        //the sizes are mixed with a fixed sequence, so the test is always the same.
+        let sizes = (0..60u32).map(|index| (1 + index * 7 % 23, 1 + index * 13 % 17)).collect::<Vec::<(u32, u32)>>();
+        let packing = pack(&sizes, 1024).unwrap();
+
+        assert!(packing.width.is_power_of_two() && packing.height.is_power_of_two());
+        check_rects(&sizes, &packing.rects, packing.width, packing.height);
+    }
+
+    #[test]
+    fn exact_fit_is_packed() {
+        let sizes = [(2, 2), (2, 2), (4, 2), (1, 1), (1, 1), (1, 1), (1, 1)];
+        let rects = pack_in(&sizes, 4, 5).unwrap();
+
+        check_rects(&sizes, &rects, 4, 5);
+    }
+
+    #[test]
+    fn atlas_grows_to_fit() {
+        //8x8 has enough area, but only four 3x3 rectangles fit in it.
+        let packing = pack(&[(3, 3); 5], 64).unwrap();
+
+        assert_eq!((packing.width, packing.height), (16, 8));
+        check_rects(&[(3, 3); 5], &packing.rects, 16, 8);
+    }
+
+    #[test]
+    fn no_fit_is_none() {
+        assert_eq!(pack_in(&[(5, 4)], 4, 4), None);
+        assert_eq!(pack_in(&[(2, 3), (3, 2)], 4, 4), None);
+        assert_eq!(pack(&[(300, 10)], 256), None);
+        assert_eq!(pack(&[(200, 200), (200, 200)], 256), None);
+    }
+
+    #[test]
+    fn nothing_to_pack() {
+        assert_eq!(pack(&[], 16).map(|packing| packing.rects), Some(Vec::<PackedRect>::new()));
+    }
+}
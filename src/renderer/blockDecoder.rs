@@ -0,0 +1,642 @@
+/*  File: renderer/blockDecoder.rs
+*   Author: Vicix
+*
+*   This file contains the functions that decompress the compressed images on the cpu.
+*   They are used when the gpu does not support a compressed format (see TextureTrait::upload_compressed): the image is decompressed in rgba and uploaded like a normal image.
+*   The supported formats are BC1, BC2, BC3, BC7 and ETC2 (with the punchthrough alpha and with the EAC alpha).
+*   BC4, BC5 (core since opengl 3.0), BC6H, EAC and ASTC are not decompressed: there is no cpu fallback for them,
+*   so they can only be loaded when the driver supports them (BC6H needs opengl 4.2 or ARB_texture_compression_bptc, ASTC needs KHR_texture_compression_astc_ldr).
+*/
+
+use crate::renderer::compressedImage::CompressedFormat;
+
+/*  These are the modifiers of the ETC1/ETC2 individual and differential modes.
+*/
+const ETC_MODIFIERS: [[i32; 4]; 8] = [[2, 8, -2, -8], [5, 17, -5, -17], [9, 29, -9, -29], [13, 42, -13, -42], [18, 60, -18, -60], [24, 80, -24, -80], [33, 106, -33, -106], [47, 183, -47, -183]];
+
+/*  These are the distances of the ETC2 T and H modes.
+*/
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/*  These are the modifiers of the EAC alpha.
+*/
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/*  This struct describes a BC7 mode: the number of subsets, the bits of the partition, of the rotation and of the index selection,
+*   the bits of the colors and of the alpha, if there is a p-bit for every endpoint or for every subset and the bits of the two index sets.
+*/
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_p_bits: bool,
+    shared_p_bits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_p_bits: true, shared_p_bits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_p_bits: true, shared_p_bits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+];
+
+/*  These are the weights of the BC7 interpolation, for the indices of 2, 3 and 4 bits.
+*/
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/*  These are the subsets of the pixels of the BC7 partitions with 2 subsets.
+*/
+const BC7_PARTITIONS_2: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1], [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1], [0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1], [0, 0, 0, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1, 1], [0, 1, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0], [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0], [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0], [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0], [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0], [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0], [0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0], [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0], [0, 0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 0, 0],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0], [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0], [0, 1, 1, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0], [0, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1], [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1], [0, 1, 0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0], [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0], [0, 1, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0], [0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0, 0, 1], [0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0, 0, 1, 0, 1],
+    [0, 1, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 0], [0, 0, 0, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 0, 0, 0], [0, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 0], [0, 0, 1, 1, 1, 0, 1, 1, 1, 1, 0, 1, 1, 1, 0, 0],
+    [0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0, 1, 1, 0], [0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 1, 1], [0, 1, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1], [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0], [0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0], [0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0], [0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1], [0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0], [0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 0, 0, 1], [0, 1, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1], [0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1], [0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0], [0, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0], [0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1],
+];
+
+/*  These are the subsets of the pixels of the BC7 partitions with 3 subsets.
+*/
+const BC7_PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2], [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1], [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2], [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2], [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2], [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2], [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2], [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2], [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0], [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2], [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1], [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2], [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0], [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2], [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0], [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2], [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2], [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1], [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1], [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2], [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0], [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0], [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0], [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1], [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1], [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1], [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1], [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1], [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2], [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1], [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2], [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2], [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2], [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2], [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1], [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2], [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2], [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+/*  These are the anchor pixels (the pixels whose index has one bit less) of the second subset of the partitions with 2 subsets
+*   and of the second and third subsets of the partitions with 3 subsets. The anchor of the first subset is always the pixel 0.
+*/
+const BC7_ANCHORS_2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+const BC7_ANCHORS_3_SECOND: [usize; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+    3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+    8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+    3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+];
+
+const BC7_ANCHORS_3_THIRD: [usize; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+    15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+    15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+    15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+/*  This function returns true if the format can be decompressed on the cpu.
+*/
+pub fn is_decompressible(format: CompressedFormat) -> bool {
+    matches!(format, CompressedFormat::Bc1 | CompressedFormat::Bc1Alpha | CompressedFormat::Bc2 | CompressedFormat::Bc3 | CompressedFormat::Bc7
+        | CompressedFormat::Etc2 | CompressedFormat::Etc2Alpha1 | CompressedFormat::Etc2Eac)
+}
+
+/*  This function decompresses an image in rgba pixels (4 bytes per pixel, the rows from the first one of the image).
+*   It returns None if the format cannot be decompressed, if the data is too short or if the size overflows.
+*/
+pub fn decompress(format: CompressedFormat, width: u32, height: u32, data: &[u8]) -> Option<Vec::<u8>> {
+    let decode_block: fn(&[u8], &mut [[u8; 4]; 16]) = match format {
+        CompressedFormat::Bc1 => |block, pixels| decode_bc1(block, pixels, false, false),
+        CompressedFormat::Bc1Alpha => |block, pixels| decode_bc1(block, pixels, true, false),
+        CompressedFormat::Bc2 => decode_bc2,
+        CompressedFormat::Bc3 => decode_bc3,
+        CompressedFormat::Bc7 => decode_bc7,
+        CompressedFormat::Etc2 => |block, pixels| decode_etc2(block, pixels, false),
+        CompressedFormat::Etc2Alpha1 => |block, pixels| decode_etc2(block, pixels, true),
+        CompressedFormat::Etc2Eac => decode_etc2_eac,
+        _ => return None,
+    };
+
+    let block_bytes = format.get_block_bytes();
+    let (block_columns, block_rows) = (width.div_ceil(4) as usize, height.div_ceil(4) as usize);
+    if data.len() < block_columns.checked_mul(block_rows)?.checked_mul(block_bytes)? {
+        return None;
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let mut output = vec![0; width.checked_mul(height)?.checked_mul(4)?];
+    let mut pixels = [[0; 4]; 16];
+    for (block_index, block) in data.chunks_exact(block_bytes).take(block_columns * block_rows).enumerate() {
+        decode_block(block, &mut pixels);
+
+        let (block_x, block_y) = (block_index % block_columns * 4, block_index / block_columns * 4);
+        for (pixel_index, pixel) in pixels.iter().enumerate() {
+            let (x, y) = (block_x + pixel_index % 4, block_y + pixel_index / 4);
+            if x < width && y < height {
+                let start = (y * width + x) * 4;
+                output[start..start + 4].copy_from_slice(pixel);
+            }
+        }
+    }
+
+    Some(output)
+}
+
+/*  This function decodes a BC1 block (or the color of a BC2 and BC3 block, that always use 4 colors).
+*   With alpha the pixels with the index 3 of a block with 3 colors are transparent, without alpha they are black.
+*/
+fn decode_bc1(block: &[u8], pixels: &mut [[u8; 4]; 16], alpha: bool, always_four_colors: bool) {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let [red0, green0, blue0] = expand_565(color0);
+    let [red1, green1, blue1] = expand_565(color1);
+    let mix = |weight0: u32, weight1: u32, divisor: u32| [
+        ((red0 * weight0 + red1 * weight1) / divisor) as u8,
+        ((green0 * weight0 + green1 * weight1) / divisor) as u8,
+        ((blue0 * weight0 + blue1 * weight1) / divisor) as u8,
+        255,
+    ];
+
+    let colors = if color0 > color1 || always_four_colors {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2), [0, 0, 0, if alpha { 0 } else { 255 }]]
+    };
+
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = colors[(indices >> (index * 2)) as usize & 3];
+    }
+}
+
+/*  This function decodes a BC2 block: 4 bit alphas followed by a BC1 color.
+*/
+fn decode_bc2(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    decode_bc1(&block[8..], pixels, false, true);
+
+    let alphas = u64::from_le_bytes([block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7]]);
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        pixel[3] = ((alphas >> (index * 4)) & 15) as u8 * 17;
+    }
+}
+
+/*  This function decodes a BC3 block: two alphas with 3 bit indices followed by a BC1 color.
+*/
+fn decode_bc3(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    decode_bc1(&block[8..], pixels, false, true);
+
+    let (alpha0, alpha1) = (block[0] as u32, block[1] as u32);
+    let mut alphas = [alpha0, alpha1, 0, 0, 0, 0, 0, 255];
+    if alpha0 > alpha1 {
+        for (index, alpha) in alphas.iter_mut().enumerate().skip(2) {
+            *alpha = ((8 - index as u32) * alpha0 + (index as u32 - 1) * alpha1) / 7;
+        }
+    } else {
+        for (index, alpha) in alphas.iter_mut().enumerate().take(6).skip(2) {
+            *alpha = ((6 - index as u32) * alpha0 + (index as u32 - 1) * alpha1) / 5;
+        }
+    }
+
+    let indices = u64::from_le_bytes([block[2], block[3], block[4], block[5], block[6], block[7], 0, 0]);
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        pixel[3] = alphas[((indices >> (index * 3)) & 7) as usize] as u8;
+    }
+}
+
+/*  This function expands a 5:6:5 color to 8 bits per channel.
+*/
+fn expand_565(color: u16) -> [u32; 3] {
+    let (red, green, blue) = ((color >> 11) as u32 & 31, (color >> 5) as u32 & 63, color as u32 & 31);
+
+    [red << 3 | red >> 2, green << 2 | green >> 4, blue << 3 | blue >> 2]
+}
+
+/*  This function decodes a BC7 block.
+*   The mode is the number of zeros before the first set bit. The bits are read from the lowest one.
+*/
+fn decode_bc7(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    let mut bits = [0; 16];
+    bits.copy_from_slice(&block[..16]);
+    let mut reader = BitReader { bits: u128::from_le_bytes(bits), position: 0 };
+
+    let mode_number = block[0].trailing_zeros() as usize;
+    if mode_number >= BC7_MODES.len() {
+        //the reserved mode is decoded as transparent black.
+        *pixels = [[0; 4]; 16];
+        return;
+    }
+    let mode = &BC7_MODES[mode_number];
+    reader.read(mode_number as u32 + 1);
+
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    //the endpoints are read channel by channel: all the reds, then the greens, the blues and the alphas.
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..4 {
+        let channel_bits = if channel < 3 { mode.color_bits } else { mode.alpha_bits };
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = reader.read(channel_bits);
+        }
+    }
+
+    let mut p_bits = [0u32; 6];
+    if mode.endpoint_p_bits {
+        for p_bit in p_bits.iter_mut().take(endpoint_count) {
+            *p_bit = reader.read(1);
+        }
+    } else if mode.shared_p_bits {
+        for subset in 0..mode.subsets {
+            let p_bit = reader.read(1);
+            p_bits[subset * 2] = p_bit;
+            p_bits[subset * 2 + 1] = p_bit;
+        }
+    }
+
+    let has_p_bits = mode.endpoint_p_bits || mode.shared_p_bits;
+    for (endpoint, p_bit) in endpoints.iter_mut().zip(p_bits.iter()).take(endpoint_count) {
+        for (channel, value) in endpoint.iter_mut().enumerate() {
+            let channel_bits = if channel < 3 { mode.color_bits } else { mode.alpha_bits };
+            *value = if channel_bits == 0 {
+                255
+            } else if has_p_bits {
+                expand_bits(*value << 1 | p_bit, channel_bits + 1)
+            } else {
+                expand_bits(*value, channel_bits)
+            };
+        }
+    }
+
+    let subsets = match mode.subsets {
+        2 => BC7_PARTITIONS_2[partition],
+        3 => BC7_PARTITIONS_3[partition],
+        _ => [0; 16],
+    };
+    let is_anchor = |pixel: usize| match mode.subsets {
+        2 => pixel == 0 || pixel == BC7_ANCHORS_2[partition],
+        3 => pixel == 0 || pixel == BC7_ANCHORS_3_SECOND[partition] || pixel == BC7_ANCHORS_3_THIRD[partition],
+        _ => pixel == 0,
+    };
+
+    let mut indices = [0u32; 16];
+    for (pixel, index) in indices.iter_mut().enumerate() {
+        *index = reader.read(mode.index_bits - is_anchor(pixel) as u32);
+    }
+    let mut secondary_indices = [0u32; 16];
+    if mode.secondary_index_bits > 0 {
+        for (pixel, index) in secondary_indices.iter_mut().enumerate() {
+            *index = reader.read(mode.secondary_index_bits - (pixel == 0) as u32);
+        }
+    }
+
+    for (pixel_index, pixel) in pixels.iter_mut().enumerate() {
+        let subset = subsets[pixel_index] as usize;
+        let (endpoint0, endpoint1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+
+        //the modes 4 and 5 have a second set of indices for the alpha (or for the color, if the index selection is set).
+        let (color_index, color_bits, alpha_index, alpha_bits) = if mode.secondary_index_bits == 0 {
+            (indices[pixel_index], mode.index_bits, indices[pixel_index], mode.index_bits)
+        } else if index_selection == 0 {
+            (indices[pixel_index], mode.index_bits, secondary_indices[pixel_index], mode.secondary_index_bits)
+        } else {
+            (secondary_indices[pixel_index], mode.secondary_index_bits, indices[pixel_index], mode.index_bits)
+        };
+
+        for channel in 0..4 {
+            let (index, index_bits) = if channel < 3 { (color_index, color_bits) } else { (alpha_index, alpha_bits) };
+            let weight = match index_bits {
+                2 => BC7_WEIGHTS_2[index as usize],
+                3 => BC7_WEIGHTS_3[index as usize],
+                _ => BC7_WEIGHTS_4[index as usize],
+            };
+            pixel[channel] = (((64 - weight) * endpoint0[channel] + weight * endpoint1[channel] + 32) >> 6) as u8;
+        }
+
+        match rotation {
+            1 => pixel.swap(0, 3),
+            2 => pixel.swap(1, 3),
+            3 => pixel.swap(2, 3),
+            _ => {},
+        }
+    }
+}
+
+/*  This struct reads the bits of a BC7 block, from the lowest one.
+*/
+struct BitReader {
+    bits: u128,
+    position: u32,
+}
+
+impl BitReader {
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+
+        let value = (self.bits >> self.position) as u32 & ((1 << count) - 1);
+        self.position += count;
+
+        value
+    }
+}
+
+/*  This function expands a value of some bits to 8 bits, repeating the highest bits in the lowest ones.
+*/
+fn expand_bits(value: u32, bits: u32) -> u32 {
+    let value = value << (8 - bits);
+
+    value | value >> bits
+}
+
+/*  This function decodes an ETC2 rgb block (or the color of an ETC2 EAC block).
+*   With punchthrough the differential bit is the opaque bit: when it is not set some pixels are transparent and the individual mode is not available.
+*   The pixel indices are stored by column, the bits are numbered from the highest one of the first byte.
+*/
+fn decode_etc2(block: &[u8], pixels: &mut [[u8; 4]; 16], punchthrough: bool) {
+    let bits = u64::from_be_bytes([block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7]]);
+    let get = |high: u32, count: u32| ((bits >> (high + 1 - count)) & ((1 << count) - 1)) as i32;
+
+    let differential = punchthrough || get(33, 1) == 1;
+    let opaque = !punchthrough || get(33, 1) == 1;
+    let pixel_index = |x: usize, y: usize| {
+        let bit = (x * 4 + y) as u32;
+        (get(bit + 16, 1) << 1 | get(bit, 1)) as usize
+    };
+
+    if differential {
+        let (red, green, blue) = (get(63, 5), get(55, 5), get(47, 5));
+        let (red2, green2, blue2) = (red + sign_extend_3(get(58, 3)), green + sign_extend_3(get(50, 3)), blue + sign_extend_3(get(42, 3)));
+
+        if !(0..32).contains(&red2) {
+            return decode_etc2_t(&get, &pixel_index, pixels, opaque);
+        }
+        if !(0..32).contains(&green2) {
+            return decode_etc2_h(&get, &pixel_index, pixels, opaque);
+        }
+        if !(0..32).contains(&blue2) {
+            return decode_etc2_planar(&get, pixels);
+        }
+
+        let expand_5 = |value: i32| value << 3 | value >> 2;
+        let base_colors = [[expand_5(red), expand_5(green), expand_5(blue)], [expand_5(red2), expand_5(green2), expand_5(blue2)]];
+        decode_etc_subblocks(&get, &pixel_index, pixels, base_colors, opaque);
+    } else {
+        let base_colors = [[get(63, 4) * 17, get(55, 4) * 17, get(47, 4) * 17], [get(59, 4) * 17, get(51, 4) * 17, get(43, 4) * 17]];
+        decode_etc_subblocks(&get, &pixel_index, pixels, base_colors, true);
+    }
+}
+
+/*  This function decodes the two sub-blocks of the individual and differential modes.
+*   Without the opaque bit the modifier of the indices 0 and 2 is 0 and the index 2 is transparent.
+*/
+fn decode_etc_subblocks(get: &dyn Fn(u32, u32) -> i32, pixel_index: &dyn Fn(usize, usize) -> usize, pixels: &mut [[u8; 4]; 16], base_colors: [[i32; 3]; 2], opaque: bool) {
+    let tables = [ETC_MODIFIERS[get(39, 3) as usize], ETC_MODIFIERS[get(36, 3) as usize]];
+    let flip = get(32, 1) == 1;
+
+    for y in 0..4 {
+        for x in 0..4 {
+            let subblock = if flip { (y >= 2) as usize } else { (x >= 2) as usize };
+            let index = pixel_index(x, y);
+            pixels[y * 4 + x] = if !opaque && index == 2 {
+                [0, 0, 0, 0]
+            } else {
+                let modifier = if !opaque && index == 0 { 0 } else { tables[subblock][index] };
+                let [red, green, blue] = base_colors[subblock].map(|channel| clamp_u8(channel + modifier));
+                [red, green, blue, 255]
+            };
+        }
+    }
+}
+
+/*  This function decodes the T mode of ETC2: one color and three colors around a second one.
+*/
+fn decode_etc2_t(get: &dyn Fn(u32, u32) -> i32, pixel_index: &dyn Fn(usize, usize) -> usize, pixels: &mut [[u8; 4]; 16], opaque: bool) {
+    let color1 = [get(60, 2) << 2 | get(57, 2), get(55, 4), get(51, 4)].map(|channel| channel * 17);
+    let color2 = [get(47, 4), get(43, 4), get(39, 4)].map(|channel| channel * 17);
+    let distance = ETC_DISTANCES[(get(35, 2) << 1 | get(32, 1)) as usize];
+
+    let paint_colors = [color1, color2.map(|channel| channel + distance), color2, color2.map(|channel| channel - distance)];
+    decode_etc_paint_colors(pixel_index, pixels, paint_colors, opaque);
+}
+
+/*  This function decodes the H mode of ETC2: two colors, each one plus and minus a distance.
+*/
+fn decode_etc2_h(get: &dyn Fn(u32, u32) -> i32, pixel_index: &dyn Fn(usize, usize) -> usize, pixels: &mut [[u8; 4]; 16], opaque: bool) {
+    let color1 = [get(62, 4), get(58, 3) << 1 | get(52, 1), get(51, 1) << 3 | get(49, 3)];
+    let color2 = [get(46, 4), get(43, 4), get(38, 4)];
+    let value1 = color1[0] << 8 | color1[1] << 4 | color1[2];
+    let value2 = color2[0] << 8 | color2[1] << 4 | color2[2];
+    let distance = ETC_DISTANCES[(get(34, 1) << 2 | get(32, 1) << 1 | (value1 >= value2) as i32) as usize];
+
+    let (color1, color2) = (color1.map(|channel| channel * 17), color2.map(|channel| channel * 17));
+    let paint_colors = [color1.map(|channel| channel + distance), color1.map(|channel| channel - distance), color2.map(|channel| channel + distance), color2.map(|channel| channel - distance)];
+    decode_etc_paint_colors(pixel_index, pixels, paint_colors, opaque);
+}
+
+/*  This function writes the pixels of the T and H modes. Without the opaque bit the index 2 is transparent.
+*/
+fn decode_etc_paint_colors(pixel_index: &dyn Fn(usize, usize) -> usize, pixels: &mut [[u8; 4]; 16], paint_colors: [[i32; 3]; 4], opaque: bool) {
+    for y in 0..4 {
+        for x in 0..4 {
+            let index = pixel_index(x, y);
+            pixels[y * 4 + x] = if !opaque && index == 2 {
+                [0, 0, 0, 0]
+            } else {
+                let [red, green, blue] = paint_colors[index].map(clamp_u8);
+                [red, green, blue, 255]
+            };
+        }
+    }
+}
+
+/*  This function decodes the planar mode of ETC2: a gradient between the colors of the origin, of the right and of the bottom.
+*/
+fn decode_etc2_planar(get: &dyn Fn(u32, u32) -> i32, pixels: &mut [[u8; 4]; 16]) {
+    let expand_6 = |value: i32| value << 2 | value >> 4;
+    let expand_7 = |value: i32| value << 1 | value >> 6;
+
+    let origin = [expand_6(get(62, 6)), expand_7(get(56, 1) << 6 | get(54, 6)), expand_6(get(48, 1) << 5 | get(44, 2) << 3 | get(41, 3))];
+    let horizontal = [expand_6(get(38, 5) << 1 | get(32, 1)), expand_7(get(31, 7)), expand_6(get(24, 6))];
+    let vertical = [expand_6(get(18, 6)), expand_7(get(12, 7)), expand_6(get(5, 6))];
+
+    for y in 0..4 {
+        for x in 0..4 {
+            let mut pixel = [0, 0, 0, 255];
+            for channel in 0..3 {
+                let value = (x as i32 * (horizontal[channel] - origin[channel]) + y as i32 * (vertical[channel] - origin[channel]) + 4 * origin[channel] + 2) >> 2;
+                pixel[channel] = clamp_u8(value);
+            }
+            pixels[y * 4 + x] = pixel;
+        }
+    }
+}
+
+/*  This function decodes an ETC2 EAC block: an EAC alpha followed by an ETC2 color.
+*/
+fn decode_etc2_eac(block: &[u8], pixels: &mut [[u8; 4]; 16]) {
+    decode_etc2(&block[8..], pixels, false);
+
+    let bits = u64::from_be_bytes([block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7]]);
+    let base = (bits >> 56) as i32;
+    let multiplier = (bits >> 52 & 15) as i32;
+    let modifiers = EAC_MODIFIERS[(bits >> 48 & 15) as usize];
+
+    for y in 0..4 {
+        for x in 0..4 {
+            let index = (bits >> (45 - (x * 4 + y) * 3) & 7) as usize;
+            pixels[y * 4 + x][3] = clamp_u8(base + modifiers[index] * multiplier);
+        }
+    }
+}
+
+/*  This function reads a signed 3 bit number.
+*/
+fn sign_extend_3(value: i32) -> i32 {
+    if value >= 4 { value - 8 } else { value }
+}
+
+fn clamp_u8(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*  This function decompresses a single block and returns its 16 pixels.
+    */
+    fn decode(format: CompressedFormat, block: &[u8]) -> Vec::<[u8; 4]> {
+        decompress(format, 4, 4, block).unwrap().chunks_exact(4).map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]]).collect()
+    }
+
+    #[test]
+    fn bc1_four_colors() {
+        //red and blue, the first four pixels use the indices 0, 1, 2 and 3.
+        let pixels = decode(CompressedFormat::Bc1, &[0x00, 0xF8, 0x1F, 0x00, 0xE4, 0x00, 0x00, 0x00]);
+
+        assert_eq!(pixels[0], [255, 0, 0, 255]);
+        assert_eq!(pixels[1], [0, 0, 255, 255]);
+        assert_eq!(pixels[2], [170, 0, 85, 255]);
+        assert_eq!(pixels[3], [85, 0, 170, 255]);
+        assert!(pixels[4..].iter().all(|pixel| *pixel == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn bc1_three_colors_and_transparent() {
+        let block = [0x1F, 0x00, 0x00, 0xF8, 0xE4, 0x00, 0x00, 0x00];
+
+        let pixels = decode(CompressedFormat::Bc1Alpha, &block);
+        assert_eq!(pixels[0], [0, 0, 255, 255]);
+        assert_eq!(pixels[1], [255, 0, 0, 255]);
+        assert_eq!(pixels[2], [127, 0, 127, 255]);
+        assert_eq!(pixels[3], [0, 0, 0, 0]);
+
+        assert_eq!(decode(CompressedFormat::Bc1, &block)[3], [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn bc3_alphas() {
+        //white, the first four pixels use the alpha indices 0, 1, 2 and 7.
+        let color = [0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let mut block = vec![70, 0, 0x88, 0x0E, 0x00, 0x00, 0x00, 0x00];
+        block.extend(color);
+
+        let pixels = decode(CompressedFormat::Bc3, &block);
+        assert_eq!(pixels[..4], [[255, 255, 255, 70], [255, 255, 255, 0], [255, 255, 255, 60], [255, 255, 255, 10]]);
+        assert!(pixels[4..].iter().all(|pixel| *pixel == [255, 255, 255, 70]));
+
+        //with alpha0 <= alpha1 there are 6 alphas, 0 and 255.
+        block[0] = 0;
+        block[1] = 50;
+        let pixels = decode(CompressedFormat::Bc3, &block);
+        assert_eq!(pixels[..4].iter().map(|pixel| pixel[3]).collect::<Vec::<u8>>(), vec![0, 50, 10, 255]);
+    }
+
+    #[test]
+    fn etc2_differential_block() {
+        //a gray of 132 with the modifiers +2, +8, -2 and -8: (1, 0) has the index 1, (0, 1) the index 2 and (0, 2) the index 3.
+        let pixels = decode(CompressedFormat::Etc2, &[0x80, 0x80, 0x80, 0x02, 0x00, 0x06, 0x00, 0x14]);
+
+        assert_eq!(pixels[0], [134, 134, 134, 255]);
+        assert_eq!(pixels[1], [140, 140, 140, 255]);
+        assert_eq!(pixels[4], [130, 130, 130, 255]);
+        assert_eq!(pixels[8], [124, 124, 124, 255]);
+        assert_eq!(pixels[15], [134, 134, 134, 255]);
+    }
+
+    #[test]
+    fn etc2_eac_alpha() {
+        //a base of 128 with the multiplier 1: the first pixel has the index 3 (-15), the others the index 4 (+2).
+        let mut block = vec![128, 0x10, 0x72, 0x49, 0x24, 0x92, 0x49, 0x24];
+        block.extend([0x80, 0x80, 0x80, 0x02, 0x00, 0x00, 0x00, 0x00]);
+
+        let pixels = decode(CompressedFormat::Etc2Eac, &block);
+        assert_eq!(pixels[0], [134, 134, 134, 113]);
+        assert!(pixels[1..].iter().all(|pixel| *pixel == [134, 134, 134, 130]));
+    }
+
+    #[test]
+    fn images_are_cropped_and_checked() {
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0x00, 0x00, 0x00];
+
+        assert_eq!(decompress(CompressedFormat::Bc1, 2, 1, &block), Some(vec![255, 0, 0, 255, 0, 0, 255, 255]));
+        assert_eq!(decompress(CompressedFormat::Bc1, 8, 4, &block), None);
+        assert_eq!(decompress(CompressedFormat::Bc4, 4, 4, &block), None);
+        assert_eq!(decompress(CompressedFormat::Bc1, 0xFFFFFFFF, 0xFFFFFFFF, &block), None);
+    }
+
+    #[test]
+    fn formats_without_fallback_are_reported() {
+        assert!(is_decompressible(CompressedFormat::Bc7));
+        assert!(!is_decompressible(CompressedFormat::Bc6hUnsigned));
+        assert!(!is_decompressible(CompressedFormat::Astc { block_width: 4, block_height: 4 }));
+    }
+}
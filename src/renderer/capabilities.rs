@@ -0,0 +1,113 @@
+/*  File: renderer/capabilities.rs
+*   Author: Vicix
+*
+*   This file contains the functions that check what the opengl context supports.
+*   A feature is available if the context version is high enough or if the driver exposes the extension that adds it.
+*   They must be called after the opengl functions have been loaded.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::spirv;
+use crate::renderer::compressedImage::CompressedFormat;
+use crate::renderer::sampler;
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/*  This function returns the major and minor version of the context.
+*/
+pub fn get_gl_version() -> (GLint, GLint) {
+    let mut major = 0;
+    let mut minor = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+
+    (major, minor)
+}
+
+/*  This function checks if the driver exposes an extension (for example "GL_ARB_compute_shader").
+*/
+pub fn has_extension(extension: &str) -> bool {
+    let mut extension_count = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count);
+    }
+
+    (0..extension_count.max(0) as GLuint).any(|index| unsafe {
+        let name = gl::GetStringi(gl::EXTENSIONS, index);
+        !name.is_null() && CStr::from_ptr(name as *const c_char).to_bytes() == extension.as_bytes()
+    })
+}
+
+/*  This function checks if the context is at least major.minor or if it exposes the extension.
+*/
+pub fn has_version_or_extension(major: GLint, minor: GLint, extension: &str) -> bool {
+    get_gl_version() >= (major, minor) || has_extension(extension)
+}
+
+/*  This function checks if compute shaders can be used (opengl 4.3 or ARB_compute_shader).
+*/
+pub fn is_compute_supported() -> bool {
+    gl::DispatchCompute::is_loaded() && has_version_or_extension(4, 3, "GL_ARB_compute_shader")
+}
+
+/*  This function checks if separable programs and program pipelines can be used (opengl 4.1 or ARB_separate_shader_objects).
+*/
+pub fn is_separate_shader_supported() -> bool {
+    gl::BindProgramPipeline::is_loaded() && has_version_or_extension(4, 1, "GL_ARB_separate_shader_objects")
+}
+
+/*  This function checks if shader storage buffers can be used (opengl 4.3 or ARB_shader_storage_buffer_object).
+*/
+pub fn is_storage_buffer_supported() -> bool {
+    has_version_or_extension(4, 3, "GL_ARB_shader_storage_buffer_object")
+}
+
+/*  This function checks if spir-v shaders can be loaded (opengl 4.6 or ARB_gl_spirv).
+*   glSpecializeShader must have been loaded with spirv::load_with.
+*/
+pub fn is_spirv_supported() -> bool {
+    spirv::is_loaded() && has_version_or_extension(4, 6, "GL_ARB_gl_spirv")
+}
+
+/*  This function checks if a compressed format can be given to opengl without decompressing it.
+*   BC4 and BC5 (RGTC) are core since opengl 3.0, BC6H and BC7 (BPTC) since 4.2 and ETC2/EAC since 4.3; BC1-BC3 (S3TC) and ASTC are only extensions.
+*/
+pub fn is_compressed_format_supported(format: CompressedFormat, srgb: bool) -> bool {
+    match format {
+        CompressedFormat::Bc1 | CompressedFormat::Bc1Alpha | CompressedFormat::Bc2 | CompressedFormat::Bc3 => {
+            has_extension("GL_EXT_texture_compression_s3tc")
+                && (!srgb || has_extension("GL_EXT_texture_sRGB") || has_extension("GL_EXT_texture_compression_s3tc_srgb"))
+        },
+        CompressedFormat::Bc4 | CompressedFormat::Bc4Signed | CompressedFormat::Bc5 | CompressedFormat::Bc5Signed => true,
+        CompressedFormat::Bc6hUnsigned | CompressedFormat::Bc6hSigned | CompressedFormat::Bc7 => has_version_or_extension(4, 2, "GL_ARB_texture_compression_bptc"),
+        CompressedFormat::Etc2 | CompressedFormat::Etc2Alpha1 | CompressedFormat::Etc2Eac
+            | CompressedFormat::EacR11 | CompressedFormat::EacR11Signed | CompressedFormat::EacRg11 | CompressedFormat::EacRg11Signed => has_version_or_extension(4, 3, "GL_ARB_ES3_compatibility"),
+        CompressedFormat::Astc { .. } => has_extension("GL_KHR_texture_compression_astc_ldr"),
+    }
+}
+
+/*  This function checks if the anisotropic filter can be used (opengl 4.6, EXT_texture_filter_anisotropic or ARB_texture_filter_anisotropic).
+*/
+pub fn is_anisotropy_supported() -> bool {
+    has_version_or_extension(4, 6, "GL_EXT_texture_filter_anisotropic") || has_extension("GL_ARB_texture_filter_anisotropic")
+}
+
+/*  This function returns the maximum anisotropy of the driver, or 1 if the anisotropic filter is not supported.
+*/
+pub fn get_max_anisotropy() -> f32 {
+    if !is_anisotropy_supported() {
+        return 1.0;
+    }
+
+    let mut max_anisotropy = 1.0;
+    unsafe {
+        gl::GetFloatv(sampler::MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+    }
+
+    max_anisotropy
+}
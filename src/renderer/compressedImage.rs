@@ -0,0 +1,596 @@
+/*  File: renderer/compressedImage.rs
+*   Author: Vicix
+*
+*   This file contains the CompressedImage class.
+*   The class CompressedImage is an image already compressed for the gpu (BC1-BC7, ETC2/EAC or ASTC), read from a KTX2 or a DDS file with all its mip levels.
+*   The compressed blocks are given to opengl as they are (see TextureTrait::upload_compressed), so nothing is decoded at startup and the texture uses less video memory.
+*   The files are parsed without opengl, only 2d textures are supported (no cubemaps, arrays or 3d textures) and the KTX2 supercompression is not supported.
+*   Note: the compressed images cannot be flipped, so they must be stored in the orientation used by opengl.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::errors::TextureError;
+
+use std::fs;
+use std::path::Path;
+
+/*  These are the opengl constants of the compressed formats that are extensions (S3TC and ASTC), missing in the gl crate.
+*/
+pub const COMPRESSED_RGB_S3TC_DXT1: GLenum = 0x83F0;
+pub const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+pub const COMPRESSED_RGBA_S3TC_DXT3: GLenum = 0x83F2;
+pub const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+pub const COMPRESSED_SRGB_S3TC_DXT1: GLenum = 0x8C4C;
+pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: GLenum = 0x8C4D;
+pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: GLenum = 0x8C4E;
+pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+pub const COMPRESSED_RGBA_ASTC_4X4: GLenum = 0x93B0;
+pub const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: GLenum = 0x93D0;
+
+/*  These are the block sizes of ASTC, in the order of the opengl constants (COMPRESSED_RGBA_ASTC_4X4 is the first one).
+*/
+pub const ASTC_BLOCK_SIZES: [(u32, u32); 14] = [(4, 4), (5, 4), (5, 5), (6, 5), (6, 6), (8, 5), (8, 6), (8, 8), (10, 5), (10, 6), (10, 8), (10, 10), (12, 10), (12, 12)];
+
+/*  These are the identifiers at the start of the files.
+*/
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/*  These are the sizes of the headers.
+*/
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_SIZE: usize = 24;
+const DDS_HEADER_SIZE: usize = 128;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+
+/*  This is the biggest width or height accepted, the max texture size of most gpus.
+*   The size is read from the file, so it is limited before the byte counts are computed.
+*/
+const MAX_TEXTURE_SIZE: u32 = 16384;
+
+/*  These are the flags of the DDS header used here.
+*/
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/*  This enum lists the compressed formats.
+*   Every format is made of blocks of pixels (4x4, or the block size for ASTC) with a fixed size in bytes.
+*   Bc1Alpha is BC1 with the transparent pixels, Etc2Alpha1 is ETC2 with the transparent pixels (punchthrough) and Etc2Eac is ETC2 with an EAC alpha.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressedFormat {
+    Bc1,
+    Bc1Alpha,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc4Signed,
+    Bc5,
+    Bc5Signed,
+    Bc6hUnsigned,
+    Bc6hSigned,
+    Bc7,
+    Etc2,
+    Etc2Alpha1,
+    Etc2Eac,
+    EacR11,
+    EacR11Signed,
+    EacRg11,
+    EacRg11Signed,
+    Astc {
+        block_width: u32,
+        block_height: u32,
+    },
+}
+
+impl CompressedFormat {
+    /*  This function returns the width and the height of a block in pixels.
+    */
+    pub fn get_block_size(&self) -> (u32, u32) {
+        match self {
+            CompressedFormat::Astc { block_width, block_height } => (*block_width, *block_height),
+            _ => (4, 4),
+        }
+    }
+
+    /*  This function returns the size of a block in bytes.
+    */
+    pub fn get_block_bytes(&self) -> usize {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc1Alpha | CompressedFormat::Bc4 | CompressedFormat::Bc4Signed
+                | CompressedFormat::Etc2 | CompressedFormat::Etc2Alpha1 | CompressedFormat::EacR11 | CompressedFormat::EacR11Signed => 8,
+            _ => 16,
+        }
+    }
+
+    /*  This function returns the size in bytes of an image of this format, or None if it overflows.
+    */
+    pub fn get_image_bytes(&self, width: u32, height: u32) -> Option<usize> {
+        let (block_width, block_height) = self.get_block_size();
+
+        (width.div_ceil(block_width) as usize).checked_mul(height.div_ceil(block_height) as usize)?.checked_mul(self.get_block_bytes())
+    }
+
+    /*  This function returns the opengl internal format.
+    *   srgb is ignored by the formats without an srgb version (BC4, BC5, BC6H and EAC).
+    */
+    pub fn get_gl_format(&self, srgb: bool) -> GLenum {
+        match (self, srgb) {
+            (CompressedFormat::Bc1, false) => COMPRESSED_RGB_S3TC_DXT1,
+            (CompressedFormat::Bc1, true) => COMPRESSED_SRGB_S3TC_DXT1,
+            (CompressedFormat::Bc1Alpha, false) => COMPRESSED_RGBA_S3TC_DXT1,
+            (CompressedFormat::Bc1Alpha, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+            (CompressedFormat::Bc2, false) => COMPRESSED_RGBA_S3TC_DXT3,
+            (CompressedFormat::Bc2, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+            (CompressedFormat::Bc3, false) => COMPRESSED_RGBA_S3TC_DXT5,
+            (CompressedFormat::Bc3, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+            (CompressedFormat::Bc4, _) => gl::COMPRESSED_RED_RGTC1,
+            (CompressedFormat::Bc4Signed, _) => gl::COMPRESSED_SIGNED_RED_RGTC1,
+            (CompressedFormat::Bc5, _) => gl::COMPRESSED_RG_RGTC2,
+            (CompressedFormat::Bc5Signed, _) => gl::COMPRESSED_SIGNED_RG_RGTC2,
+            (CompressedFormat::Bc6hUnsigned, _) => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            (CompressedFormat::Bc6hSigned, _) => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+            (CompressedFormat::Bc7, false) => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            (CompressedFormat::Bc7, true) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            (CompressedFormat::Etc2, false) => gl::COMPRESSED_RGB8_ETC2,
+            (CompressedFormat::Etc2, true) => gl::COMPRESSED_SRGB8_ETC2,
+            (CompressedFormat::Etc2Alpha1, false) => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (CompressedFormat::Etc2Alpha1, true) => gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (CompressedFormat::Etc2Eac, false) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            (CompressedFormat::Etc2Eac, true) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            (CompressedFormat::EacR11, _) => gl::COMPRESSED_R11_EAC,
+            (CompressedFormat::EacR11Signed, _) => gl::COMPRESSED_SIGNED_R11_EAC,
+            (CompressedFormat::EacRg11, _) => gl::COMPRESSED_RG11_EAC,
+            (CompressedFormat::EacRg11Signed, _) => gl::COMPRESSED_SIGNED_RG11_EAC,
+            (CompressedFormat::Astc { block_width, block_height }, srgb) => {
+                let index = ASTC_BLOCK_SIZES.iter().position(|size| *size == (*block_width, *block_height)).unwrap_or(0) as GLenum;
+                if srgb { COMPRESSED_SRGB8_ALPHA8_ASTC_4X4 + index } else { COMPRESSED_RGBA_ASTC_4X4 + index }
+            },
+        }
+    }
+}
+
+/*  This is the declaration of the class.
+*   levels contains the compressed blocks of every mip level, from the biggest (level 0, width x height) to the smallest.
+*   srgb is true if the colors are in srgb.
+*/
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: CompressedFormat,
+    pub srgb: bool,
+    pub levels: Vec::<Vec::<u8>>,
+}
+
+#[allow(dead_code)]
+impl CompressedImage {
+    /*  This constructor reads a KTX2 or a DDS file. The container is found from the start of the file.
+    *   It returns TextureError::Io if the file cannot be read, TextureError::UnsupportedFormat if the container or its format is not supported
+    *   and TextureError::Decode if the file is corrupted.
+    */
+    pub fn load(path: &str) -> Result<CompressedImage, TextureError> {
+        let bytes = fs::read(path).map_err(|error| TextureError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+
+        CompressedImage::parse(&bytes, path)
+    }
+
+    /*  This constructor parses the bytes of a KTX2 or a DDS file. path is only used in the errors.
+    */
+    pub fn parse(bytes: &[u8], path: &str) -> Result<CompressedImage, TextureError> {
+        if bytes.starts_with(&KTX2_IDENTIFIER) {
+            parse_ktx2(bytes, path)
+        } else if bytes.starts_with(&DDS_MAGIC) {
+            parse_dds(bytes, path)
+        } else {
+            Err(unsupported(path, "The file is not a KTX2 or a DDS file."))
+        }
+    }
+
+    /*  This function returns the size of a mip level in pixels.
+    */
+    pub fn get_level_size(&self, level: usize) -> (u32, u32) {
+        (get_mip_size(self.width, level), get_mip_size(self.height, level))
+    }
+}
+
+/*  This function checks if a file is a compressed texture from its extension (.ktx2 or .dds).
+*/
+pub fn is_compressed_file(path: &str) -> bool {
+    Path::new(path).extension().and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ktx2") || extension.eq_ignore_ascii_case("dds"))
+}
+
+/*  This function parses a KTX2 file.
+*   The header is followed by the index of the levels (offset and size of every level). The data format descriptor and the key/value data are ignored.
+*/
+pub fn parse_ktx2(bytes: &[u8], path: &str) -> Result<CompressedImage, TextureError> {
+    if bytes.len() < KTX2_HEADER_SIZE || !bytes.starts_with(&KTX2_IDENTIFIER) {
+        return Err(corrupted(path, "The KTX2 header is incomplete."));
+    }
+
+    let vk_format = read_u32(bytes, 12);
+    let width = read_u32(bytes, 20);
+    let height = read_u32(bytes, 24);
+    let depth = read_u32(bytes, 28);
+    let layer_count = read_u32(bytes, 32);
+    let face_count = read_u32(bytes, 36);
+    let level_count = read_u32(bytes, 40).max(1) as usize;
+    let supercompression = read_u32(bytes, 44);
+
+    if depth > 1 || layer_count > 1 || face_count > 1 {
+        return Err(unsupported(path, "Only the 2d KTX2 textures are supported (no cubemaps, arrays or 3d textures)."));
+    }
+    if supercompression != 0 {
+        return Err(unsupported(path, &format!("The KTX2 supercompression {} is not supported.", supercompression)));
+    }
+    let (format, srgb) = get_vk_format(vk_format).ok_or_else(|| unsupported(path, &format!("The KTX2 format {} is not a supported compressed format.", vk_format)))?;
+    check_size(width, height, path)?;
+    check_level_count(width, height, level_count, path)?;
+
+    let levels = (0..level_count).map(|level| {
+        let index = KTX2_HEADER_SIZE + level * KTX2_LEVEL_SIZE;
+        if index + KTX2_LEVEL_SIZE > bytes.len() {
+            return Err(corrupted(path, "The KTX2 level index is incomplete."));
+        }
+
+        let offset = read_u64(bytes, index) as usize;
+        let size = read_u64(bytes, index + 8) as usize;
+        let expected_size = format.get_image_bytes(get_mip_size(width, level), get_mip_size(height, level)).ok_or_else(|| corrupted(path, "The image is too big."))?;
+        if size != expected_size {
+            return Err(corrupted(path, &format!("The level {} has {} bytes instead of {}.", level, size, expected_size)));
+        }
+
+        get_range(bytes, offset, size).map(|data| data.to_vec()).ok_or_else(|| corrupted(path, &format!("The level {} is outside the file.", level)))
+    }).collect::<Result<Vec::<Vec::<u8>>, TextureError>>()?;
+
+    Ok(CompressedImage {
+        width,
+        height,
+        format,
+        srgb,
+        levels,
+    })
+}
+
+/*  This function parses a DDS file.
+*   The format is a four character code in the pixel format of the header (DXT1, DXT3, DXT5, ATI1, ATI2...) or a dxgi format in the DX10 header.
+*   The levels follow the header one after the other, from the biggest.
+*/
+pub fn parse_dds(bytes: &[u8], path: &str) -> Result<CompressedImage, TextureError> {
+    if bytes.len() < DDS_HEADER_SIZE || !bytes.starts_with(&DDS_MAGIC) {
+        return Err(corrupted(path, "The DDS header is incomplete."));
+    }
+
+    let flags = read_u32(bytes, 8);
+    let height = read_u32(bytes, 12);
+    let width = read_u32(bytes, 16);
+    let mip_map_count = read_u32(bytes, 28);
+    let pixel_format_flags = read_u32(bytes, 80);
+    let four_cc = &bytes[84..88];
+    let caps2 = read_u32(bytes, 112);
+
+    let level_count = if flags & DDSD_MIPMAPCOUNT != 0 { mip_map_count.max(1) as usize } else { 1 };
+    if caps2 & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 {
+        return Err(unsupported(path, "Only the 2d DDS textures are supported (no cubemaps or 3d textures)."));
+    }
+    if pixel_format_flags & DDPF_FOURCC == 0 {
+        return Err(unsupported(path, "The DDS file is not compressed."));
+    }
+
+    let (format, srgb, mut offset) = if four_cc == b"DX10" {
+        if bytes.len() < DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE {
+            return Err(corrupted(path, "The DX10 header is incomplete."));
+        }
+        let dxgi_format = read_u32(bytes, 128);
+        if read_u32(bytes, 136) & DDS_RESOURCE_MISC_TEXTURECUBE != 0 || read_u32(bytes, 140) > 1 {
+            return Err(unsupported(path, "Only the 2d DDS textures are supported (no cubemaps or arrays)."));
+        }
+        let (format, srgb) = get_dxgi_format(dxgi_format).ok_or_else(|| unsupported(path, &format!("The dxgi format {} is not a supported compressed format.", dxgi_format)))?;
+
+        (format, srgb, DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE)
+    } else {
+        let format = get_four_cc_format(four_cc).ok_or_else(|| unsupported(path, &format!("The DDS format {} is not supported.", String::from_utf8_lossy(four_cc))))?;
+
+        (format, false, DDS_HEADER_SIZE)
+    };
+
+    check_size(width, height, path)?;
+    check_level_count(width, height, level_count, path)?;
+
+    let mut levels = Vec::<Vec::<u8>>::with_capacity(level_count);
+    for level in 0..level_count {
+        let size = format.get_image_bytes(get_mip_size(width, level), get_mip_size(height, level)).ok_or_else(|| corrupted(path, "The image is too big."))?;
+        let data = get_range(bytes, offset, size).ok_or_else(|| corrupted(path, &format!("The level {} is outside the file.", level)))?;
+        levels.push(data.to_vec());
+        offset += size;
+    }
+
+    Ok(CompressedImage {
+        width,
+        height,
+        format,
+        srgb,
+        levels,
+    })
+}
+
+/*  This function returns the format of a KTX2 vkFormat and if it is srgb.
+*   The vulkan formats of the compressed images go from VK_FORMAT_BC1_RGB_UNORM_BLOCK (131) to VK_FORMAT_ASTC_12x12_SRGB_BLOCK (184).
+*/
+fn get_vk_format(vk_format: u32) -> Option<(CompressedFormat, bool)> {
+    let format = match vk_format {
+        131 | 132 => CompressedFormat::Bc1,
+        133 | 134 => CompressedFormat::Bc1Alpha,
+        135 | 136 => CompressedFormat::Bc2,
+        137 | 138 => CompressedFormat::Bc3,
+        139 => CompressedFormat::Bc4,
+        140 => CompressedFormat::Bc4Signed,
+        141 => CompressedFormat::Bc5,
+        142 => CompressedFormat::Bc5Signed,
+        143 => CompressedFormat::Bc6hUnsigned,
+        144 => CompressedFormat::Bc6hSigned,
+        145 | 146 => CompressedFormat::Bc7,
+        147 | 148 => CompressedFormat::Etc2,
+        149 | 150 => CompressedFormat::Etc2Alpha1,
+        151 | 152 => CompressedFormat::Etc2Eac,
+        153 => CompressedFormat::EacR11,
+        154 => CompressedFormat::EacR11Signed,
+        155 => CompressedFormat::EacRg11,
+        156 => CompressedFormat::EacRg11Signed,
+        157..=184 => {
+            let (block_width, block_height) = ASTC_BLOCK_SIZES[(vk_format - 157) as usize / 2];
+            CompressedFormat::Astc { block_width, block_height }
+        },
+        _ => return None,
+    };
+
+    //every srgb format follows its unorm format, BC4, BC5, BC6H and EAC have no srgb format.
+    let srgb = matches!(vk_format, 131..=138 | 145..=152 | 157..=184) && vk_format % 2 == 0;
+
+    Some((format, srgb))
+}
+
+/*  This function returns the format of a DDS four character code.
+*/
+fn get_four_cc_format(four_cc: &[u8]) -> Option<CompressedFormat> {
+    match four_cc {
+        b"DXT1" => Some(CompressedFormat::Bc1Alpha),
+        b"DXT2" | b"DXT3" => Some(CompressedFormat::Bc2),
+        b"DXT4" | b"DXT5" => Some(CompressedFormat::Bc3),
+        b"ATI1" | b"BC4U" => Some(CompressedFormat::Bc4),
+        b"BC4S" => Some(CompressedFormat::Bc4Signed),
+        b"ATI2" | b"BC5U" => Some(CompressedFormat::Bc5),
+        b"BC5S" => Some(CompressedFormat::Bc5Signed),
+        _ => None,
+    }
+}
+
+/*  This function returns the format of a dxgi format (in the DX10 header of a DDS file) and if it is srgb.
+*   The typeless formats are read as unorm.
+*/
+fn get_dxgi_format(dxgi_format: u32) -> Option<(CompressedFormat, bool)> {
+    match dxgi_format {
+        70 | 71 => Some((CompressedFormat::Bc1Alpha, false)),
+        72 => Some((CompressedFormat::Bc1Alpha, true)),
+        73 | 74 => Some((CompressedFormat::Bc2, false)),
+        75 => Some((CompressedFormat::Bc2, true)),
+        76 | 77 => Some((CompressedFormat::Bc3, false)),
+        78 => Some((CompressedFormat::Bc3, true)),
+        79 | 80 => Some((CompressedFormat::Bc4, false)),
+        81 => Some((CompressedFormat::Bc4Signed, false)),
+        82 | 83 => Some((CompressedFormat::Bc5, false)),
+        84 => Some((CompressedFormat::Bc5Signed, false)),
+        94 | 95 => Some((CompressedFormat::Bc6hUnsigned, false)),
+        96 => Some((CompressedFormat::Bc6hSigned, false)),
+        97 | 98 => Some((CompressedFormat::Bc7, false)),
+        99 => Some((CompressedFormat::Bc7, true)),
+        _ => None,
+    }
+}
+
+/*  This function checks that the image is not empty and not bigger than MAX_TEXTURE_SIZE.
+*/
+fn check_size(width: u32, height: u32, path: &str) -> Result<(), TextureError> {
+    if width == 0 || height == 0 {
+        return Err(corrupted(path, "The image is empty."));
+    }
+    if width > MAX_TEXTURE_SIZE || height > MAX_TEXTURE_SIZE {
+        return Err(corrupted(path, &format!("The image is {}x{}, the max size is {}x{}.", width, height, MAX_TEXTURE_SIZE, MAX_TEXTURE_SIZE)));
+    }
+
+    Ok(())
+}
+
+/*  This function checks that the file doesn't have more levels than the image can have (a 1x1 level is the last one).
+*   The level count is read from the file, so it is checked before the levels are allocated.
+*/
+fn check_level_count(width: u32, height: u32, level_count: usize, path: &str) -> Result<(), TextureError> {
+    let max_level_count = 32 - width.max(height).leading_zeros();
+    if level_count > max_level_count as usize {
+        return Err(corrupted(path, &format!("The file has {} levels, a {}x{} image has at most {}.", level_count, width, height, max_level_count)));
+    }
+
+    Ok(())
+}
+
+/*  This function returns the size of a mip level from the size of the level 0. The levels after the last one are 1 pixel too.
+*/
+fn get_mip_size(size: u32, level: usize) -> u32 {
+    size.checked_shr(level.min(32) as u32).unwrap_or(0).max(1)
+}
+
+/*  These functions read the little endian numbers of the headers.
+*/
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    read_u32(bytes, offset) as u64 | (read_u32(bytes, offset + 4) as u64) << 32
+}
+
+/*  This function returns size bytes from offset, or None if they are outside the file.
+*/
+fn get_range(bytes: &[u8], offset: usize, size: usize) -> Option<&[u8]> {
+    bytes.get(offset..offset.checked_add(size)?)
+}
+
+/*  These functions create the errors.
+*/
+fn corrupted(path: &str, message: &str) -> TextureError {
+    TextureError::Decode {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn unsupported(path: &str, message: &str) -> TextureError {
+    TextureError::UnsupportedFormat {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*  This function creates a DDS file with a four character code and data_size bytes after the header.
+    */
+    fn get_dds(width: u32, height: u32, mip_map_count: u32, four_cc: &[u8; 4], data_size: usize) -> Vec::<u8> {
+        let mut bytes = vec![0; DDS_HEADER_SIZE];
+        bytes[..4].copy_from_slice(&DDS_MAGIC);
+        bytes[4..8].copy_from_slice(&124u32.to_le_bytes());
+        bytes[8..12].copy_from_slice(&DDSD_MIPMAPCOUNT.to_le_bytes());
+        bytes[12..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&width.to_le_bytes());
+        bytes[28..32].copy_from_slice(&mip_map_count.to_le_bytes());
+        bytes[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        bytes[84..88].copy_from_slice(four_cc);
+        bytes.extend((0..data_size).map(|index| index as u8));
+
+        bytes
+    }
+
+    /*  This function creates a KTX2 file with a level index for level_count levels and the levels given, stored from the smallest like in the real files.
+    */
+    fn get_ktx2(vk_format: u32, width: u32, height: u32, level_count: u32, levels: &[Vec::<u8>]) -> Vec::<u8> {
+        let mut bytes = vec![0; KTX2_HEADER_SIZE];
+        bytes[..12].copy_from_slice(&KTX2_IDENTIFIER);
+        bytes[12..16].copy_from_slice(&vk_format.to_le_bytes());
+        bytes[16..20].copy_from_slice(&1u32.to_le_bytes());
+        bytes[20..24].copy_from_slice(&width.to_le_bytes());
+        bytes[24..28].copy_from_slice(&height.to_le_bytes());
+        bytes[36..40].copy_from_slice(&1u32.to_le_bytes());
+        bytes[40..44].copy_from_slice(&level_count.to_le_bytes());
+
+        let mut offsets = vec![0; levels.len()];
+        let mut offset = KTX2_HEADER_SIZE + levels.len() * KTX2_LEVEL_SIZE;
+        for (level, data) in levels.iter().enumerate().rev() {
+            offsets[level] = offset;
+            offset += data.len();
+        }
+        for (level, data) in levels.iter().enumerate() {
+            bytes.extend((offsets[level] as u64).to_le_bytes());
+            bytes.extend((data.len() as u64).to_le_bytes());
+            bytes.extend((data.len() as u64).to_le_bytes());
+        }
+        for data in levels.iter().rev() {
+            bytes.extend(data);
+        }
+
+        bytes
+    }
+
+    fn is_corrupted(result: Result<CompressedImage, TextureError>) -> bool {
+        matches!(result, Err(TextureError::Decode { .. }))
+    }
+
+    #[test]
+    fn dds_levels_are_read() {
+        let bytes = get_dds(8, 8, 4, b"DXT1", 32 + 8 + 8 + 8);
+        let image = CompressedImage::parse(&bytes, "test.dds").unwrap();
+
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!(image.format, CompressedFormat::Bc1Alpha);
+        assert!(!image.srgb);
+        assert_eq!(image.levels.iter().map(|level| level.len()).collect::<Vec::<usize>>(), vec![32, 8, 8, 8]);
+        assert_eq!(image.levels[1][0], 32);
+        assert_eq!(image.get_level_size(2), (2, 2));
+        assert_eq!(image.get_level_size(3), (1, 1));
+        assert_eq!(image.get_level_size(40), (1, 1));
+    }
+
+    #[test]
+    fn dds_truncated_is_corrupted() {
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(8, 8, 4, b"DXT1", 32 + 8 + 8 + 7), "test.dds")));
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(8, 8, 1, b"DXT1", 0)[..100], "test.dds")));
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(8, 8, 1, b"DX10", 10), "test.dds")));
+    }
+
+    #[test]
+    fn dds_huge_level_count_is_corrupted() {
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(8, 8, 0xFFFFFFFF, b"DXT1", 64), "test.dds")));
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(8, 8, 40, b"DXT1", 64), "test.dds")));
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(8, 8, 5, b"DXT1", 64), "test.dds")));
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(0, 8, 1, b"DXT1", 64), "test.dds")));
+    }
+
+    #[test]
+    fn dds_huge_size_is_corrupted() {
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(0xFFFFFFFF, 0xFFFFFFFF, 1, b"DXT1", 64), "test.dds")));
+        assert!(is_corrupted(CompressedImage::parse(&get_dds(8, MAX_TEXTURE_SIZE + 1, 1, b"DXT1", 64), "test.dds")));
+    }
+
+    #[test]
+    fn ktx2_levels_are_read() {
+        let levels = vec![vec![1; 64], vec![2; 16]];
+        let image = CompressedImage::parse(&get_ktx2(138, 8, 8, 2, &levels), "test.ktx2").unwrap();
+
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!(image.format, CompressedFormat::Bc3);
+        assert!(image.srgb);
+        assert_eq!(image.levels, levels);
+    }
+
+    #[test]
+    fn ktx2_truncated_is_corrupted() {
+        let bytes = get_ktx2(137, 8, 8, 2, &[vec![1; 64], vec![2; 16]]);
+        assert!(is_corrupted(CompressedImage::parse(&bytes[..bytes.len() - 1], "test.ktx2")));
+        assert!(is_corrupted(CompressedImage::parse(&bytes[..KTX2_HEADER_SIZE + KTX2_LEVEL_SIZE], "test.ktx2")));
+        assert!(is_corrupted(CompressedImage::parse(&bytes[..KTX2_HEADER_SIZE - 1], "test.ktx2")));
+
+        //the size of a level must match the format.
+        assert!(is_corrupted(CompressedImage::parse(&get_ktx2(137, 8, 8, 2, &[vec![1; 64], vec![2; 8]]), "test.ktx2")));
+    }
+
+    #[test]
+    fn ktx2_huge_level_count_is_corrupted() {
+        let levels = [vec![1; 64], vec![2; 16]];
+        assert!(is_corrupted(CompressedImage::parse(&get_ktx2(137, 8, 8, 0xFFFFFFFF, &levels), "test.ktx2")));
+        assert!(is_corrupted(CompressedImage::parse(&get_ktx2(137, 8, 8, 40, &levels), "test.ktx2")));
+    }
+
+    #[test]
+    fn ktx2_huge_size_is_corrupted() {
+        let levels = [vec![1; 64]];
+        assert!(is_corrupted(CompressedImage::parse(&get_ktx2(137, 0xFFFFFFFF, 0xFFFFFFFF, 1, &levels), "test.ktx2")));
+        assert!(is_corrupted(CompressedImage::parse(&get_ktx2(137, MAX_TEXTURE_SIZE + 1, 8, 1, &levels), "test.ktx2")));
+    }
+
+    #[test]
+    fn unknown_files_are_unsupported() {
+        assert!(matches!(CompressedImage::parse(b"\x89PNG\r\n\x1a\n", "test.png"), Err(TextureError::UnsupportedFormat { .. })));
+        assert!(matches!(CompressedImage::parse(&get_dds(8, 8, 1, b"ABCD", 32), "test.dds"), Err(TextureError::UnsupportedFormat { .. })));
+        assert!(matches!(CompressedImage::parse(&get_ktx2(37, 8, 8, 1, &[vec![0; 256]]), "test.ktx2"), Err(TextureError::UnsupportedFormat { .. })));
+    }
+}
@@ -0,0 +1,166 @@
+/*  File: renderer/compute.rs
+*   Author: Vicix
+*
+*   This file contains the ComputeShader class and the memory barrier helpers.
+*   The class ComputeShader is a program with only a compute stage. It is dispatched instead of being used to draw.
+*   It needs opengl 4.3 or ARB_compute_shader: the constructors return ShaderError::Unsupported on older contexts.
+*
+*   Example (a particle simulation):
+*       let particles = Ssbo::new(&initial_particles, gl::DYNAMIC_COPY)?;
+*       let simulation = ComputeShader::new("./src/shaders/particles_comp.glsl")?;
+*       particles.bind_to(0);
+*       simulation.set_float_uniform("delta", delta);
+*       simulation.dispatch_for(particles.len() as GLuint, 1, 1);
+*       compute::memory_barrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::shader::{Shader, ShaderStage};
+use crate::renderer::shaderBuilder::ShaderBuilder;
+use crate::renderer::errors::ShaderError;
+use crate::renderer::ssbo::Ssbo;
+use crate::renderer::capabilities;
+use crate::renderer::traits::shaderTrait::ShaderTrait;
+
+use std::mem;
+
+/*  This struct is the command read by ComputeShader::dispatch_indirect.
+*   It has the same layout of the glsl struct (three uints), so a shader can write it in an Ssbo.
+*/
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchIndirectCommand {
+    pub num_groups_x: GLuint,
+    pub num_groups_y: GLuint,
+    pub num_groups_z: GLuint,
+}
+
+/*  This is the declaration of the class.
+*   It wraps a Shader, so it can be watched and reloaded like the other shaders, and it caches the work group size declared in the shader (local_size_x...).
+*/
+#[derive(Debug, Clone)]
+pub struct ComputeShader {
+    shader: Shader,
+    work_group_size: [GLint; 3],
+}
+
+#[allow(dead_code)]
+impl ComputeShader {
+    /*  The constructor of the class.
+    *   It takes the path of the compute shader, then compiles and links it.
+    */
+    pub fn new(path: &str) -> Result<ComputeShader, ShaderError> {
+        ComputeShader::from_builder(&ShaderBuilder::new().with_file(ShaderStage::Compute, path))
+    }
+
+    /*  This constructor builds a compute shader from a builder (to use defines, code sources or the binary cache).
+    *   The builder must contain only a compute stage.
+    */
+    pub fn from_builder(builder: &ShaderBuilder) -> Result<ComputeShader, ShaderError> {
+        if !capabilities::is_compute_supported() {
+            let (major, minor) = capabilities::get_gl_version();
+            return Err(ShaderError::Unsupported(format!("Compute shaders need opengl 4.3 or ARB_compute_shader, the context is {}.{}.", major, minor)));
+        }
+        if builder.get_stages_ref().iter().any(|(stage, _)| *stage != ShaderStage::Compute) {
+            return Err(ShaderError::InvalidStages("A compute shader can contain only a compute stage.".to_string()));
+        }
+
+        let shader = builder.build()?;
+        let mut work_group_size = [1; 3];
+        unsafe {
+            gl::GetProgramiv(shader.get_program_id(), gl::COMPUTE_WORK_GROUP_SIZE, work_group_size.as_mut_ptr());
+        }
+
+        Ok(ComputeShader {
+            shader,
+            work_group_size,
+        })
+    }
+
+    /*  This function returns the wrapped Shader (to watch it or read its reflection).
+    */
+    pub fn get_shader_ref(&self) -> &Shader {
+        &self.shader
+    }
+
+    /*  This function returns the work group size declared in the shader.
+    *   Note: it is read when the shader is built, if the shader is reloaded with a different size call update_work_group_size.
+    */
+    pub fn get_work_group_size(&self) -> [GLint; 3] {
+        self.work_group_size
+    }
+
+    /*  This function reads the work group size again from the program.
+    */
+    pub fn update_work_group_size(&mut self) {
+        unsafe {
+            gl::GetProgramiv(self.shader.get_program_id(), gl::COMPUTE_WORK_GROUP_SIZE, self.work_group_size.as_mut_ptr());
+        }
+    }
+
+    /*  This function binds the shader and runs x * y * z work groups.
+    */
+    pub fn dispatch(&self, x: GLuint, y: GLuint, z: GLuint) {
+        self.bind();
+        unsafe {
+            gl::DispatchCompute(x, y, z);
+        }
+    }
+
+    /*  This function runs enough work groups to cover x * y * z invocations, rounding up with the work group size.
+    *   For example with local_size_x = 64, dispatch_for(1000, 1, 1) runs 16 work groups. The shader must skip the invocations out of range.
+    */
+    pub fn dispatch_for(&self, x: GLuint, y: GLuint, z: GLuint) {
+        let get_count = |invocations: GLuint, size: GLint| invocations.div_ceil(size.max(1) as GLuint);
+
+        self.dispatch(get_count(x, self.work_group_size[0]), get_count(y, self.work_group_size[1]), get_count(z, self.work_group_size[2]));
+    }
+
+    /*  This function binds the shader and runs the number of work groups written in a buffer, at the index-th command.
+    *   The buffer can be written by another shader, so the cpu doesn't need to know the count.
+    *   Note: if a shader wrote the commands, call memory_barrier(gl::COMMAND_BARRIER_BIT) before.
+    */
+    pub fn dispatch_indirect(&self, commands: &Ssbo<DispatchIndirectCommand>, index: usize) {
+        if index >= commands.len() {
+            println!("Indirect dispatch command {} out of range in ssbo {} with {} commands.", index, commands.get_ssbo_id(), commands.len());
+            return;
+        }
+
+        self.bind();
+        unsafe {
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, commands.get_ssbo_id());
+            gl::DispatchComputeIndirect((index * mem::size_of::<DispatchIndirectCommand>()) as GLintptr);
+        }
+    }
+}
+
+impl ShaderTrait for ComputeShader {
+    fn get_program_id(&self) -> u32 {
+        self.shader.get_program_id()
+    }
+
+    /*  The uniforms use the cached locations of the wrapped Shader.
+    */
+    fn get_uniform_location(&self, uniform_name: &str, value_type: GLenum) -> GLint {
+        self.shader.get_uniform_location(uniform_name, value_type)
+    }
+}
+
+/*  This function makes the writes of the previous dispatches (or draws) visible to the next operations.
+*   The barriers tell how the data will be read, for example:
+*   gl::SHADER_STORAGE_BARRIER_BIT for another shader reading an ssbo, gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT for a vbo, gl::BUFFER_UPDATE_BARRIER_BIT for a read back,
+*   gl::SHADER_IMAGE_ACCESS_BARRIER_BIT for images, gl::TEXTURE_FETCH_BARRIER_BIT for textures sampled after an image write and gl::COMMAND_BARRIER_BIT for indirect commands.
+*/
+pub fn memory_barrier(barriers: GLbitfield) {
+    unsafe {
+        gl::MemoryBarrier(barriers);
+    }
+}
+
+/*  This function waits for every write of the previous dispatches. It is the simplest and slowest barrier.
+*/
+pub fn memory_barrier_all() {
+    memory_barrier(gl::ALL_BARRIER_BITS);
+}
@@ -8,9 +8,11 @@
 //globject preperties
 pub const DRAW_MODE_PROPERTY: u8 = 0;
 pub const EBO_TYPE_PROPERTY: u8  = 1;
+pub const VALIDATE_LAYOUT_PROPERTY: u8 = 2;
 
 //texture properties
 pub const FLIP_V_PROPERTY: u8 = 0;
 pub const FLIP_H_PROPERTY: u8 = 1;
 pub const INTERNAL_FORMAT_PROPERTY: u8 = 2;
-pub const FORMAT_PROPERTY: u8 = 3;
\ No newline at end of file
+pub const FORMAT_PROPERTY: u8 = 3;
+pub const SRGB_PROPERTY: u8 = 4;
\ No newline at end of file
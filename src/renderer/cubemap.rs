@@ -0,0 +1,176 @@
+/*  File: renderer/cubemap.rs
+*   Author: Vicix
+*
+*   This file contains the functions that create the six faces of a cubemap texture.
+*   The faces can be six images, a single image with the cube unfolded in a cross or an equirectangular panorama.
+*   The faces are in the opengl order (+x, -x, +y, -y, +z, -z) and in the opengl orientation: the first row of a face is its top, like in the image files.
+*   Those functions don't use opengl, the faces are given to opengl by TextureTrait::gen_texture.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::errors::TextureError;
+use crate::renderer::imageData::{self, ImageData};
+
+use std::f32::consts::PI;
+
+/*  These are the opengl targets of the faces, in the order used everywhere in the renderer.
+*/
+pub const FACE_TARGETS: [GLenum; 6] = [
+    gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+];
+
+/*  These are the positions (column and row, in faces) of the faces in a horizontal cross (4 faces wide, 3 faces high):
+*           +y
+*       -x  +z  +x  -z
+*           -y
+*/
+const HORIZONTAL_CROSS: [(u32, u32); 6] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+
+/*  These are the positions of the faces in a vertical cross (3 faces wide, 4 faces high).
+*   The -z face is under -y, so it is upside down.
+*           +y
+*       -x  +z  +x
+*           -y
+*           -z
+*/
+const VERTICAL_CROSS: [(u32, u32); 6] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)];
+
+/*  This function checks that six images can be the faces of a cubemap: they must be squares of the same size and color type.
+*   path is used in the error.
+*/
+pub fn check_faces(faces: &[ImageData], path: &str) -> Result<(), TextureError> {
+    let message = if faces.len() != 6 {
+        format!("A cubemap needs 6 faces, found {}.", faces.len())
+    } else if faces.iter().any(|face| face.width != face.height) {
+        "The faces of a cubemap must be squares.".to_string()
+    } else if faces.iter().any(|face| face.width != faces[0].width || face.color != faces[0].color) {
+        "The faces of a cubemap must have the same size and color type.".to_string()
+    } else {
+        return Ok(());
+    };
+
+    Err(TextureError::Decode {
+        path: path.to_string(),
+        message,
+    })
+}
+
+/*  This function cuts the six faces of a cubemap unfolded in a horizontal or a vertical cross (see HORIZONTAL_CROSS and VERTICAL_CROSS).
+*   The kind of cross is found from the size of the image. path is used in the error.
+*/
+pub fn split_cross(image: &ImageData, path: &str) -> Result<Vec::<ImageData>, TextureError> {
+    let (face_size, positions, vertical) = if image.width * 3 == image.height * 4 {
+        (image.width / 4, HORIZONTAL_CROSS, false)
+    } else if image.width * 4 == image.height * 3 {
+        (image.width / 3, VERTICAL_CROSS, true)
+    } else {
+        return Err(TextureError::Decode {
+            path: path.to_string(),
+            message: format!("A {}x{} image is not a cubemap cross (it must be 4:3 or 3:4).", image.width, image.height),
+        });
+    };
+
+    let mut faces = positions.iter().filter_map(|(column, row)| image.get_region(column * face_size, row * face_size, face_size, face_size)).collect::<Vec::<ImageData>>();
+    if vertical {
+        faces[5].flip_vertical();
+        faces[5].flip_horizontal();
+    }
+
+    Ok(faces)
+}
+
+/*  This function projects an equirectangular panorama (360 degrees wide and 180 degrees high) on the six faces of a cubemap.
+*   The center of the panorama is the -z face. face_size is the size of a face, 0 uses a quarter of the width of the panorama.
+*   The pixels are sampled with a bilinear filter, the panorama wraps horizontally.
+*/
+pub fn project_equirectangular(image: &ImageData, face_size: u32) -> Vec::<ImageData> {
+    let face_size = if face_size == 0 { (image.width / 4).max(1) } else { face_size };
+    let channel_count = imageData::get_channel_count(image.color);
+    let channel_size = image.get_bytes_per_pixel() / channel_count;
+
+    (0..FACE_TARGETS.len()).map(|face| {
+        let mut pixels = Vec::<u8>::with_capacity((face_size * face_size) as usize * image.get_bytes_per_pixel());
+        for y in 0..face_size {
+            for x in 0..face_size {
+                let u = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                let v = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                let [direction_x, direction_y, direction_z] = get_face_direction(face, u, v);
+                let length = (direction_x * direction_x + direction_y * direction_y + direction_z * direction_z).sqrt();
+
+                let sample_x = (0.5 + direction_x.atan2(-direction_z) / (2.0 * PI)) * image.width as f32 - 0.5;
+                let sample_y = (direction_y / length).acos() / PI * image.height as f32 - 0.5;
+                for channel in 0..channel_count {
+                    let value = sample_bilinear(image, sample_x, sample_y, channel, channel_size);
+                    write_channel(&mut pixels, value, channel_size);
+                }
+            }
+        }
+
+        ImageData {
+            width: face_size,
+            height: face_size,
+            color: image.color,
+            pixels,
+        }
+    }).collect()
+}
+
+/*  This function returns the direction (not normalized) of a point of a face, with u and v from -1 to 1 (v goes down).
+*   It is the inverse of the face selection of the opengl specification.
+*/
+fn get_face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+/*  This function samples a channel of the image between four pixels.
+*/
+fn sample_bilinear(image: &ImageData, x: f32, y: f32, channel: usize, channel_size: usize) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fraction_x, fraction_y) = (x - x0, y - y0);
+    let wrap_x = |x: f32| (x as i64).rem_euclid(image.width as i64) as usize;
+    let clamp_y = |y: f32| (y as i64).clamp(0, image.height as i64 - 1) as usize;
+
+    let (left, right) = (wrap_x(x0), wrap_x(x0 + 1.0));
+    let (top, bottom) = (clamp_y(y0), clamp_y(y0 + 1.0));
+    let read = |x: usize, y: usize| read_channel(image, x, y, channel, channel_size);
+
+    let top_value = read(left, top) * (1.0 - fraction_x) + read(right, top) * fraction_x;
+    let bottom_value = read(left, bottom) * (1.0 - fraction_x) + read(right, bottom) * fraction_x;
+
+    top_value * (1.0 - fraction_y) + bottom_value * fraction_y
+}
+
+/*  This function reads a channel of a pixel (8 bit or 16 bit in the native byte order).
+*/
+fn read_channel(image: &ImageData, x: usize, y: usize, channel: usize, channel_size: usize) -> f32 {
+    let pixel_size = image.get_bytes_per_pixel();
+    let index = (y * image.width as usize + x) * pixel_size + channel * channel_size;
+
+    match channel_size {
+        2 => u16::from_ne_bytes([image.pixels[index], image.pixels[index + 1]]) as f32,
+        _ => image.pixels[index] as f32,
+    }
+}
+
+/*  This function writes a channel of a pixel, in the same size used by read_channel.
+*/
+fn write_channel(pixels: &mut Vec::<u8>, value: f32, channel_size: usize) {
+    match channel_size {
+        2 => pixels.extend_from_slice(&(value.round().clamp(0.0, u16::MAX as f32) as u16).to_ne_bytes()),
+        _ => pixels.push(value.round().clamp(0.0, u8::MAX as f32) as u8),
+    }
+}
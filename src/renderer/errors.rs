@@ -0,0 +1,129 @@
+/*  File: renderer/errors.rs
+*   Author: Vicix
+*
+*   This file contains the error types of the renderer.
+*   Every fallible constructor returns one of those instead of panicking or printing to the console.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::shader::ShaderStage;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/*  This is the error returned when building a shader.
+*   Io is used when a shader file cannot be opened or read.
+*   Compile contains the full info log of the stage that failed and the path of its file.
+*   Link contains the full info log of the program.
+*   InvalidStages is used when the attached stages cannot form a program.
+*   Preprocess is used when an #include is malformed or creates a cycle.
+*   InvalidKeywords is used when a shader variant is requested with an unknown keyword or with two values of the same enum keyword.
+*   Unsupported is used when the opengl context does not support a kind of shader or the storage buffers (for example compute shaders before opengl 4.3).
+*/
+#[derive(Debug)]
+pub enum ShaderError {
+    Io {
+        path: String,
+        error: io::Error,
+    },
+    Compile {
+        stage: ShaderStage,
+        path: String,
+        log: String,
+    },
+    Link {
+        log: String,
+    },
+    InvalidStages(String),
+    Preprocess {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    InvalidKeywords(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, error } => write!(f, "Failed to read shader {}: {}", path, error),
+            ShaderError::Compile { stage, path, log } => write!(f, "Failed to compile {} shader {}:\n{}", stage, path, log),
+            ShaderError::Link { log } => write!(f, "Failed to link shader program:\n{}", log),
+            ShaderError::InvalidStages(message) => write!(f, "Invalid shader stages: {}", message),
+            ShaderError::Preprocess { path, line, message } => write!(f, "Failed to preprocess shader {} at line {}: {}", path, line, message),
+            ShaderError::InvalidKeywords(message) => write!(f, "Invalid shader keywords: {}", message),
+            ShaderError::Unsupported(message) => write!(f, "Unsupported shader: {}", message),
+        }
+    }
+}
+
+impl Error for ShaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ShaderError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/*  This is the error returned when loading a texture.
+*   Io is used when the image file cannot be found, opened or read.
+*   Decode is used when the image is corrupted or empty.
+*   UnsupportedFormat is used when the image format or its pixel format cannot be loaded.
+*   Upload is used when opengl rejects the texture, with the opengl error (for example gl::INVALID_ENUM for a wrong format).
+*   Readback is used when the pixels of a texture or of the framebuffer cannot be read back, with the opengl error.
+*   Save is used when an image cannot be written to a file.
+*/
+#[derive(Debug)]
+pub enum TextureError {
+    Io {
+        path: String,
+        error: io::Error,
+    },
+    Decode {
+        path: String,
+        message: String,
+    },
+    UnsupportedFormat {
+        path: String,
+        message: String,
+    },
+    Upload {
+        path: String,
+        error: GLenum,
+    },
+    Readback {
+        path: String,
+        error: GLenum,
+    },
+    Save {
+        path: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Io { path, error } => write!(f, "Failed to read texture {}: {}", path, error),
+            TextureError::Decode { path, message } => write!(f, "Failed to decode texture {}: {}", path, message),
+            TextureError::UnsupportedFormat { path, message } => write!(f, "Unsupported texture format in {}: {}", path, message),
+            TextureError::Upload { path, error } => write!(f, "Failed to upload texture {}: opengl error 0x{:X}", path, error),
+            TextureError::Readback { path, error } => write!(f, "Failed to read back texture {}: opengl error 0x{:X}", path, error),
+            TextureError::Save { path, message } => write!(f, "Failed to save image {}: {}", path, message),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
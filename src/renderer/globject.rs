@@ -16,6 +16,9 @@ use crate::renderer::traits::glObjectTrait::GlObjectTrait;
 
 use crate::renderer::vaoLayoutElement::VaoLayoutElement;
 use crate::renderer::properties::GlObjectProperties;
+use crate::renderer::vbo::Vbo;
+
+use std::cell::Cell;
 
 
 /*  The structure definition.
@@ -36,7 +39,7 @@ impl GlObject {
     *   We create a new vao and a new vbo.
     *   We don't want to assign an ebo, because it is not necessary.
     *   We'll assign it only when we need it.
-    *   Also properties are set as gl::TRIANGLES and gl::UNSIGNED_INT by default.
+    *   Also properties are set as gl::TRIANGLES and gl::UNSIGNED_INT by default and the layout is validated on the first draw.
     *   Those will likely be overritten by the user using the function set_property()
     */
     pub fn new() -> GlObject {
@@ -64,13 +67,15 @@ impl GlObject {
             properties: GlObjectProperties {
                 draw_mode: gl::TRIANGLES,
                 ebo_type: gl::UNSIGNED_INT,
+                validate_layout: true,
+                layout_validated: Cell::new(false),
             },
         }
     }
 
     /*  The main constructor of the class. 
     *   We create a new vao, a new vbo and a new ebo.
-    *   Also properties are set as gl::TRIANGLES and gl::UNSIGNED_INT by default.
+    *   Also properties are set as gl::TRIANGLES and gl::UNSIGNED_INT by default and the layout is validated on the first draw.
     *   Those will likely be overritten by the user using the function set_property()
     */
     pub fn with_ebo() -> GlObject {
@@ -106,6 +111,33 @@ impl GlObject {
             properties: GlObjectProperties {
                 draw_mode: gl::TRIANGLES,
                 ebo_type: gl::UNSIGNED_INT,
+                validate_layout: true,
+                layout_validated: Cell::new(false),
+            },
+        }
+    }
+
+    /*  This constructor creates a new vao that reads the vertices of an existing vbo (for example a vbo written by transform feedback).
+    *   The vbo is shared, not copied. The object has no ebo.
+    */
+    pub fn with_vbo(vbo: &Vbo) -> GlObject {
+        let mut vao_id = 1;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao_id);
+            gl::BindVertexArray(vao_id);
+        }
+        vbo.bind_vbo();
+
+        GlObject {
+            vao_id,
+            layout: Vec::<VaoLayoutElement>::new(),
+            vbo_id: vbo.get_vbo_id(),
+            ebo_id: None,
+            properties: GlObjectProperties {
+                draw_mode: gl::TRIANGLES,
+                ebo_type: gl::UNSIGNED_INT,
+                validate_layout: true,
+                layout_validated: Cell::new(false),
             },
         }
     }
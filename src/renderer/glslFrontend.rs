@@ -0,0 +1,808 @@
+/*  File: renderer/glslFrontend.rs
+*   Author: Vicix
+*
+*   This file contains the offline glsl front end. It is only compiled with the shader_tools feature.
+*   It parses and validates the shaders with naga, without an opengl context, so a broken shader is found on a machine without a gpu.
+*
+*   naga only understands vulkan glsl, so the code is converted first (see to_naga_glsl):
+*   the version becomes 450, the loose uniforms are moved in a uniform block, the combined samplers are split in a texture and a sampler
+*   and the inputs and outputs without a location get one. The line numbers are kept, so the errors point to the original file and line.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::shader::ShaderStage;
+use crate::renderer::errors::ShaderError;
+use crate::renderer::preprocessor::PreprocessedSource;
+use crate::renderer::vertexInput::VertexInput;
+
+use naga::front::glsl::{ErrorKind, Frontend, Options};
+use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+/*  This is the name of the uniform block that contains the loose uniforms.
+*/
+pub const GLOBALS_BLOCK_NAME: &str = "Globals_";
+
+/*  This is the suffix of the sampler created for every combined sampler.
+*/
+pub const SAMPLER_SUFFIX: &str = "_sampler_";
+
+/*  This function parses and validates a preprocessed stage and returns the naga module.
+*   The errors are returned as ShaderError::Compile, with every message pointing to the original file and line.
+*   Geometry and tessellation stages cannot be parsed by naga, so they return ShaderError::InvalidStages.
+*/
+pub fn parse_module(stage: ShaderStage, processed: &PreprocessedSource) -> Result<naga::Module, ShaderError> {
+    parse_and_validate(stage, processed).map(|(module, _)| module)
+}
+
+/*  This function is like parse_module, but it also returns the validation info, needed by the naga back ends (see shaderTranslator).
+*/
+pub fn parse_and_validate(stage: ShaderStage, processed: &PreprocessedSource) -> Result<(naga::Module, ModuleInfo), ShaderError> {
+    let naga_stage = match stage {
+        ShaderStage::Vertex => naga::ShaderStage::Vertex,
+        ShaderStage::Fragment => naga::ShaderStage::Fragment,
+        ShaderStage::Compute => naga::ShaderStage::Compute,
+        _ => return Err(ShaderError::InvalidStages(format!("The {} stage cannot be validated offline.", stage))),
+    };
+
+    let code = to_naga_glsl(&processed.code);
+    let compile_error = |log: String| ShaderError::Compile {
+        stage,
+        path: processed.files[0].clone(),
+        log,
+    };
+
+    let mut module = Frontend::default().parse(&Options::from(naga_stage), &code).map_err(|errors| {
+        let log = errors.errors.iter()
+            .map(|error| format_message(processed, error.meta.location(&code).line_number as usize, &error.kind.to_string()))
+            .collect::<Vec::<String>>()
+            .join("\n");
+        compile_error(log)
+    })?;
+    split_opaque_arrays(&mut module).map_err(|(span, message)| compile_error(format_message(processed, span.location(&code).line_number as usize, &message)))?;
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all()).validate(&module).map_err(|error| {
+        let mut message = error.as_inner().to_string();
+        for (_, description) in error.spans() {
+            if !description.is_empty() {
+                message.push_str(&format!(" ({})", description));
+            }
+        }
+
+        let line_number = error.location(&code).map(|location| location.line_number as usize).unwrap_or(0);
+        compile_error(format_message(processed, line_number, &message))
+    })?;
+
+    Ok((module, info))
+}
+
+/*  This function asks naga if a preprocessed file has a main function.
+*   The files without one are include files, that cannot be validated alone. The other errors of the file are ignored here.
+*/
+pub fn has_entry_point(processed: &PreprocessedSource) -> bool {
+    let (code, _) = convert_to_naga_glsl(&processed.code);
+    match Frontend::default().parse(&Options::from(naga::ShaderStage::Vertex), &code) {
+        Ok(module) => !module.entry_points.is_empty(),
+        Err(errors) => !errors.errors.iter().any(|error| matches!(&error.kind, ErrorKind::SemanticError(message) if message == "Missing entry point")),
+    }
+}
+
+/*  This function returns the inputs of the vertex entry point of a module.
+*   The inputs without a layout(location = N) are numbered in declaration order by to_naga_glsl.
+*/
+pub fn get_vertex_inputs(module: &naga::Module) -> Vec::<VertexInput> {
+    let mut inputs = Vec::<VertexInput>::new();
+
+    for entry_point in module.entry_points.iter().filter(|entry_point| entry_point.stage == naga::ShaderStage::Vertex) {
+        for argument in &entry_point.function.arguments {
+            let location = match argument.binding {
+                Some(naga::Binding::Location { location, .. }) => location,
+                _ => continue,
+            };
+
+            let (component_count, scalar) = match module.types[argument.ty].inner {
+                naga::TypeInner::Scalar(scalar) => (1, scalar),
+                naga::TypeInner::Vector { size, scalar } => (size as GLint, scalar),
+                _ => continue,
+            };
+
+            inputs.push(VertexInput {
+                name: argument.name.clone().unwrap_or_default(),
+                location,
+                component_count,
+                base_type: match scalar.kind {
+                    naga::ScalarKind::Sint => gl::INT,
+                    naga::ScalarKind::Uint => gl::UNSIGNED_INT,
+                    naga::ScalarKind::Bool => gl::BOOL,
+                    _ if scalar.width == 8 => gl::DOUBLE,
+                    _ => gl::FLOAT,
+                },
+            });
+        }
+    }
+
+    inputs
+}
+
+/*  This function returns the stage of a shader from its file name (for example albedo_vert.glsl or albedo.vert is a vertex shader).
+*   The stage must be a whole word of the name (separated by _ or ., a number can follow it like in vert2.glsl), so invert_frag.glsl is a fragment shader. The last word wins.
+*   It is used by the shader commands, that find the stage of every file of a directory.
+*/
+pub fn get_file_stage(file_name: &str) -> Option<ShaderStage> {
+    let stages = [
+        ("vert", ShaderStage::Vertex),
+        ("frag", ShaderStage::Fragment),
+        ("geom", ShaderStage::Geometry),
+        ("tesc", ShaderStage::TessControl),
+        ("tese", ShaderStage::TessEvaluation),
+        ("comp", ShaderStage::Compute),
+    ];
+
+    file_name.rsplit(['_', '.'])
+        .find_map(|word| stages.iter().find(|(name, _)| *name == word.trim_end_matches(|c: char| c.is_ascii_digit())))
+        .map(|(_, stage)| *stage)
+}
+
+/*  This function converts opengl glsl in the vulkan glsl understood by naga. Every line stays on the same line number.
+*   - The #version becomes 450 and the #line directives are removed.
+*   - The loose uniforms are moved in a uniform block called Globals_, at binding 0, declared after the last uniform or struct before the first function,
+*     so the structs used by the uniforms are declared before it. The initializers of the loose uniforms are removed (a block member cannot have one) with a warning.
+*   - Every combined sampler (sampler2D tex) becomes a texture (texture2D tex) and a sampler (sampler tex_sampler_), and every use of tex becomes sampler2D(tex, tex_sampler_).
+*     The arrays keep their size (sampler2D tex[2] becomes texture2D tex[2] and sampler tex_sampler_[2]) and tex[i] becomes sampler2D(tex[i], tex_sampler_[i]).
+*   - The uniform blocks, the textures and the images without a binding get one.
+*   - The global inputs and outputs without a location get one, after the highest explicit location.
+*   naga cannot use the arrays of textures and samplers, they are split after parsing (see split_opaque_arrays).
+*/
+pub fn to_naga_glsl(code: &str) -> String {
+    let (code, warnings) = convert_to_naga_glsl(code);
+    for warning in warnings {
+        println!("{}", warning);
+    }
+
+    code
+}
+
+/*  This function is to_naga_glsl, but it returns the warnings instead of printing them.
+*/
+fn convert_to_naga_glsl(code: &str) -> (String, Vec::<String>) {
+    let lines: Vec::<&str> = code.lines().collect();
+    let mut warnings = Vec::<String>::new();
+
+    let mut next_location = [0, 0];
+    let mut depth = 0;
+    let mut statement = String::new();
+    let mut is_struct = false;
+    let mut first_function = None;
+    let mut last_struct = None;
+    for (i, line) in lines.iter().enumerate() {
+        let code = line.split("//").next().unwrap_or("");
+        if depth == 0 {
+            let (layout, rest) = split_layout(line.trim());
+            if let (Some(direction), Some(location)) = (get_io_direction(rest), layout.and_then(|layout| get_layout_value(layout, "location"))) {
+                next_location[direction] = next_location[direction].max(location + 1);
+            }
+
+            //the statement that opens a block tells if it is a function, a struct or an interface block.
+            if let Some(brace) = code.find('{') {
+                statement.push_str(&code[..brace]);
+                let (_, header) = split_layout(statement.trim());
+                is_struct = header.split_whitespace().any(|word| word == "struct");
+                if header.contains('(') {
+                    first_function.get_or_insert(i);
+                }
+                statement.clear();
+            } else if code.contains(';') || code.trim_start().starts_with('#') {
+                statement.clear();
+            } else {
+                statement.push_str(code);
+                statement.push(' ');
+            }
+        }
+
+        let change = get_depth_change(line);
+        if is_struct && depth + change == 0 && (depth > 0 || code.contains('{')) && first_function.is_none() {
+            last_struct = Some(i);
+        }
+        depth += change;
+    }
+
+    let mut output: Vec::<String> = Vec::with_capacity(lines.len());
+    let mut generated_lines = Vec::<usize>::new();
+    let mut block_members = Vec::<String>::new();
+    let mut first_member_line = None;
+    let mut last_member_line = None;
+    let mut samplers = Vec::<(String, String, bool)>::new();
+    let mut next_binding = 1;
+    depth = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        let (layout, rest) = split_layout(trimmed);
+        let mut new_line = line.to_string();
+
+        if trimmed.starts_with("#version") {
+            new_line = "#version 450 core".to_string();
+        } else if trimmed.starts_with("#line") {
+            new_line = String::new();
+        } else if depth == 0 && rest.starts_with("uniform ") {
+            let declaration = rest["uniform ".len()..].trim_start();
+            let type_name = declaration.split(|c: char| c.is_whitespace() || c == '{').find(|word| !word.is_empty() && !is_qualifier(word)).unwrap_or("");
+
+            if trimmed.contains('{') || !trimmed.contains(';') || is_opaque(type_name) && !is_combined_sampler(type_name) {
+                new_line = add_binding(layout, rest, &mut next_binding);
+            } else if is_combined_sampler(type_name) {
+                let names = &declaration[declaration.find(type_name).unwrap_or(0) + type_name.len()..];
+                let mut declarations = Vec::<String>::new();
+                for declarator in names.trim_end_matches(';').split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+                    let (name, array) = declarator.split_at(declarator.find('[').unwrap_or(declarator.len()));
+                    let (name, array) = (name.trim(), array.trim());
+                    let texture_type = type_name.replacen("sampler", "texture", 1).replace("Shadow", "");
+                    let sampler_type = if type_name.ends_with("Shadow") { "samplerShadow" } else { "sampler" };
+                    declarations.push(format!("layout(binding = {}) uniform {} {}{}; layout(binding = {}) uniform {} {}{}{};",
+                        next_binding, texture_type, name, array, next_binding + 1, sampler_type, name, SAMPLER_SUFFIX, array));
+                    samplers.push((name.to_string(), type_name.to_string(), !array.is_empty()));
+                    next_binding += 2;
+                }
+                new_line = declarations.join(" ");
+                generated_lines.push(output.len());
+            } else {
+                let (member, has_initializer) = remove_initializers(declaration);
+                if has_initializer {
+                    warnings.push(format!("The initializer of \"{}\" is removed: the loose uniforms are moved in a uniform block, whose members cannot have one.", declaration.trim_end_matches(';')));
+                }
+                block_members.push(member);
+                first_member_line.get_or_insert(i);
+                if first_function.map_or(true, |function_line| i < function_line) {
+                    last_member_line = Some(i);
+                }
+                new_line = String::new();
+            }
+        } else if depth == 0 && layout.map_or(true, |layout| get_layout_value(layout, "location").is_none()) && !trimmed.contains('{') {
+            if let Some(direction) = get_io_direction(rest) {
+                new_line = match layout {
+                    Some(layout) => format!("{}location = {}, {}", &layout[..layout.find('(').unwrap_or(0) + 1], next_location[direction], &trimmed[layout.find('(').unwrap_or(0) + 1..]),
+                    None => format!("layout(location = {}) {}", next_location[direction], trimmed),
+                };
+                next_location[direction] += 1;
+            }
+        }
+
+        depth += get_depth_change(line);
+        output.push(new_line);
+    }
+
+    //the block goes on the line of the last uniform or struct before the first function (the line of a struct ends with its }; so the block is added after it).
+    let block_line = first_member_line.map(|first_member_line| last_member_line.max(last_struct).unwrap_or(first_member_line));
+    if let Some(block_line) = block_line {
+        let block = format!("layout(binding = 0) uniform {} {{ {} }};", GLOBALS_BLOCK_NAME, block_members.join(" "));
+        output[block_line] = if output[block_line].trim().is_empty() { block } else { format!("{} {}", output[block_line], block) };
+        generated_lines.push(block_line);
+    }
+
+    for (i, line) in output.iter_mut().enumerate() {
+        if generated_lines.contains(&i) {
+            continue;
+        }
+        for (name, type_name, is_array) in &samplers {
+            *line = if *is_array {
+                replace_sampler_array(line, name, type_name)
+            } else {
+                replace_identifier(line, name, &format!("{}({}, {}{})", type_name, name, name, SAMPLER_SUFFIX))
+            };
+        }
+    }
+
+    let mut result = output.join("\n");
+    result.push('\n');
+
+    (result, warnings)
+}
+
+/*  This function splits the arrays of textures and samplers of a module in a global for every element, called tex[0], tex[1]...
+*   naga parses them as arrays of opaque types, that cannot be validated or translated. The element 0 keeps the global and the binding of the array,
+*   the other elements get the next free bindings. Every tex[N] becomes its global, so only constant indices are supported, like in glsl 330.
+*   It returns the span of the first use that cannot be split, with the error.
+*/
+fn split_opaque_arrays(module: &mut naga::Module) -> Result<(), (naga::Span, String)> {
+    let arrays: Vec::<(naga::Handle<naga::GlobalVariable>, naga::Handle<naga::Type>, u32)> = module.global_variables.iter()
+        .filter_map(|(handle, global)| match module.types[global.ty].inner {
+            naga::TypeInner::Array { base, size: naga::ArraySize::Constant(size), .. }
+                if matches!(module.types[base].inner, naga::TypeInner::Image { .. } | naga::TypeInner::Sampler { .. }) => Some((handle, base, size.get())),
+            _ => None,
+        })
+        .collect();
+    if arrays.is_empty() {
+        return Ok(());
+    }
+
+    let mut next_binding = module.global_variables.iter().filter_map(|(_, global)| global.binding.as_ref().map(|binding| binding.binding + 1)).max().unwrap_or(0);
+    let mut elements = HashMap::<naga::Handle<naga::GlobalVariable>, Vec::<naga::Handle<naga::GlobalVariable>>>::new();
+    for (handle, base, size) in arrays {
+        let span = module.global_variables.get_span(handle);
+        let array = module.global_variables[handle].clone();
+        let name = array.name.clone().unwrap_or_default();
+
+        let mut handles = vec![handle];
+        for index in 1..size {
+            let binding = array.binding.as_ref().map(|binding| naga::ResourceBinding {
+                group: binding.group,
+                binding: next_binding,
+            });
+            next_binding += 1;
+            handles.push(module.global_variables.append(naga::GlobalVariable {
+                name: Some(format!("{}[{}]", name, index)),
+                space: naga::AddressSpace::Handle,
+                binding,
+                ty: base,
+                ..array.clone()
+            }, span));
+        }
+
+        let first = &mut module.global_variables[handle];
+        first.name = Some(format!("{}[0]", name));
+        first.space = naga::AddressSpace::Handle;
+        first.ty = base;
+        elements.insert(handle, handles);
+    }
+
+    let constants = &module.constants;
+    let global_expressions = &module.global_expressions;
+    let functions = module.functions.iter_mut().map(|(_, function)| function).chain(module.entry_points.iter_mut().map(|entry_point| &mut entry_point.function));
+    for function in functions {
+        //tex[N] becomes the global of the element, and its load too, because the elements are not pointers.
+        let mut replaced = HashMap::<naga::Handle<naga::Expression>, naga::Expression>::new();
+        for (handle, expression) in function.expressions.iter() {
+            let (base, index) = match *expression {
+                naga::Expression::AccessIndex { base, index } => (base, Some(index)),
+                naga::Expression::Access { base, index } => (base, get_constant_index(&function.expressions[index], constants, global_expressions)),
+                naga::Expression::Load { pointer } => {
+                    if let Some(element) = replaced.get(&pointer).cloned() {
+                        replaced.insert(handle, element);
+                    }
+                    continue;
+                },
+                _ => continue,
+            };
+            let array = match function.expressions[base] {
+                naga::Expression::GlobalVariable(global) => match elements.get(&global) {
+                    Some(array) => array,
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            let span = function.expressions.get_span(handle);
+            let name = module.global_variables[array[0]].name.as_deref().unwrap_or("").trim_end_matches("[0]").to_string();
+            let element = index.and_then(|index| array.get(index as usize))
+                .ok_or_else(|| (span, format!("The array of samplers {} can only be indexed with a constant smaller than its size.", name)))?;
+            replaced.insert(handle, naga::Expression::GlobalVariable(*element));
+        }
+
+        for (handle, expression) in &replaced {
+            *function.expressions.get_mut(*handle) = expression.clone();
+        }
+        remove_emitted(&mut function.body, &replaced.keys().copied().collect::<HashSet::<_>>());
+    }
+
+    //the array types are not used anymore, they would not be valid.
+    naga::compact::compact(module, naga::compact::KeepUnused::Yes);
+
+    Ok(())
+}
+
+/*  This function returns the value of a constant index: a literal or a constant.
+*/
+fn get_constant_index(index: &naga::Expression, constants: &naga::Arena<naga::Constant>, global_expressions: &naga::Arena<naga::Expression>) -> Option<u32> {
+    match *index {
+        naga::Expression::Literal(naga::Literal::I32(value)) => u32::try_from(value).ok(),
+        naga::Expression::Literal(naga::Literal::U32(value)) => Some(value),
+        naga::Expression::Literal(naga::Literal::AbstractInt(value)) => u32::try_from(value).ok(),
+        naga::Expression::Constant(constant) => get_constant_index(&global_expressions[constants[constant].init], constants, global_expressions),
+        _ => None,
+    }
+}
+
+/*  This function removes expressions from the Emit statements of a block and of the blocks inside it.
+*   The globals are never emitted, so the expressions replaced with a global must not be.
+*/
+fn remove_emitted(block: &mut naga::Block, removed: &HashSet::<naga::Handle<naga::Expression>>) {
+    let old_block = std::mem::take(block);
+    for (mut statement, span) in old_block.span_into_iter() {
+        match statement {
+            naga::Statement::Emit(ref range) => {
+                let mut kept: Option<(naga::Handle<naga::Expression>, naga::Handle<naga::Expression>)> = None;
+                for handle in range.clone() {
+                    if removed.contains(&handle) {
+                        if let Some((first, last)) = kept.take() {
+                            block.push(naga::Statement::Emit(naga::Range::new_from_bounds(first, last)), span);
+                        }
+                    } else {
+                        kept = Some(kept.map_or((handle, handle), |(first, _)| (first, handle)));
+                    }
+                }
+                if let Some((first, last)) = kept {
+                    block.push(naga::Statement::Emit(naga::Range::new_from_bounds(first, last)), span);
+                }
+                continue;
+            },
+            naga::Statement::Block(ref mut inner) => remove_emitted(inner, removed),
+            naga::Statement::If { ref mut accept, ref mut reject, .. } => {
+                remove_emitted(accept, removed);
+                remove_emitted(reject, removed);
+            },
+            naga::Statement::Switch { ref mut cases, .. } => {
+                for case in cases.iter_mut() {
+                    remove_emitted(&mut case.body, removed);
+                }
+            },
+            naga::Statement::Loop { ref mut body, ref mut continuing, .. } => {
+                remove_emitted(body, removed);
+                remove_emitted(continuing, removed);
+            },
+            _ => {},
+        }
+        block.push(statement, span);
+    }
+}
+
+/*  This function removes the initializers of the declarators of a declaration (float a = 1.0, b; becomes float a, b;).
+*   It returns the declaration and true if an initializer was removed.
+*/
+fn remove_initializers(declaration: &str) -> (String, bool) {
+    let mut result = String::with_capacity(declaration.len());
+    let mut depth = 0;
+    let mut in_initializer = false;
+    let mut removed = false;
+
+    for c in declaration.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '=' if depth == 0 => {
+                in_initializer = true;
+                removed = true;
+                continue;
+            },
+            ',' | ';' if depth == 0 => in_initializer = false,
+            _ => {},
+        }
+        if !in_initializer {
+            result.push(c);
+        }
+    }
+
+    //the spaces before the = are left before the , or the ;.
+    (result.replace(" ,", ",").replace(" ;", ";"), removed)
+}
+
+/*  This function replaces every use of an element of a combined sampler array: tex[i] becomes sampler2D(tex[i], tex_sampler_[i]).
+*   A use without an index (passing the whole array) is left as it is.
+*/
+fn replace_sampler_array(line: &str, name: &str, type_name: &str) -> String {
+    let is_identifier_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(position) = rest.find(name) {
+        let before = rest[..position].chars().next_back();
+        let after = &rest[position + name.len()..];
+        result.push_str(&rest[..position]);
+        rest = after;
+
+        let index_start = after.len() - after.trim_start().len();
+        if before.is_some_and(is_identifier_char) || after.chars().next().is_some_and(is_identifier_char) || !after[index_start..].starts_with('[') {
+            result.push_str(name);
+            continue;
+        }
+
+        //the index ends at the matching ], it can contain other brackets.
+        let mut depth = 0;
+        let index_end = after[index_start..].char_indices().find(|(_, c)| {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => {},
+            }
+            depth == 0
+        }).map(|(end, _)| index_start + end);
+        match index_end {
+            Some(index_end) => {
+                let index = &after[index_start..=index_end];
+                result.push_str(&format!("{}({}{}, {}{}{})", type_name, name, index, name, SAMPLER_SUFFIX, index));
+                rest = &after[index_end + 1..];
+            },
+            None => result.push_str(name),
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
+
+/*  This function formats an error message with the original file and line.
+*/
+fn format_message(processed: &PreprocessedSource, line_number: usize, message: &str) -> String {
+    if line_number == 0 {
+        return format!("{}: {}", processed.files[0], message);
+    }
+
+    let (file, line) = processed.get_original_line(line_number);
+    format!("{}:{}: {}", file, line, message)
+}
+
+/*  This function splits a declaration in its layout qualifier (if any) and the rest.
+*/
+fn split_layout(line: &str) -> (Option<&str>, &str) {
+    if line.starts_with("layout") {
+        if let Some(end) = line.find(')') {
+            return (Some(&line[..=end]), line[end + 1..].trim_start());
+        }
+    }
+
+    (None, line)
+}
+
+/*  This function returns the value of a qualifier in a layout, for example the 2 of layout(location = 2).
+*/
+fn get_layout_value(layout: &str, qualifier: &str) -> Option<u32> {
+    let parameters = &layout[layout.find('(')? + 1..layout.rfind(')')?];
+    parameters.split(',').find_map(|parameter| {
+        let mut parts = parameter.split('=');
+        if parts.next()?.trim() == qualifier {
+            parts.next()?.trim().parse::<u32>().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/*  This function adds a binding to the layout of a declaration, or a layout if it doesn't have one.
+*/
+fn add_binding(layout: Option<&str>, rest: &str, next_binding: &mut u32) -> String {
+    match layout {
+        Some(layout) if get_layout_value(layout, "binding").is_some() => format!("{} {}", layout, rest),
+        Some(layout) => {
+            *next_binding += 1;
+            format!("{}binding = {}, {} {}", &layout[..layout.find('(').unwrap_or(0) + 1], *next_binding - 1, &layout[layout.find('(').unwrap_or(0) + 1..], rest)
+        },
+        None => {
+            *next_binding += 1;
+            format!("layout(binding = {}) {}", *next_binding - 1, rest)
+        },
+    }
+}
+
+/*  This function returns 0 for a global input and 1 for a global output (interpolation qualifiers are skipped).
+*/
+fn get_io_direction(declaration: &str) -> Option<usize> {
+    let word = declaration.split_whitespace()
+        .find(|word| !matches!(*word, "flat" | "smooth" | "noperspective" | "centroid" | "sample" | "invariant"))?;
+
+    match word {
+        "in" => Some(0),
+        "out" => Some(1),
+        _ => None,
+    }
+}
+
+/*  This function returns how much a line changes the brace depth.
+*/
+fn get_depth_change(line: &str) -> i32 {
+    let code = line.split("//").next().unwrap_or("");
+    code.matches('{').count() as i32 - code.matches('}').count() as i32
+}
+
+/*  This function checks if a word is a precision or a memory qualifier, written before the type of a uniform.
+*/
+fn is_qualifier(word: &str) -> bool {
+    matches!(word, "lowp" | "mediump" | "highp" | "readonly" | "writeonly" | "coherent" | "volatile" | "restrict")
+}
+
+/*  This function checks if a type is opaque (samplers, textures and images), so it cannot be in a uniform block.
+*/
+fn is_opaque(type_name: &str) -> bool {
+    let type_name = strip_integer_prefix(type_name);
+    type_name.starts_with("sampler") || type_name.starts_with("texture") || type_name.starts_with("image")
+}
+
+/*  This function checks if a type is a combined sampler (sampler2D, isampler3D, samplerCubeShadow...).
+*/
+fn is_combined_sampler(type_name: &str) -> bool {
+    let type_name = strip_integer_prefix(type_name);
+    type_name.starts_with("sampler") && type_name != "sampler" && type_name != "samplerShadow"
+}
+
+/*  This function removes the i or u of the integer opaque types (isampler2D becomes sampler2D, uimage2D becomes image2D).
+*   The other types are returned as they are, so image2D stays image2D.
+*/
+fn strip_integer_prefix(type_name: &str) -> &str {
+    type_name.strip_prefix('i').or_else(|| type_name.strip_prefix('u'))
+        .filter(|rest| rest.starts_with("sampler") || rest.starts_with("texture") || rest.starts_with("image"))
+        .unwrap_or(type_name)
+}
+
+/*  This function replaces every use of an identifier in a line.
+*/
+pub(crate) fn replace_identifier(line: &str, name: &str, replacement: &str) -> String {
+    let is_identifier_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(position) = rest.find(name) {
+        let before = rest[..position].chars().next_back();
+        let after = rest[position + name.len()..].chars().next();
+        result.push_str(&rest[..position]);
+        if !before.is_some_and(is_identifier_char) && !after.is_some_and(is_identifier_char) {
+            result.push_str(replacement);
+        } else {
+            result.push_str(name);
+        }
+        rest = &rest[position + name.len()..];
+    }
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::preprocessor::Preprocessor;
+
+    /*  This function preprocesses an inline shader.
+    */
+    fn process(code: &str) -> PreprocessedSource {
+        Preprocessor::new().process_code(code, "test.glsl").unwrap()
+    }
+
+    #[test]
+    fn file_stage_is_a_whole_word() {
+        assert_eq!(get_file_stage("vert.glsl"), Some(ShaderStage::Vertex));
+        assert_eq!(get_file_stage("albedo_frag.glsl"), Some(ShaderStage::Fragment));
+        assert_eq!(get_file_stage("invert_frag.glsl"), Some(ShaderStage::Fragment));
+        assert_eq!(get_file_stage("particles.comp"), Some(ShaderStage::Compute));
+        assert_eq!(get_file_stage("sky_geom.glsl"), Some(ShaderStage::Geometry));
+        assert_eq!(get_file_stage("vert2.glsl"), Some(ShaderStage::Vertex));
+        assert_eq!(get_file_stage("inverted.glsl"), None);
+        assert_eq!(get_file_stage("textures.glsl"), None);
+    }
+
+    #[test]
+    fn opaque_types_keep_their_name() {
+        assert!(is_opaque("sampler2D"));
+        assert!(is_opaque("isampler3D"));
+        assert!(is_opaque("image2D"));
+        assert!(is_opaque("uimage2D"));
+        assert!(is_opaque("texture2D"));
+        assert!(!is_opaque("int"));
+        assert!(!is_opaque("uint"));
+        assert!(!is_opaque("ivec2"));
+
+        assert!(is_combined_sampler("usampler2D"));
+        assert!(is_combined_sampler("sampler2DShadow"));
+        assert!(!is_combined_sampler("sampler"));
+        assert!(!is_combined_sampler("image2D"));
+        assert!(!is_combined_sampler("iimage2D"));
+    }
+
+    #[test]
+    fn version_and_line_are_replaced() {
+        let code = to_naga_glsl("#version 330 core\n#line 1 0\nvoid main() {}\n");
+        assert_eq!(code, "#version 450 core\n\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn loose_uniforms_are_moved_in_globals() {
+        let code = to_naga_glsl("#version 330 core\nuniform float time;\nuniform vec3 color;\nvoid main() {}\n");
+        let lines: Vec::<&str> = code.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1], "");
+        assert_eq!(lines[2], format!("layout(binding = 0) uniform {} {{ float time; vec3 color; }};", GLOBALS_BLOCK_NAME));
+    }
+
+    #[test]
+    fn globals_follow_the_structs() {
+        let code = "#version 330 core\nuniform float time;\nstruct Light {\n    vec3 color;\n};\nuniform Light light;\nstruct Unused { float value; };\nvoid main() {\n    gl_Position = vec4(time * light.color.x);\n}\nuniform float late;\n";
+        let converted = to_naga_glsl(code);
+        let lines: Vec::<&str> = converted.lines().collect();
+
+        assert_eq!(lines[1], "");
+        assert_eq!(lines[5], "");
+        assert_eq!(lines[6], format!("struct Unused {{ float value; }}; layout(binding = 0) uniform {} {{ float time; Light light; float late; }};", GLOBALS_BLOCK_NAME));
+        assert_eq!(lines[10], "");
+
+        parse_module(ShaderStage::Vertex, &process(code)).unwrap();
+    }
+
+    #[test]
+    fn uniform_initializers_are_removed() {
+        let (code, warnings) = convert_to_naga_glsl("uniform float gain = 1.0;\nuniform vec3 tint = vec3(1.0, 0.5, 0.0), offset;\nuniform float scale;\nvoid main() {}\n");
+
+        assert_eq!(code.lines().nth(2), Some(format!("layout(binding = 0) uniform {} {{ float gain; vec3 tint, offset; float scale; }};", GLOBALS_BLOCK_NAME).as_str()));
+        assert_eq!(warnings.len(), 2);
+
+        parse_module(ShaderStage::Vertex, &process("#version 330 core\nuniform float gain = 1.0;\nvoid main() {\n    gl_Position = vec4(gain);\n}\n")).unwrap();
+    }
+
+    #[test]
+    fn combined_samplers_are_split() {
+        let code = to_naga_glsl("uniform sampler2D diffuse;\nvoid main() { vec4 color = texture(diffuse, uv); }\n");
+        let lines: Vec::<&str> = code.lines().collect();
+
+        assert_eq!(lines[0], format!("layout(binding = 1) uniform texture2D diffuse; layout(binding = 2) uniform sampler diffuse{};", SAMPLER_SUFFIX));
+        assert_eq!(lines[1], format!("void main() {{ vec4 color = texture(sampler2D(diffuse, diffuse{}), uv); }}", SAMPLER_SUFFIX));
+    }
+
+    #[test]
+    fn sampler_arrays_are_split() {
+        let code = to_naga_glsl("uniform sampler2D t[2], single;\nvoid main() { vec4 color = texture(t[1], uv) + texture(t[ 0 ], uv) + texture(single, uv); }\n");
+        let lines: Vec::<&str> = code.lines().collect();
+
+        assert_eq!(lines[0], format!("layout(binding = 1) uniform texture2D t[2]; layout(binding = 2) uniform sampler t{0}[2]; layout(binding = 3) uniform texture2D single; layout(binding = 4) uniform sampler single{0};", SAMPLER_SUFFIX));
+        assert_eq!(lines[1], format!("void main() {{ vec4 color = texture(sampler2D(t[1], t{0}[1]), uv) + texture(sampler2D(t[ 0 ], t{0}[ 0 ]), uv) + texture(sampler2D(single, single{0}), uv); }}", SAMPLER_SUFFIX));
+    }
+
+    #[test]
+    fn sampler_arrays_are_valid() {
+        let processed = process("#version 330 core\nin vec2 uv;\nout vec4 color;\nuniform sampler2D t[2];\nuniform sampler2D other;\nconst int LAST = 1;\nvoid main() {\n    color = texture(t[0], uv) + texture(t[LAST], uv) + texture(other, uv);\n}\n");
+        let module = parse_module(ShaderStage::Fragment, &processed).unwrap();
+
+        let names: Vec::<&str> = module.global_variables.iter().filter_map(|(_, global)| global.name.as_deref()).collect();
+        assert!(names.contains(&"t[0]") && names.contains(&"t[1]"));
+        let mut bindings: Vec::<u32> = module.global_variables.iter().filter_map(|(_, global)| global.binding.as_ref().map(|binding| binding.binding)).collect();
+        bindings.sort_unstable();
+        bindings.dedup();
+        assert_eq!(bindings.len(), module.global_variables.iter().filter(|(_, global)| global.binding.is_some()).count());
+
+        //glsl 330 only allows constant indices.
+        let processed = process("#version 330 core\nin vec2 uv;\nout vec4 color;\nuniform sampler2D t[2];\nuniform int index;\nvoid main() {\n    color = texture(t[index], uv);\n}\n");
+        match parse_module(ShaderStage::Fragment, &processed) {
+            Err(ShaderError::Compile { log, .. }) => assert!(log.starts_with("test.glsl:7: "), "{}", log),
+            result => panic!("Expected a compile error, found {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn images_and_blocks_get_a_binding() {
+        let code = to_naga_glsl("layout(rgba8) uniform image2D target;\nuniform Camera { mat4 view; };\nlayout(binding = 5) uniform image2D fixed_target;\n");
+        let lines: Vec::<&str> = code.lines().collect();
+
+        assert_eq!(lines[0], "layout(binding = 1, rgba8) uniform image2D target;");
+        assert_eq!(lines[1], "layout(binding = 2) uniform Camera { mat4 view; };");
+        assert_eq!(lines[2], "layout(binding = 5) uniform image2D fixed_target;");
+        assert!(!code.contains(GLOBALS_BLOCK_NAME));
+    }
+
+    #[test]
+    fn inputs_and_outputs_get_a_location() {
+        let code = to_naga_glsl("layout(location = 2) in vec3 normal;\nin vec2 uv;\nflat out int id;\nlayout(std140) out vec4 color;\nvoid main() {\n    float in_value = 1.0;\n}\n");
+        let lines: Vec::<&str> = code.lines().collect();
+
+        assert_eq!(lines[0], "layout(location = 2) in vec3 normal;");
+        assert_eq!(lines[1], "layout(location = 3) in vec2 uv;");
+        assert_eq!(lines[2], "layout(location = 0) flat out int id;");
+        assert_eq!(lines[3], "layout(location = 1, std140) out vec4 color;");
+        assert_eq!(lines[5], "    float in_value = 1.0;");
+    }
+
+    #[test]
+    fn compute_shader_with_image_is_valid() {
+        let processed = process("#version 430 core\nlayout(local_size_x = 8, local_size_y = 8) in;\nlayout(rgba8) uniform writeonly image2D target;\nuniform float time;\nvoid main() {\n    imageStore(target, ivec2(gl_GlobalInvocationID.xy), vec4(time));\n}\n");
+
+        parse_module(ShaderStage::Compute, &processed).unwrap();
+
+        let processed = process("#version 430 core\nlayout(local_size_x = 1) in;\nlayout(rgba8) uniform image2D img;\nvoid main() {\n    imageStore(img, ivec2(0), vec4(1.0));\n}\n");
+        parse_module(ShaderStage::Compute, &processed).unwrap();
+    }
+
+    #[test]
+    fn entry_point_is_found() {
+        assert!(has_entry_point(&process("#version 330 core\nvoid main ()\n{\n}\n")));
+        assert!(!has_entry_point(&process("uniform sampler2D texture1;\n// main() is in the shader\n")));
+    }
+}
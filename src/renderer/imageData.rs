@@ -0,0 +1,273 @@
+/*  File: renderer/imageData.rs
+*   Author: Vicix
+*
+*   This file contains the ImageData class.
+*   The class ImageData is a decoded image: its size, its color type and the raw pixels, ready to be given to opengl.
+*   The image crate converts every image to 8 bits in a DynamicImage, so the 16 bit pngs are decoded directly with the png crate (the version used by image).
+*   The 16 bit pixels are stored in the native byte order, the one expected by gl::UNSIGNED_SHORT.
+*/
+
+extern crate image;
+use image::{ColorType, DynamicImage, GenericImage, ImageBuffer, ImageError, ImageFormat};
+
+extern crate png;
+use png::HasParameters;
+
+use crate::renderer::errors::TextureError;
+
+use std::fs;
+
+/*  This is the position of the bit depth in a png file (after the signature and the start of the IHDR chunk).
+*/
+const PNG_BIT_DEPTH_OFFSET: usize = 24;
+
+/*  This is the declaration of the class.
+*   The rows are stored from the top to the bottom, like in the image file.
+*/
+#[derive(Debug, Clone)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub color: ColorType,
+    pub pixels: Vec::<u8>,
+}
+
+#[allow(dead_code)]
+impl ImageData {
+    /*  This constructor reads and decodes an image file.
+    *   It returns TextureError::Io if the file cannot be read, TextureError::UnsupportedFormat if the format or the color type cannot be decoded
+    *   and TextureError::Decode if the image is corrupted or empty.
+    */
+    pub fn load(path: &str) -> Result<ImageData, TextureError> {
+        let bytes = fs::read(path).map_err(|error| TextureError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+
+        let is_png16 = image::guess_format(&bytes).ok() == Some(ImageFormat::PNG) && bytes.get(PNG_BIT_DEPTH_OFFSET) == Some(&16);
+        let image = if is_png16 {
+            decode_png16(&bytes)
+        } else {
+            image::load_from_memory(&bytes).map(|image| ImageData::from_dynamic_image(&image))
+        }.map_err(|error| convert_error(path, error))?;
+
+        if image.is_empty() {
+            return Err(TextureError::Decode {
+                path: path.to_string(),
+                message: "The image is empty.".to_string(),
+            });
+        }
+
+        Ok(image)
+    }
+
+    /*  This constructor copies the pixels of a DynamicImage.
+    */
+    pub fn from_dynamic_image(image: &DynamicImage) -> ImageData {
+        ImageData {
+            width: image.width(),
+            height: image.height(),
+            color: image.color(),
+            pixels: image.raw_pixels(),
+        }
+    }
+
+    /*  This function copies the pixels in a DynamicImage.
+    *   It returns None for the color types that a DynamicImage cannot contain (palettes and channels that are not 8 bits).
+    */
+    pub fn to_dynamic_image(&self) -> Option<DynamicImage> {
+        let pixels = self.pixels.clone();
+        match self.color {
+            ColorType::Gray(8) => ImageBuffer::from_raw(self.width, self.height, pixels).map(DynamicImage::ImageLuma8),
+            ColorType::GrayA(8) => ImageBuffer::from_raw(self.width, self.height, pixels).map(DynamicImage::ImageLumaA8),
+            ColorType::RGB(8) => ImageBuffer::from_raw(self.width, self.height, pixels).map(DynamicImage::ImageRgb8),
+            ColorType::RGBA(8) => ImageBuffer::from_raw(self.width, self.height, pixels).map(DynamicImage::ImageRgba8),
+            _ => None,
+        }
+    }
+
+    /*  This function returns the size of a pixel in bytes.
+    */
+    pub fn get_bytes_per_pixel(&self) -> usize {
+        get_bytes_per_pixel(self.color)
+    }
+
+    /*  This function checks if the image has no pixels.
+    */
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0 || self.pixels.is_empty()
+    }
+
+    /*  This function copies a rectangle of the image, with the top left corner in x, y.
+    *   It returns None if the rectangle is not inside the image.
+    */
+    pub fn get_region(&self, x: u32, y: u32, width: u32, height: u32) -> Option<ImageData> {
+        if x.checked_add(width)? > self.width || y.checked_add(height)? > self.height {
+            return None;
+        }
+
+        let pixel_size = self.get_bytes_per_pixel();
+        let row_size = self.width as usize * pixel_size;
+        let region_row_size = width as usize * pixel_size;
+        let mut pixels = Vec::<u8>::with_capacity(region_row_size * height as usize);
+        for row in y as usize..(y + height) as usize {
+            let start = row * row_size + x as usize * pixel_size;
+            pixels.extend_from_slice(&self.pixels[start..start + region_row_size]);
+        }
+
+        Some(ImageData {
+            width,
+            height,
+            color: self.color,
+            pixels,
+        })
+    }
+
+    /*  This function cuts the image in a grid of columns * rows images of the same size, from left to right and from top to bottom.
+    *   It returns None if the grid is empty or the size of the image is not a multiple of the grid.
+    */
+    pub fn split_sheet(&self, columns: u32, rows: u32) -> Option<Vec::<ImageData>> {
+        if columns == 0 || rows == 0 || self.width % columns != 0 || self.height % rows != 0 {
+            return None;
+        }
+
+        let (width, height) = (self.width / columns, self.height / rows);
+        (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| self.get_region(column * width, row * height, width, height))
+            .collect()
+    }
+
+    /*  This function converts the image to 8 bit rgba. The 16 bit channels keep their highest 8 bits.
+    *   It returns None for the palette images and the images with less than 8 bits.
+    */
+    pub fn to_rgba8(&self) -> Option<ImageData> {
+        let channel_size = match get_bit_depth(self.color) {
+            8 => 1,
+            16 => 2,
+            _ => return None,
+        };
+        let expand = match self.color {
+            ColorType::Gray(_) => |channels: &[u8]| [channels[0], channels[0], channels[0], 255],
+            ColorType::GrayA(_) => |channels: &[u8]| [channels[0], channels[0], channels[0], channels[1]],
+            ColorType::RGB(_) => |channels: &[u8]| [channels[0], channels[1], channels[2], 255],
+            ColorType::RGBA(_) => |channels: &[u8]| [channels[0], channels[1], channels[2], channels[3]],
+            ColorType::Palette(_) => return None,
+        };
+
+        let mut pixels = Vec::<u8>::with_capacity(self.width as usize * self.height as usize * 4);
+        for pixel in self.pixels.chunks_exact(self.get_bytes_per_pixel()) {
+            let channels = pixel.chunks_exact(channel_size).map(|channel| match channel {
+                [high, low] => (u16::from_ne_bytes([*high, *low]) >> 8) as u8,
+                _ => channel[0],
+            }).collect::<Vec::<u8>>();
+            pixels.extend_from_slice(&expand(&channels));
+        }
+
+        Some(ImageData {
+            width: self.width,
+            height: self.height,
+            color: ColorType::RGBA(8),
+            pixels,
+        })
+    }
+
+    /*  This function flips the image upside down.
+    */
+    pub fn flip_vertical(&mut self) {
+        let row_size = self.width as usize * self.get_bytes_per_pixel();
+        let height = self.height as usize;
+        for y in 0..height / 2 {
+            let (top, bottom) = self.pixels.split_at_mut((height - 1 - y) * row_size);
+            top[y * row_size..(y + 1) * row_size].swap_with_slice(&mut bottom[..row_size]);
+        }
+    }
+
+    /*  This function flips the image from left to right.
+    */
+    pub fn flip_horizontal(&mut self) {
+        let pixel_size = self.get_bytes_per_pixel();
+        let row_size = self.width as usize * pixel_size;
+        for row in self.pixels.chunks_exact_mut(row_size) {
+            let width = row.len() / pixel_size;
+            for x in 0..width / 2 {
+                let (left, right) = row.split_at_mut((width - 1 - x) * pixel_size);
+                left[x * pixel_size..(x + 1) * pixel_size].swap_with_slice(&mut right[..pixel_size]);
+            }
+        }
+    }
+}
+
+/*  This function returns the bits of every channel of a color type.
+*/
+pub fn get_bit_depth(color: ColorType) -> u8 {
+    match color {
+        ColorType::Gray(bits) | ColorType::GrayA(bits) | ColorType::RGB(bits) | ColorType::RGBA(bits) | ColorType::Palette(bits) => bits,
+    }
+}
+
+/*  This function returns the size of a pixel of a color type in bytes (rounded up for the images with less than 8 bits).
+*/
+pub fn get_bytes_per_pixel(color: ColorType) -> usize {
+    (get_channel_count(color) * get_bit_depth(color) as usize).div_ceil(8)
+}
+
+/*  This function returns the number of channels of a color type (a palette image has only the index).
+*/
+pub fn get_channel_count(color: ColorType) -> usize {
+    match color {
+        ColorType::Gray(_) | ColorType::Palette(_) => 1,
+        ColorType::GrayA(_) => 2,
+        ColorType::RGB(_) => 3,
+        ColorType::RGBA(_) => 4,
+    }
+}
+
+/*  This function decodes a 16 bit png without converting it to 8 bits, then converts the big endian pixels of the file in the native byte order.
+*/
+fn decode_png16(bytes: &[u8]) -> Result<ImageData, ImageError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set(png::Transformations::IDENTITY);
+    let (info, mut reader) = decoder.read_info()?;
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels)?;
+
+    let (width, height) = (info.width, info.height);
+    let color = match info.color_type {
+        png::ColorType::Grayscale => ColorType::Gray(16),
+        png::ColorType::GrayscaleAlpha => ColorType::GrayA(16),
+        png::ColorType::RGB => ColorType::RGB(16),
+        png::ColorType::RGBA => ColorType::RGBA(16),
+        png::ColorType::Indexed => ColorType::Palette(16),
+    };
+
+    for value in pixels.chunks_exact_mut(2) {
+        let native = u16::from_be_bytes([value[0], value[1]]).to_ne_bytes();
+        value.copy_from_slice(&native);
+    }
+
+    Ok(ImageData {
+        width,
+        height,
+        color,
+        pixels,
+    })
+}
+
+/*  This function converts the errors of the image crate.
+*/
+fn convert_error(path: &str, error: ImageError) -> TextureError {
+    match error {
+        ImageError::IoError(error) => TextureError::Io {
+            path: path.to_string(),
+            error,
+        },
+        ImageError::UnsupportedError(_) | ImageError::UnsupportedColor(_) => TextureError::UnsupportedFormat {
+            path: path.to_string(),
+            message: error.to_string(),
+        },
+        error => TextureError::Decode {
+            path: path.to_string(),
+            message: error.to_string(),
+        },
+    }
+}
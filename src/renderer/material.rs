@@ -2,45 +2,108 @@
 *   Author: Vicix
 *
 *   This file contains a class called Material.
-*   Material holds a Shader and an array of textures. 
+*   Material holds a shader (a Shader, a ProgramPipeline or anything implementing ShaderTrait) and an array of textures. 
+*   Every texture slot can have a Sampler, bound to the unit of the texture, so any texture can be read with any sampler.
 *   It makes easier to apply textures and shaders.
 */
 extern crate gl;
 use self::gl::types::*;
 
-use crate::renderer::shader;
 use crate::renderer::texture;
+use crate::renderer::sampler::Sampler;
+use crate::renderer::shaderVariants::ShaderVariants;
+use crate::renderer::errors::ShaderError;
 use crate::renderer::traits::shaderTrait::ShaderTrait;
 use crate::renderer::traits::textureTrait::TextureTrait;
+use crate::renderer::uniform::UniformValue;
 
 /*  This is the declaration of the class.
 *   It holds an array of textures. 
-*   For each texture we have a string (used for the uniform name) at the bind moment and a boolean value.
+*   For each texture we have an optional sampler, a string (used for the uniform name) at the bind moment and a boolean value.
 *   This value is true if the texture uniform has already been bound.
+*   It also stores the program ids used in the last bind. If the shader is reloaded an id changes and the texture uniforms are created again.
+*   If the material is created from ShaderVariants it keeps them, so another variant can be selected with set_keywords.
 */
 pub struct Material {
-    shader: shader::Shader,
-    textures: Vec::<(texture::Texture, String, bool)>,
+    shader: Box<dyn ShaderTrait>,
+    textures: Vec::<(texture::Texture, Option<Sampler>, String, bool)>,
+    program_ids: Vec::<GLuint>,
+    variants: Option<ShaderVariants>,
+    keywords: Vec::<String>,
 }
 
 #[allow(dead_code)]
 impl Material {
     /*  The constructor of the class.
     *   It takes a shader, makes a clone and creates a new textures vector.
+    *   Note: the clones of a Shader and of a ProgramPipeline share the program, so the material sees the reloads.
     */
-    pub fn new(shader: &shader::Shader) -> Material {
+    pub fn new<S: ShaderTrait + Clone + 'static>(shader: &S) -> Material {
         Material {
-            shader: shader.clone(),
-            textures: Vec::<(texture::Texture, String, bool)>::new(),
+            shader: Box::new(shader.clone()),
+            textures: Vec::<(texture::Texture, Option<Sampler>, String, bool)>::new(),
+            program_ids: shader.get_program_ids(),
+            variants: None,
+            keywords: Vec::<String>::new(),
         }
     }
 
+    /*  This constructor creates a material that uses the variant of a keyword set.
+    *   It returns an error if the keywords are invalid or the variant does not compile.
+    */
+    pub fn from_variants(variants: &ShaderVariants, keywords: &[&str]) -> Result<Material, ShaderError> {
+        let mut material = Material::new(&variants.get_variant(keywords)?);
+        material.variants = Some(variants.clone());
+        material.keywords = keywords.iter().map(|keyword| keyword.to_string()).collect();
+
+        Ok(material)
+    }
+
+    /*  This function selects the variant of another keyword set.
+    *   The texture uniforms are created again at the next bind, the other uniforms must be set again.
+    *   If it fails the current variant is kept. A material created without variants returns ShaderError::InvalidKeywords.
+    */
+    pub fn set_keywords(&mut self, keywords: &[&str]) -> Result<(), ShaderError> {
+        let variants = match &self.variants {
+            Some(variants) => variants,
+            None => return Err(ShaderError::InvalidKeywords("The material has no shader variants.".to_string())),
+        };
+
+        self.shader = Box::new(variants.get_variant(keywords)?);
+        self.keywords = keywords.iter().map(|keyword| keyword.to_string()).collect();
+
+        Ok(())
+    }
+
+    /*  This function returns the keywords of the selected variant.
+    */
+    pub fn get_keywords_ref(&self) -> &Vec::<String> {
+        &self.keywords
+    }
+
     /*  This function is used to push a texture in the textures vector. 
     *   It also takes a uniform name, used in the binding.
     *   Note: the boolean value is always false, because the texture cannot be alreaby bound.
     */
     pub fn push_texture(&mut self, texture: &texture::Texture, uniform_name: &str) {
-        self.textures.push((texture.clone(), uniform_name.to_string(), false));
+        self.textures.push((texture.clone(), None, uniform_name.to_string(), false));
+    }
+
+    /*  This function is like push_texture, but the texture is read with a sampler instead of its own filters and wrap.
+    *   The sampler is bound to the active texture number of the texture.
+    */
+    pub fn push_texture_with_sampler(&mut self, texture: &texture::Texture, sampler: &Sampler, uniform_name: &str) {
+        self.textures.push((texture.clone(), Some(sampler.clone()), uniform_name.to_string(), false));
+    }
+
+    /*  This function changes the sampler of a texture slot (in the push order), None goes back to the parameters of the texture.
+    *   It does nothing if the slot doesn't exist.
+    */
+    pub fn set_sampler(&mut self, slot: usize, sampler: Option<&Sampler>) {
+        match self.textures.get_mut(slot) {
+            Some((_, slot_sampler, _, _)) => *slot_sampler = sampler.cloned(),
+            None => println!("The material has no texture slot {}.", slot),
+        }
     }
 
     /*  This function pops the pushed texture.
@@ -51,14 +114,28 @@ impl Material {
 
     /*  This function binds the shader and the textures.
     *   It also create uniforms for the to not bound textures.Material
+    *   If the shader has been reloaded since the last bind every texture uniform is created again.
     */
     pub fn bind(&mut self) {
         self.shader.bind();
-        
-        for (texture, uniform_name, bound) in &mut self.textures {
+
+        let program_ids = self.shader.get_program_ids();
+        if self.program_ids != program_ids {
+            self.program_ids = program_ids;
+            for (_, _, _, bound) in &mut self.textures {
+                *bound = false;
+            }
+        }
+
+        for (texture, sampler, uniform_name, bound) in &mut self.textures {
             texture.bind();
+            let texture_unit = texture.get_gl_properties_ref().active_texture_number;
+            match sampler {
+                Some(sampler) => sampler.bind(texture_unit),
+                None => Sampler::unbind(texture_unit),
+            }
             if !*bound {
-                texture.create_uniform_from_name(&self.shader, &uniform_name.as_str());
+                texture.create_uniform_from_name(self.shader.as_ref(), &uniform_name.as_str());
                 *bound = true;
             }
         }
@@ -84,4 +161,11 @@ impl Material {
     pub fn set_float_uniform(&self, uniform_name: &str, uniform_value: f32) {
         self.shader.set_float_uniform(uniform_name, uniform_value);
     }
+
+    /*  This function is a link to the set_uniform function in renderer::shader.
+    *   We cannot access the shader in the Material so we must expose the function.
+    */
+    pub fn set_uniform<T: UniformValue>(&self, uniform_name: &str, uniform_value: T) {
+        self.shader.set_uniform_value(uniform_name, &uniform_value);
+    }
 }
\ No newline at end of file
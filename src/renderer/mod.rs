@@ -10,6 +10,53 @@ pub mod vbo;
 pub mod ebo;
 pub mod globject;
 pub mod material;
+pub mod errors;
+pub mod preprocessor;
+pub mod reflection;
+pub mod uniform;
+pub mod std140;
+pub mod ubo;
+pub mod ssbo;
+pub mod compute;
+pub mod capabilities;
+pub mod spirv;
+pub mod cubemap;
+pub mod skybox;
+pub mod readback;
+pub mod sampler;
+
+#[allow(non_snake_case)]
+pub mod shaderBuilder;
+#[allow(non_snake_case)]
+pub mod programCache;
+#[allow(non_snake_case)]
+pub mod shaderVariants;
+#[allow(non_snake_case)]
+pub mod transformFeedback;
+#[allow(non_snake_case)]
+pub mod programPipeline;
+#[allow(non_snake_case)]
+pub mod vertexInput;
+#[allow(non_snake_case)]
+pub mod imageData;
+#[allow(non_snake_case)]
+pub mod textureFormat;
+#[allow(non_snake_case)]
+pub mod compressedImage;
+#[allow(non_snake_case)]
+pub mod blockDecoder;
+#[allow(non_snake_case)]
+pub mod atlasPacker;
+#[allow(non_snake_case)]
+pub mod textureAtlas;
+#[allow(non_snake_case)]
+pub mod textureLoader;
+#[cfg(feature = "shader_tools")]
+#[allow(non_snake_case)]
+pub mod glslFrontend;
+#[cfg(feature = "shader_tools")]
+#[allow(non_snake_case)]
+pub mod shaderTranslator;
 
 pub mod constants;
 pub mod properties;
@@ -0,0 +1,247 @@
+/*  File: renderer/preprocessor.rs
+*   Author: Vicix
+*
+*   This file contains the Preprocessor class.
+*   The class Preprocessor resolves the #include "file" directives of a glsl source and injects the #defines given by the caller.
+*   It emits #line directives, so the errors of the compiler point to the original file and line.
+*   It does not use opengl.
+*/
+
+use crate::renderer::errors::ShaderError;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/*  This is the declaration of the class.
+*   It only contains the defines to inject after the #version line.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessor {
+    defines: Vec::<(String, String)>,
+}
+
+/*  This is the result of the preprocessor.
+*   code is the glsl code ready to be compiled.
+*   files contains the path of every file used. The index of a file is the source string number used in its #line directives.
+*/
+#[derive(Debug, Clone)]
+pub struct PreprocessedSource {
+    pub code: String,
+    pub files: Vec::<String>,
+}
+
+#[allow(dead_code)]
+impl Preprocessor {
+    /*  The constructor of the class.
+    *   It creates a preprocessor without defines.
+    */
+    pub fn new() -> Preprocessor {
+        Preprocessor {
+            defines: Vec::<(String, String)>::new(),
+        }
+    }
+
+    /*  This function adds a define. It will be injected as "#define name value".
+    */
+    pub fn define(&mut self, name: &str, value: &str) {
+        self.defines.push((name.to_string(), value.to_string()));
+    }
+
+    /*  This function returns the defines.
+    */
+    pub fn get_defines_ref(&self) -> &Vec::<(String, String)> {
+        &self.defines
+    }
+
+    /*  This function reads and preprocesses a file.
+    *   The includes are resolved relative to the directory of the file.
+    */
+    pub fn process_file(&self, path: &str) -> Result<PreprocessedSource, ShaderError> {
+        let code = read_file(path)?;
+
+        self.process_code(&code, path)
+    }
+
+    /*  This function preprocesses some glsl code.
+    *   The name is used in the errors and to resolve the includes: they are relative to the directory of name.
+    *   First the defines are injected after the #version line (or at the top if there isn't one), then the includes are resolved recursively.
+    */
+    pub fn process_code(&self, code: &str, name: &str) -> Result<PreprocessedSource, ShaderError> {
+        let mut result = PreprocessedSource {
+            code: String::new(),
+            files: vec![name.to_string()],
+        };
+
+        let lines: Vec::<&str> = code.lines().collect();
+        let version_line = lines.iter().position(|line| line.trim_start().starts_with("#version"));
+
+        let first_line = match version_line {
+            Some(version_line) => {
+                for line in &lines[..=version_line] {
+                    result.code.push_str(line);
+                    result.code.push('\n');
+                }
+                version_line + 1
+            },
+            None => 0,
+        };
+
+        for (define_name, define_value) in &self.defines {
+            result.code.push_str(&format!("#define {} {}\n", define_name, define_value));
+        }
+        result.code.push_str(&format!("#line {} 0\n", first_line + 1));
+
+        let mut include_stack = vec![canonical_path(name)];
+        process_lines(&lines, first_line, 0, name, &mut include_stack, &mut result)?;
+
+        Ok(result)
+    }
+}
+
+#[allow(dead_code)]
+impl PreprocessedSource {
+    /*  This function replaces the source string numbers in a compiler log with the file paths.
+    *   It understands the most common formats: "0(12) : error" (nvidia), "0:12(5): error" (mesa) and "ERROR: 0:12: " (amd and intel).
+    */
+    pub fn resolve_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.resolve_log_line(line))
+            .collect::<Vec::<String>>()
+            .join("\n")
+    }
+
+    /*  This function returns the original file and line of a line of the preprocessed code (both line numbers start from 1).
+    *   It follows the #line directives emitted by the preprocessor.
+    */
+    pub fn get_original_line(&self, line_number: usize) -> (String, usize) {
+        let mut file_index = 0;
+        let mut original_line = 1;
+        for line in self.code.lines().take(line_number.saturating_sub(1)) {
+            let mut directive = line.trim_start().strip_prefix("#line").map(|rest| rest.split_whitespace());
+            match directive.as_mut().and_then(|words| words.next()).and_then(|word| word.parse::<usize>().ok()) {
+                Some(next_line) => {
+                    original_line = next_line;
+                    if let Some(index) = directive.and_then(|mut words| words.next()).and_then(|word| word.parse::<usize>().ok()) {
+                        file_index = index;
+                    }
+                },
+                None => original_line += 1,
+            }
+        }
+
+        (self.files.get(file_index).cloned().unwrap_or_default(), original_line)
+    }
+
+    /*  This function replaces the first source string number of a log line.
+    *   A source string number is a group of digits at the start of the line or after a space, followed by ':' or '(' and another digit.
+    */
+    fn resolve_log_line(&self, line: &str) -> String {
+        let bytes = line.as_bytes();
+        let mut start = 0;
+        while start < bytes.len() {
+            if (start == 0 || bytes[start - 1] == b' ') && bytes[start].is_ascii_digit() {
+                let mut end = start;
+                while end < bytes.len() && bytes[end].is_ascii_digit() {
+                    end += 1;
+                }
+
+                let followed_by_line = end + 1 < bytes.len()
+                    && (bytes[end] == b':' || bytes[end] == b'(')
+                    && bytes[end + 1].is_ascii_digit();
+                if followed_by_line {
+                    if let Some(file) = line[start..end].parse::<usize>().ok().and_then(|index| self.files.get(index)) {
+                        return format!("{}{}{}", &line[..start], file, &line[end..]);
+                    }
+                }
+
+                start = end;
+            } else {
+                start += 1;
+            }
+        }
+
+        line.to_string()
+    }
+}
+
+/*  This function copies the lines of a file in the result, starting from first_line, and resolves the includes.
+*   file_index is the source string number of the file and include_stack contains the files that are being included, used to find the cycles.
+*   Note: #version lines in the included files are replaced with empty lines.
+*/
+fn process_lines(lines: &[&str], first_line: usize, file_index: usize, path: &str, include_stack: &mut Vec::<PathBuf>, result: &mut PreprocessedSource) -> Result<(), ShaderError> {
+    for (line_number, line) in lines.iter().enumerate().skip(first_line) {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("#include") {
+            let include_name = parse_include(trimmed).ok_or_else(|| ShaderError::Preprocess {
+                path: path.to_string(),
+                line: line_number + 1,
+                message: "Expected #include \"file\".".to_string(),
+            })?;
+
+            let include_path = Path::new(path).parent().unwrap_or_else(|| Path::new("")).join(include_name);
+            let include_path_string = include_path.to_string_lossy().into_owned();
+            let include_canonical_path = canonical_path(&include_path_string);
+
+            if include_stack.contains(&include_canonical_path) {
+                let mut cycle: Vec::<String> = include_stack.iter().map(|file| file.to_string_lossy().into_owned()).collect();
+                cycle.push(include_canonical_path.to_string_lossy().into_owned());
+                return Err(ShaderError::Preprocess {
+                    path: path.to_string(),
+                    line: line_number + 1,
+                    message: format!("Include cycle: {}", cycle.join(" -> ")),
+                });
+            }
+
+            let include_code = read_file(&include_path_string)?;
+            let include_index = match result.files.iter().position(|file| *file == include_path_string) {
+                Some(include_index) => include_index,
+                None => {
+                    result.files.push(include_path_string.clone());
+                    result.files.len() - 1
+                },
+            };
+
+            result.code.push_str(&format!("#line 1 {}\n", include_index));
+            include_stack.push(include_canonical_path);
+            let include_lines: Vec::<&str> = include_code.lines().collect();
+            process_lines(&include_lines, 0, include_index, &include_path_string, include_stack, result)?;
+            include_stack.pop();
+            result.code.push_str(&format!("#line {} {}\n", line_number + 2, file_index));
+        } else if file_index != 0 && trimmed.starts_with("#version") {
+            result.code.push('\n');
+        } else {
+            result.code.push_str(line);
+            result.code.push('\n');
+        }
+    }
+
+    Ok(())
+}
+
+/*  This function extracts the file name from an #include "file" line.
+*/
+fn parse_include(line: &str) -> Option<&str> {
+    let rest = line["#include".len()..].trim();
+    if rest.len() >= 2 && rest.starts_with('"') {
+        let end = rest[1..].find('"')? + 1;
+        if rest[end + 1..].trim().is_empty() || rest[end + 1..].trim().starts_with("//") {
+            return Some(&rest[1..end]);
+        }
+    }
+
+    None
+}
+
+/*  This function returns the canonical path of a file, used to compare the includes.
+*   If the file does not exist the path is returned as it is: the error is reported when the file is read.
+*/
+fn canonical_path(path: &str) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/*  This function reads a whole file into a string.
+*/
+fn read_file(path: &str) -> Result<String, ShaderError> {
+    fs::read_to_string(path).map_err(|error| ShaderError::Io { path: path.to_string(), error })
+}
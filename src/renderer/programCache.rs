@@ -0,0 +1,176 @@
+/*  File: renderer/programCache.rs
+*   Author: Vicix
+*
+*   This file contains the ProgramCache class.
+*   The class ProgramCache saves the linked programs in a directory using gl::GetProgramBinary and loads them back with gl::ProgramBinary.
+*   Every program is saved in a file named after a hash of the driver string and of the preprocessed sources,
+*   so a program is recompiled when a source or the driver changes.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::shader::ShaderStage;
+
+use std::ffi::{c_void, CStr};
+use std::fs;
+use std::os::raw::c_char;
+use std::path::PathBuf;
+
+/*  This is the declaration of the class.
+*   It only contains the cache directory.
+*/
+#[derive(Debug, Clone)]
+pub struct ProgramCache {
+    directory: PathBuf,
+}
+
+#[allow(dead_code)]
+impl ProgramCache {
+    /*  The constructor of the class.
+    *   The directory is created when the first program is saved.
+    */
+    pub fn new(directory: &str) -> ProgramCache {
+        ProgramCache {
+            directory: PathBuf::from(directory),
+        }
+    }
+
+    /*  This function checks if the driver can save and load program binaries (opengl 4.1 or ARB_get_program_binary).
+    */
+    pub fn is_supported() -> bool {
+        if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+            return false;
+        }
+
+        let mut format_count = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut format_count);
+        }
+
+        format_count > 0
+    }
+
+    /*  This function computes the key of a program from the driver string, the preprocessed code of every stage and the link parameters.
+    *   The link parameters describe the state set before linking that is saved in the binary (for example the transform feedback varyings).
+    */
+    pub fn get_key(sources: &[(ShaderStage, &[u8])], link_parameters: &str) -> u64 {
+        let mut hash = Fnv1a::new();
+        hash.write(get_driver_string().as_bytes());
+        hash.write(&[0]);
+        hash.write(link_parameters.as_bytes());
+        for (stage, code) in sources {
+            hash.write(stage.to_string().as_bytes());
+            hash.write(&[0]);
+            hash.write(code);
+            hash.write(&[0]);
+        }
+
+        hash.finish()
+    }
+
+    /*  This function loads a program from the cache.
+    *   It returns None if there is no file for the key or if the driver rejects the binary. In this case the program must be compiled.
+    */
+    pub fn load(&self, key: u64) -> Option<GLuint> {
+        let data = fs::read(self.get_path(key)).ok()?;
+        if data.len() <= 4 {
+            return None;
+        }
+
+        let format = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let binary = &data[4..];
+
+        unsafe {
+            let program = gl::CreateProgram();
+            gl::ProgramBinary(program, format, binary.as_ptr() as *const c_void, binary.len() as GLsizei);
+
+            let mut success = 0;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+            if success != gl::TRUE as GLint {
+                gl::DeleteProgram(program);
+                return None;
+            }
+
+            Some(program)
+        }
+    }
+
+    /*  This function saves a linked program in the cache.
+    *   The program must have been linked with gl::PROGRAM_BINARY_RETRIEVABLE_HINT set.
+    *   The file contains the binary format (4 bytes, little endian) followed by the binary.
+    *   A failure is only printed: the program will be compiled again the next time.
+    */
+    pub fn save(&self, key: u64, program: GLuint) {
+        let mut data = Vec::<u8>::new();
+
+        unsafe {
+            let mut length = 0;
+            gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut length);
+            if length <= 0 {
+                println!("Failed to get the binary of shader {}.", program);
+                return;
+            }
+
+            let mut binary: Vec::<u8> = vec![0; length as usize];
+            let mut format = 0;
+            gl::GetProgramBinary(program, length, &mut length, &mut format, binary.as_mut_ptr() as *mut c_void);
+            binary.truncate(length.max(0) as usize);
+
+            data.extend_from_slice(&format.to_le_bytes());
+            data.extend_from_slice(&binary);
+        }
+
+        let result = fs::create_dir_all(&self.directory).and_then(|_| fs::write(self.get_path(key), &data));
+        if let Err(error) = result {
+            println!("Failed to save the binary of shader {} in {}: {}", program, self.directory.display(), error);
+        }
+    }
+
+    /*  This function returns the path of the file of a key.
+    */
+    fn get_path(&self, key: u64) -> PathBuf {
+        self.directory.join(format!("{:016x}.bin", key))
+    }
+}
+
+/*  This function returns the vendor, the renderer and the version of the driver.
+*/
+fn get_driver_string() -> String {
+    let get_string = |name: GLenum| unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(string as *const c_char).to_string_lossy().into_owned()
+        }
+    };
+
+    format!("{}|{}|{}", get_string(gl::VENDOR), get_string(gl::RENDERER), get_string(gl::VERSION))
+}
+
+/*  This is a simple FNV-1a hash.
+*   It is used instead of the standard hasher because the keys must be the same in every run and with every version of rust.
+*/
+struct Fnv1a {
+    hash: u64,
+}
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
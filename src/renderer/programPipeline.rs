@@ -0,0 +1,252 @@
+/*  File: renderer/programPipeline.rs
+*   Author: Vicix
+*
+*   This file contains the ProgramPipeline class.
+*   The class ProgramPipeline mixes the stages of separable programs (see Shader::separable and ShaderBuilder::with_separable),
+*   so a vertex shader can be used with many fragment shaders without linking every pair.
+*   It implements ShaderTrait, so it can be used like a Shader, for example in a Material.
+*   It needs opengl 4.1 or ARB_separate_shader_objects.
+*
+*   Example:
+*       let vertex = Shader::separable(ShaderStage::Vertex, "./src/shaders/vert2.glsl")?;
+*       let fragment = Shader::separable(ShaderStage::Fragment, "./src/shaders/frag.glsl")?;
+*       let pipeline = ProgramPipeline::new()?.with_stage(ShaderStage::Vertex, &vertex).with_stage(ShaderStage::Fragment, &fragment);
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::shader::{Shader, ShaderStage};
+use crate::renderer::errors::ShaderError;
+use crate::renderer::capabilities;
+use crate::renderer::uniform::UniformValue;
+use crate::renderer::traits::shaderTrait::ShaderTrait;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::ptr;
+use std::rc::Rc;
+
+/*  This is the declaration of the class.
+*   stages contains the shader used by every stage and the program id given to opengl, so the pipeline is updated when a shader is reloaded.
+*   The stages are shared between the clones, like the program of a Shader.
+*   warned_uniforms contains the uniforms that have already been reported as missing in every stage.
+*/
+#[derive(Debug, Clone)]
+pub struct ProgramPipeline {
+    pipeline_id: GLuint,
+    stages: Rc<RefCell<Vec::<(ShaderStage, Shader, GLuint)>>>,
+    warned_uniforms: Rc<RefCell<HashSet<String>>>,
+}
+
+#[allow(dead_code)]
+impl ProgramPipeline {
+    /*  The constructor of the class.
+    *   It creates an empty pipeline. It returns ShaderError::Unsupported if the context does not support program pipelines.
+    */
+    pub fn new() -> Result<ProgramPipeline, ShaderError> {
+        if !capabilities::is_separate_shader_supported() {
+            let (major, minor) = capabilities::get_gl_version();
+            return Err(ShaderError::Unsupported(format!("Program pipelines need opengl 4.1 or ARB_separate_shader_objects, the context is {}.{}.", major, minor)));
+        }
+
+        let mut pipeline = 1;
+        unsafe {
+            gl::GenProgramPipelines(1, &mut pipeline);
+        }
+
+        Ok(ProgramPipeline {
+            pipeline_id: pipeline,
+            stages: Rc::new(RefCell::new(Vec::new())),
+            warned_uniforms: Rc::new(RefCell::new(HashSet::new())),
+        })
+    }
+
+    /*  This function uses a stage of a separable program in the pipeline, replacing the previous program of that stage.
+    *   It prints a warning if the program is not separable or does not contain the stage.
+    */
+    pub fn with_stage(self, stage: ShaderStage, shader: &Shader) -> ProgramPipeline {
+        self.set_stage(stage, shader);
+        self
+    }
+
+    /*  This function is like with_stage, but it can be used after the pipeline has been created (the clones see the change).
+    */
+    pub fn set_stage(&self, stage: ShaderStage, shader: &Shader) {
+        let mut separable = 0;
+        unsafe {
+            gl::GetProgramiv(shader.get_program_id(), gl::PROGRAM_SEPARABLE, &mut separable);
+        }
+        if separable != gl::TRUE as GLint {
+            println!("Shader {} is not separable and cannot be used in pipeline {}.", shader.get_program_id(), self.pipeline_id);
+        }
+        if !shader.get_builder_ref().get_stages_ref().iter().any(|(shader_stage, _)| *shader_stage == stage) {
+            println!("Shader {} has no {} stage.", shader.get_program_id(), stage);
+        }
+
+        let mut stages = self.stages.borrow_mut();
+        stages.retain(|(other, _, _)| *other != stage);
+        stages.push((stage, shader.clone(), 0));
+        self.warned_uniforms.borrow_mut().clear();
+    }
+
+    /*  This function removes a stage from the pipeline.
+    */
+    pub fn remove_stage(&self, stage: ShaderStage) {
+        self.stages.borrow_mut().retain(|(other, _, _)| *other != stage);
+        unsafe {
+            gl::UseProgramStages(self.pipeline_id, stage.gl_bit(), 0);
+        }
+    }
+
+    /*  This function returns the shader used by a stage.
+    */
+    pub fn get_stage(&self, stage: ShaderStage) -> Option<Shader> {
+        self.stages.borrow().iter().find(|(other, _, _)| *other == stage).map(|(_, shader, _)| shader.clone())
+    }
+
+    /*  This function checks if the stages can work together (for example if the outputs of the vertex stage match the inputs of the fragment stage).
+    *   If they cannot it returns ShaderError::Link with the validation log.
+    */
+    pub fn validate(&self) -> Result<(), ShaderError> {
+        self.update_stages();
+
+        unsafe {
+            gl::ValidateProgramPipeline(self.pipeline_id);
+
+            let mut success = 0;
+            gl::GetProgramPipelineiv(self.pipeline_id, gl::VALIDATE_STATUS, &mut success);
+            if success != gl::TRUE as GLint {
+                let mut log_length = 0;
+                gl::GetProgramPipelineiv(self.pipeline_id, gl::INFO_LOG_LENGTH, &mut log_length);
+                let mut info_log: Vec::<u8> = vec![0; log_length.max(1) as usize];
+                gl::GetProgramPipelineInfoLog(self.pipeline_id, log_length, ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
+                while info_log.last() == Some(&0) {
+                    info_log.pop();
+                }
+
+                return Err(ShaderError::Link {
+                    log: String::from_utf8_lossy(&info_log).into_owned(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /*  This function gives opengl the programs of the stages that have changed (new stages and reloaded shaders).
+    */
+    fn update_stages(&self) {
+        for (stage, shader, used_program) in self.stages.borrow_mut().iter_mut() {
+            if *used_program != shader.get_program_id() {
+                *used_program = shader.get_program_id();
+                unsafe {
+                    gl::UseProgramStages(self.pipeline_id, stage.gl_bit(), *used_program);
+                }
+            }
+        }
+    }
+}
+
+impl ShaderTrait for ProgramPipeline {
+    /*  The id of a pipeline is not a program id, but it is what is bound.
+    */
+    fn get_program_id(&self) -> u32 {
+        self.pipeline_id
+    }
+
+    /*  This function binds the pipeline. A bound program would be used instead of the pipeline, so it is unbound.
+    */
+    fn bind(&self) {
+        self.update_stages();
+        unsafe {
+            gl::UseProgram(0);
+            gl::BindProgramPipeline(self.pipeline_id);
+        }
+    }
+
+    /*  This function returns the programs of every stage.
+    */
+    fn get_program_ids(&self) -> Vec::<GLuint> {
+        self.stages.borrow().iter().map(|(_, shader, _)| shader.get_program_id()).collect()
+    }
+
+    /*  This function returns the location of a uniform in the first stage that has it, and makes that stage the one written by gl::Uniform.
+    *   It returns -1 if no stage has the uniform.
+    */
+    fn get_uniform_location(&self, uniform_name: &str, value_type: GLenum) -> GLint {
+        match self.stages.borrow().iter().find(|(_, shader, _)| shader.has_uniform(uniform_name)) {
+            Some((_, shader, _)) => {
+                unsafe {
+                    gl::ActiveShaderProgram(self.pipeline_id, shader.get_program_id());
+                }
+                shader.get_uniform_location(uniform_name, value_type)
+            },
+            None => -1,
+        }
+    }
+
+    /*  This function writes a uniform in every stage that has it (a uniform with the same name in two stages gets the same value).
+    *   It prints a warning (only the first time) if no stage has the uniform.
+    */
+    fn set_uniform_value(&self, uniform_name: &str, uniform_value: &dyn UniformValue) {
+        self.bind();
+
+        let mut found = false;
+        for (_, shader, _) in self.stages.borrow().iter().filter(|(_, shader, _)| shader.has_uniform(uniform_name)) {
+            found = true;
+            let uniform_location = shader.get_uniform_location(uniform_name, uniform_value.get_uniform_type());
+            if uniform_location >= 0 {
+                unsafe {
+                    gl::ActiveShaderProgram(self.pipeline_id, shader.get_program_id());
+                }
+                uniform_value.write_uniform(uniform_location);
+            }
+        }
+
+        if !found && self.warned_uniforms.borrow_mut().insert(uniform_name.to_string()) {
+            println!("Uniform {} not found in pipeline {}.", uniform_name, self.pipeline_id);
+        }
+    }
+
+    /*  This function binds a uniform block in every stage that has it.
+    */
+    fn bind_uniform_block(&self, block_name: &str, binding_point: GLuint) {
+        let c_name = CString::new(block_name.as_bytes()).unwrap();
+        let mut found = false;
+        for (_, shader, _) in self.stages.borrow().iter() {
+            unsafe {
+                let block_index = gl::GetUniformBlockIndex(shader.get_program_id(), c_name.as_ptr());
+                if block_index != gl::INVALID_INDEX {
+                    gl::UniformBlockBinding(shader.get_program_id(), block_index, binding_point);
+                    found = true;
+                }
+            }
+        }
+
+        if !found {
+            println!("Uniform block {} not found in pipeline {}.", block_name, self.pipeline_id);
+        }
+    }
+
+    /*  This function binds a shader storage block in every stage that has it.
+    */
+    fn bind_storage_block(&self, block_name: &str, binding_point: GLuint) {
+        let c_name = CString::new(block_name.as_bytes()).unwrap();
+        let mut found = false;
+        for (_, shader, _) in self.stages.borrow().iter() {
+            unsafe {
+                let block_index = gl::GetProgramResourceIndex(shader.get_program_id(), gl::SHADER_STORAGE_BLOCK, c_name.as_ptr());
+                if block_index != gl::INVALID_INDEX {
+                    gl::ShaderStorageBlockBinding(shader.get_program_id(), block_index, binding_point);
+                    found = true;
+                }
+            }
+        }
+
+        if !found {
+            println!("Storage block {} not found in pipeline {}.", block_name, self.pipeline_id);
+        }
+    }
+}
@@ -4,10 +4,17 @@
 extern crate gl;
 use self::gl::types::*;
 
+use std::cell::Cell;
+
+/*  validate_layout enables the check of the layout against the bound shader on the first draw (only in debug builds).
+*   layout_validated is set after that check, so it is done only once.
+*/
 #[derive(Clone)]
 pub struct GlObjectProperties {
     pub draw_mode: GLenum,
     pub ebo_type: GLenum,
+    pub validate_layout: bool,
+    pub layout_validated: Cell<bool>,
 }
 
 /*  This struct contains the opengl properties.
@@ -19,12 +26,16 @@ pub struct TextureGlProperties {
     pub texture_type: GLenum,
     pub texture_warp_s: GLenum,
     pub texture_warp_t: GLenum,
+    pub texture_warp_r: GLenum,
     pub texture_min_filter: GLenum,
     pub texture_mag_filter: GLenum,
     pub texture_uniform_name: String,
 }
 
 /*  This struct contains the image properties.
+*   internal_format and format are None when they are found from the image (see textureFormat::get_texture_format).
+*   srgb chooses an srgb internal format for the found formats.
+*   layout tells how the image files become the images of the texture.
 *   ***I should find a more modular system***
 */
 #[derive(Debug, Clone)]
@@ -32,6 +43,72 @@ pub struct TextureImageProperties {
     pub path: String,
     pub fliph: bool,
     pub flipv: bool,
-    pub internal_format: GLenum,
-    pub format: GLenum,
-}
\ No newline at end of file
+    pub internal_format: Option<GLenum>,
+    pub format: Option<GLenum>,
+    pub srgb: bool,
+    pub layout: ImageLayout,
+}
+
+/*  This enum lists how the images of a texture are found.
+*   Single is a single image in path.
+*   CubemapFaces are the six faces of a cubemap in the opengl order (+x, -x, +y, -y, +z, -z), path is the first one.
+*   CubemapCross is a cubemap unfolded in a cross in path (see cubemap::split_cross).
+*   CubemapEquirectangular is a panorama in path projected on the faces of a cubemap, face_size is the size of a face (0 is a quarter of the width).
+*   Layers are the layers of an array texture or the slices of a 3d texture, path is the first one.
+*   Sheet is a sprite sheet in path cut in columns * rows layers or slices, from left to right and from top to bottom.
+*   Memory is used when the pixels are given from memory or the texture is empty (see Texture::from_voxels, from_pixels and empty), so there is nothing to load.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageLayout {
+    Single,
+    CubemapFaces(Vec::<String>),
+    CubemapCross,
+    CubemapEquirectangular {
+        face_size: u32,
+    },
+    Layers(Vec::<String>),
+    Sheet {
+        columns: u32,
+        rows: u32,
+    },
+    Memory,
+}
+
+/*  This struct contains the properties of a sampler (see renderer::sampler).
+*   min_lod, max_lod and lod_bias choose the mip levels, max_anisotropy is 1 when the anisotropic filter is disabled.
+*   compare_function, if any, enables the depth comparison of the shadow samplers (gl::LEQUAL, gl::GREATER...).
+*   The defaults are the ones of opengl.
+*   ***I should find a more modular system***
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamplerProperties {
+    pub min_filter: GLenum,
+    pub mag_filter: GLenum,
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+    pub wrap_r: GLenum,
+    pub border_color: [f32; 4],
+    pub min_lod: f32,
+    pub max_lod: f32,
+    pub lod_bias: f32,
+    pub max_anisotropy: f32,
+    pub compare_function: Option<GLenum>,
+}
+
+impl Default for SamplerProperties {
+    fn default() -> SamplerProperties {
+        SamplerProperties {
+            min_filter: gl::NEAREST_MIPMAP_LINEAR,
+            mag_filter: gl::LINEAR,
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            wrap_r: gl::REPEAT,
+            border_color: [0.0, 0.0, 0.0, 0.0],
+            min_lod: -1000.0,
+            max_lod: 1000.0,
+            lod_bias: 0.0,
+            max_anisotropy: 1.0,
+            compare_function: None,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/*  File: renderer/readback.rs
+*   Author: Vicix
+*
+*   This file contains the functions that read the pixels of the framebuffer back from the gpu and save images as png files.
+*   opengl stores the rows from the bottom to the top, so the pixels are flipped to get the first row at the top, like in the image files.
+*   The textures are read with TextureTrait::read_image.
+*
+*   Example:
+*       readback::save_screenshot("./screenshot.png")?;
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+extern crate image;
+use image::{ColorType, DynamicImage, ImageOutputFormat};
+
+use crate::renderer::errors::TextureError;
+use crate::renderer::imageData::ImageData;
+use crate::renderer::traits::textureTrait;
+
+use std::ffi::c_void;
+use std::fs::File;
+use std::io::BufWriter;
+
+/*  This function reads a rectangle of the bound read framebuffer (the window if no framebuffer is bound), with the bottom left corner in x, y.
+*   The pixels are read as rgb (the alpha of the window is often meaningless) and flipped, so the first row is the top of the rectangle.
+*/
+pub fn read_framebuffer(x: i32, y: i32, width: u32, height: u32) -> Result<DynamicImage, TextureError> {
+    let mut image = ImageData {
+        width,
+        height,
+        color: ColorType::RGB(8),
+        pixels: vec![0; width as usize * height as usize * 3],
+    };
+
+    unsafe {
+        textureTrait::clear_gl_errors();
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(x, y, width as GLsizei, height as GLsizei, gl::RGB, gl::UNSIGNED_BYTE, image.pixels.as_mut_ptr() as *mut c_void);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+
+        let error = gl::GetError();
+        if error != gl::NO_ERROR {
+            return Err(TextureError::Readback {
+                path: "framebuffer".to_string(),
+                error,
+            });
+        }
+    }
+
+    image.flip_vertical();
+    image.to_dynamic_image().ok_or_else(|| TextureError::Decode {
+        path: "framebuffer".to_string(),
+        message: "The pixels don't fill the image.".to_string(),
+    })
+}
+
+/*  This function reads the whole viewport of the bound read framebuffer (usually the window).
+*/
+pub fn read_viewport() -> Result<DynamicImage, TextureError> {
+    let mut viewport = [0; 4];
+    unsafe {
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+    }
+
+    read_framebuffer(viewport[0], viewport[1], viewport[2].max(0) as u32, viewport[3].max(0) as u32)
+}
+
+/*  This function saves an image in a png file, whatever the extension of the path is.
+*/
+pub fn save_png(image: &DynamicImage, path: &str) -> Result<(), TextureError> {
+    let file = File::create(path).map_err(|error| TextureError::Save {
+        path: path.to_string(),
+        message: error.to_string(),
+    })?;
+
+    image.write_to(&mut BufWriter::new(file), ImageOutputFormat::PNG).map_err(|error| TextureError::Save {
+        path: path.to_string(),
+        message: error.to_string(),
+    })
+}
+
+/*  This function reads the viewport and saves it in a png file.
+*   Note: call it before swapping the buffers, when the back buffer still contains the frame.
+*/
+pub fn save_screenshot(path: &str) -> Result<(), TextureError> {
+    save_png(&read_viewport()?, path)
+}
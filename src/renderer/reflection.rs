@@ -0,0 +1,192 @@
+/*  File: renderer/reflection.rs
+*   Author: Vicix
+*
+*   This file contains the ProgramReflection class.
+*   The class ProgramReflection asks opengl the active uniforms and attributes of a linked program and stores their location and type.
+*   It is used by the Shader to avoid calling gl::GetUniformLocation every time a uniform is set.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use std::collections::HashMap;
+use std::ffi::CString;
+
+/*  This struct describes an active uniform or attribute.
+*   gl_type is the opengl type (gl::FLOAT_VEC3, gl::SAMPLER_2D...) and size is the number of elements (greater than 1 for arrays).
+*/
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ShaderVariable {
+    pub name: String,
+    pub location: GLint,
+    pub gl_type: GLenum,
+    pub size: GLint,
+}
+
+/*  This is the declaration of the class.
+*   The uniforms and the attributes are stored by name.
+*   The elements of an array uniform are stored both as "name", "name[0]" and "name[i]".
+*   Note: the uniforms inside a uniform block have no location, so they are not stored.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ProgramReflection {
+    uniforms: HashMap<String, ShaderVariable>,
+    attributes: HashMap<String, ShaderVariable>,
+}
+
+#[allow(dead_code)]
+impl ProgramReflection {
+    /*  The constructor of the class.
+    *   It enumerates the active uniforms and attributes of a linked program.
+    */
+    pub fn from_program(program_id: GLuint) -> ProgramReflection {
+        let mut reflection = ProgramReflection::default();
+
+        unsafe {
+            let mut uniform_count = 0;
+            let mut max_length = 0;
+            gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORMS, &mut uniform_count);
+            gl::GetProgramiv(program_id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+            for index in 0..uniform_count.max(0) as GLuint {
+                let (name, gl_type, size) = get_active(program_id, index, max_length, gl::GetActiveUniform);
+                let location = get_location(program_id, &name, gl::GetUniformLocation);
+                if location < 0 {
+                    continue;
+                }
+
+                let base_name = name.trim_end_matches("[0]").to_string();
+                if size > 1 || base_name != name {
+                    for element in 1..size {
+                        let element_name = format!("{}[{}]", base_name, element);
+                        let element_location = get_location(program_id, &element_name, gl::GetUniformLocation);
+                        reflection.uniforms.insert(element_name.clone(), ShaderVariable {
+                            name: element_name,
+                            location: element_location,
+                            gl_type,
+                            size: 1,
+                        });
+                    }
+                    reflection.uniforms.insert(format!("{}[0]", base_name), ShaderVariable {
+                        name: format!("{}[0]", base_name),
+                        location,
+                        gl_type,
+                        size,
+                    });
+                }
+
+                reflection.uniforms.insert(base_name.clone(), ShaderVariable {
+                    name: base_name,
+                    location,
+                    gl_type,
+                    size,
+                });
+            }
+
+            let mut attribute_count = 0;
+            gl::GetProgramiv(program_id, gl::ACTIVE_ATTRIBUTES, &mut attribute_count);
+            gl::GetProgramiv(program_id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+
+            for index in 0..attribute_count.max(0) as GLuint {
+                let (name, gl_type, size) = get_active(program_id, index, max_length, gl::GetActiveAttrib);
+                let location = get_location(program_id, &name, gl::GetAttribLocation);
+
+                reflection.attributes.insert(name.clone(), ShaderVariable {
+                    name,
+                    location,
+                    gl_type,
+                    size,
+                });
+            }
+        }
+
+        reflection
+    }
+
+    /*  This function returns an active uniform by name.
+    */
+    pub fn get_uniform(&self, name: &str) -> Option<&ShaderVariable> {
+        self.uniforms.get(name)
+    }
+
+    /*  This function returns an active attribute by name.
+    */
+    pub fn get_attribute(&self, name: &str) -> Option<&ShaderVariable> {
+        self.attributes.get(name)
+    }
+
+    /*  This function returns all the active uniforms.
+    */
+    pub fn get_uniforms_ref(&self) -> &HashMap<String, ShaderVariable> {
+        &self.uniforms
+    }
+
+    /*  This function returns all the active attributes.
+    *   Note: built-in attributes (like gl_VertexID) have location -1.
+    */
+    pub fn get_attributes_ref(&self) -> &HashMap<String, ShaderVariable> {
+        &self.attributes
+    }
+}
+
+/*  This function checks if a value of type value_type can be written in a uniform of type uniform_type.
+*   Other than the same type opengl accepts integers for samplers and integers or floats for booleans.
+*/
+pub fn uniform_type_matches(value_type: GLenum, uniform_type: GLenum) -> bool {
+    if value_type == uniform_type {
+        return true;
+    }
+
+    match uniform_type {
+        gl::BOOL => value_type == gl::INT || value_type == gl::UNSIGNED_INT || value_type == gl::FLOAT,
+        gl::BOOL_VEC2 => value_type == gl::INT_VEC2 || value_type == gl::UNSIGNED_INT_VEC2 || value_type == gl::FLOAT_VEC2,
+        gl::BOOL_VEC3 => value_type == gl::INT_VEC3 || value_type == gl::UNSIGNED_INT_VEC3 || value_type == gl::FLOAT_VEC3,
+        gl::BOOL_VEC4 => value_type == gl::INT_VEC4 || value_type == gl::UNSIGNED_INT_VEC4 || value_type == gl::FLOAT_VEC4,
+        gl::INT => value_type == gl::BOOL,
+        _ => is_sampler_type(uniform_type) && value_type == gl::INT,
+    }
+}
+
+/*  This function checks if a opengl type is a sampler or an image (those are set with an integer uniform).
+*/
+pub fn is_sampler_type(gl_type: GLenum) -> bool {
+    matches!(gl_type,
+        gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE |
+        gl::SAMPLER_1D_SHADOW | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_CUBE_SHADOW |
+        gl::SAMPLER_1D_ARRAY | gl::SAMPLER_2D_ARRAY | gl::SAMPLER_1D_ARRAY_SHADOW | gl::SAMPLER_2D_ARRAY_SHADOW |
+        gl::SAMPLER_2D_MULTISAMPLE | gl::SAMPLER_2D_MULTISAMPLE_ARRAY | gl::SAMPLER_2D_RECT | gl::SAMPLER_2D_RECT_SHADOW |
+        gl::SAMPLER_BUFFER | gl::SAMPLER_CUBE_MAP_ARRAY | gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW |
+        gl::INT_SAMPLER_1D | gl::INT_SAMPLER_2D | gl::INT_SAMPLER_3D | gl::INT_SAMPLER_CUBE |
+        gl::INT_SAMPLER_1D_ARRAY | gl::INT_SAMPLER_2D_ARRAY | gl::INT_SAMPLER_2D_MULTISAMPLE |
+        gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY | gl::INT_SAMPLER_2D_RECT | gl::INT_SAMPLER_BUFFER | gl::INT_SAMPLER_CUBE_MAP_ARRAY |
+        gl::UNSIGNED_INT_SAMPLER_1D | gl::UNSIGNED_INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_CUBE |
+        gl::UNSIGNED_INT_SAMPLER_1D_ARRAY | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE |
+        gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY | gl::UNSIGNED_INT_SAMPLER_2D_RECT | gl::UNSIGNED_INT_SAMPLER_BUFFER |
+        gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY |
+        gl::IMAGE_1D | gl::IMAGE_2D | gl::IMAGE_3D | gl::IMAGE_CUBE | gl::IMAGE_2D_ARRAY | gl::IMAGE_BUFFER |
+        gl::INT_IMAGE_2D | gl::INT_IMAGE_3D | gl::UNSIGNED_INT_IMAGE_2D | gl::UNSIGNED_INT_IMAGE_3D)
+}
+
+/*  This function calls gl::GetActiveUniform or gl::GetActiveAttrib and returns the name, the type and the size of the variable.
+*/
+unsafe fn get_active(program_id: GLuint, index: GLuint, max_length: GLint,
+    get_active_function: unsafe fn(GLuint, GLuint, GLsizei, *mut GLsizei, *mut GLint, *mut GLenum, *mut GLchar)) -> (String, GLenum, GLint) {
+    let mut name: Vec::<u8> = vec![0; max_length.max(1) as usize];
+    let mut length = 0;
+    let mut size = 0;
+    let mut gl_type = 0;
+    get_active_function(program_id, index, name.len() as GLsizei, &mut length, &mut size, &mut gl_type, name.as_mut_ptr() as *mut GLchar);
+    name.truncate(length.max(0) as usize);
+
+    (String::from_utf8_lossy(&name).into_owned(), gl_type, size)
+}
+
+/*  This function calls gl::GetUniformLocation or gl::GetAttribLocation.
+*/
+unsafe fn get_location(program_id: GLuint, name: &str, get_location_function: unsafe fn(GLuint, *const GLchar) -> GLint) -> GLint {
+    match CString::new(name) {
+        Ok(c_name) => get_location_function(program_id, c_name.as_ptr()),
+        Err(_) => -1,
+    }
+}
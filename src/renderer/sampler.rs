@@ -0,0 +1,223 @@
+/*  File: renderer/sampler.rs
+*   Author: Vicix
+*
+*   This file contains the Sampler class.
+*   The class Sampler is an abstraction of the opengl sampler objects (opengl 3.3): the filters, the wrap and the other sampling parameters, separated from the textures.
+*   A sampler bound to a texture unit replaces the parameters of the texture bound to the same unit, so the same texture can be read with different samplers.
+*   Every setter changes the sampler object immediately, so the changes are seen by the next draw.
+*
+*   Example:
+*       let mut sampler = sampler::Sampler::new();
+*       sampler.set_filter(gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR);
+*       sampler.set_anisotropy(8.0);
+*       material.push_texture_with_sampler(&texture, &sampler, "diffuse");
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::properties::{SamplerProperties, TextureGlProperties};
+use crate::renderer::capabilities;
+
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+
+/*  These are the constants of EXT_texture_filter_anisotropic (core in opengl 4.6), missing in the gl crate.
+*/
+pub const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+pub const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+/*  This is the declaration of the class.
+*   The sampler id and the properties are shared between the clones, so a change made through a clone (for example the one in a Material) is seen by all of them.
+*/
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    sampler_id: GLuint,
+    properties: Rc<RefCell<SamplerProperties>>,
+}
+
+#[allow(dead_code)]
+impl Sampler {
+    /*  The constructor of the class.
+    *   It creates a sampler with the default properties of opengl (see SamplerProperties).
+    */
+    pub fn new() -> Sampler {
+        Sampler::from_properties(SamplerProperties::default())
+    }
+
+    /*  This constructor creates a sampler and applies the properties.
+    */
+    pub fn from_properties(properties: SamplerProperties) -> Sampler {
+        let mut sampler_id = 0;
+        unsafe {
+            gl::GenSamplers(1, &mut sampler_id);
+        }
+
+        let sampler = Sampler {
+            sampler_id,
+            properties: Rc::new(RefCell::new(properties.clone())),
+        };
+        sampler.apply_properties(&properties);
+
+        sampler
+    }
+
+    /*  This constructor creates a sampler with the filters and the wrap of a texture, so it samples like the texture does.
+    */
+    pub fn from_texture_properties(gl_properties: &TextureGlProperties) -> Sampler {
+        Sampler::from_properties(SamplerProperties {
+            min_filter: gl_properties.texture_min_filter,
+            mag_filter: gl_properties.texture_mag_filter,
+            wrap_s: gl_properties.texture_warp_s,
+            wrap_t: gl_properties.texture_warp_t,
+            wrap_r: gl_properties.texture_warp_r,
+            ..SamplerProperties::default()
+        })
+    }
+
+    /*  This function sets the minification filter (it can use the mipmaps) and the magnification filter (gl::NEAREST or gl::LINEAR).
+    */
+    pub fn set_filter(&mut self, min_filter: GLenum, mag_filter: GLenum) {
+        let mut properties = self.properties.borrow_mut();
+        properties.min_filter = min_filter;
+        properties.mag_filter = mag_filter;
+
+        unsafe {
+            gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+            gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_MAG_FILTER, mag_filter as GLint);
+        }
+    }
+
+    /*  This function sets the wrap of the three coordinates (gl::REPEAT, gl::MIRRORED_REPEAT, gl::CLAMP_TO_EDGE or gl::CLAMP_TO_BORDER).
+    */
+    pub fn set_wrap(&mut self, wrap_s: GLenum, wrap_t: GLenum, wrap_r: GLenum) {
+        let mut properties = self.properties.borrow_mut();
+        properties.wrap_s = wrap_s;
+        properties.wrap_t = wrap_t;
+        properties.wrap_r = wrap_r;
+
+        unsafe {
+            gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_WRAP_S, wrap_s as GLint);
+            gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_WRAP_T, wrap_t as GLint);
+            gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_WRAP_R, wrap_r as GLint);
+        }
+    }
+
+    /*  This function sets the color (rgba, from 0 to 1) read outside of the texture with gl::CLAMP_TO_BORDER.
+    */
+    pub fn set_border_color(&mut self, border_color: [f32; 4]) {
+        self.properties.borrow_mut().border_color = border_color;
+
+        unsafe {
+            gl::SamplerParameterfv(self.sampler_id, gl::TEXTURE_BORDER_COLOR, border_color.as_ptr());
+        }
+    }
+
+    /*  This function limits the mip levels that can be sampled (0 is the biggest level).
+    */
+    pub fn set_lod_range(&mut self, min_lod: f32, max_lod: f32) {
+        let mut properties = self.properties.borrow_mut();
+        properties.min_lod = min_lod;
+        properties.max_lod = max_lod;
+
+        unsafe {
+            gl::SamplerParameterf(self.sampler_id, gl::TEXTURE_MIN_LOD, min_lod);
+            gl::SamplerParameterf(self.sampler_id, gl::TEXTURE_MAX_LOD, max_lod);
+        }
+    }
+
+    /*  This function sets the bias added to the mip level chosen by opengl: a positive bias makes the texture blurrier, a negative one sharper.
+    */
+    pub fn set_lod_bias(&mut self, lod_bias: f32) {
+        self.properties.borrow_mut().lod_bias = lod_bias;
+
+        unsafe {
+            gl::SamplerParameterf(self.sampler_id, gl::TEXTURE_LOD_BIAS, lod_bias);
+        }
+    }
+
+    /*  This function sets the anisotropic filter (1 disables it). The value is limited to the maximum of the driver.
+    *   It returns false and does nothing if the driver does not support the anisotropic filter (see capabilities::is_anisotropy_supported).
+    */
+    pub fn set_anisotropy(&mut self, max_anisotropy: f32) -> bool {
+        if !capabilities::is_anisotropy_supported() {
+            return false;
+        }
+
+        let max_anisotropy = max_anisotropy.clamp(1.0, capabilities::get_max_anisotropy());
+        self.properties.borrow_mut().max_anisotropy = max_anisotropy;
+
+        unsafe {
+            gl::SamplerParameterf(self.sampler_id, TEXTURE_MAX_ANISOTROPY, max_anisotropy);
+        }
+
+        true
+    }
+
+    /*  This function enables the depth comparison, for the shadow maps read with a sampler2DShadow (or disables it with None).
+    *   The depth of the texture is compared with the reference of the lookup using the function (gl::LEQUAL, gl::GREATER...).
+    */
+    pub fn set_compare_function(&mut self, compare_function: Option<GLenum>) {
+        self.properties.borrow_mut().compare_function = compare_function;
+
+        unsafe {
+            match compare_function {
+                Some(function) => {
+                    gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint);
+                    gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_COMPARE_FUNC, function as GLint);
+                },
+                None => gl::SamplerParameteri(self.sampler_id, gl::TEXTURE_COMPARE_MODE, gl::NONE as GLint),
+            }
+        }
+    }
+
+    /*  This function binds the sampler to a texture unit (the active texture number of the textures it should sample).
+    */
+    pub fn bind(&self, texture_unit: GLuint) {
+        unsafe {
+            gl::BindSampler(texture_unit, self.sampler_id);
+        }
+    }
+
+    /*  This function removes the sampler of a texture unit, so the texture bound to it uses its own parameters again.
+    */
+    pub fn unbind(texture_unit: GLuint) {
+        unsafe {
+            gl::BindSampler(texture_unit, 0);
+        }
+    }
+
+    /*  This function returns the sampler id.
+    */
+    pub fn get_sampler_id(&self) -> GLuint {
+        self.sampler_id
+    }
+
+    /*  This function returns the properties of the sampler. They are changed with the setters, that update the sampler object too.
+    */
+    pub fn get_properties_ref(&self) -> Ref<'_, SamplerProperties> {
+        self.properties.borrow()
+    }
+
+    /*  This function gives every property to the sampler object.
+    */
+    fn apply_properties(&self, properties: &SamplerProperties) {
+        let mut sampler = self.clone();
+        sampler.set_filter(properties.min_filter, properties.mag_filter);
+        sampler.set_wrap(properties.wrap_s, properties.wrap_t, properties.wrap_r);
+        sampler.set_border_color(properties.border_color);
+        sampler.set_lod_range(properties.min_lod, properties.max_lod);
+        sampler.set_lod_bias(properties.lod_bias);
+        sampler.set_compare_function(properties.compare_function);
+        if properties.max_anisotropy > 1.0 && !sampler.set_anisotropy(properties.max_anisotropy) {
+            println!("The anisotropic filter is not supported, sampler {} uses the normal filter.", self.sampler_id);
+            sampler.properties.borrow_mut().max_anisotropy = 1.0;
+        }
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Sampler {
+        Sampler::new()
+    }
+}
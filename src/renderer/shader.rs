@@ -10,78 +10,216 @@ extern crate gl;
 use self::gl::types::*;
 
 use crate::renderer::traits::shaderTrait::ShaderTrait;
+use crate::renderer::traits::vaoTrait::VaoLayoutTrait;
+use crate::renderer::errors::ShaderError;
+use crate::renderer::shaderBuilder::ShaderBuilder;
+use crate::renderer::reflection::{self, ProgramReflection};
+use crate::renderer::vertexInput::{self, LayoutMismatch};
 
-use std::ffi::CString;
-use std::ptr;
-use std::str;
-use std::fs::File;
-use std::io::Read;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::time::SystemTime;
+
+/*  This is the list of the watched files of a shader, with their last modification time.
+*/
+type WatchedFiles = Vec::<(String, Option<SystemTime>)>;
+
+/*  This enum lists the shader stages that can be attached to a program.
+*   Every stage knows its opengl shader type.
+*/
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Geometry,
+    TessControl,
+    TessEvaluation,
+    Compute,
+}
+
+impl ShaderStage {
+    /*  This function returns the opengl shader type of the stage (used in gl::CreateShader).
+    */
+    pub fn gl_type(&self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+
+    /*  This function returns the opengl bit of the stage (used in gl::UseProgramStages).
+    */
+    pub fn gl_bit(&self) -> GLbitfield {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER_BIT,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER_BIT,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER_BIT,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER_BIT,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER_BIT,
+            ShaderStage::Compute => gl::COMPUTE_SHADER_BIT,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+            ShaderStage::Geometry => write!(f, "geometry"),
+            ShaderStage::TessControl => write!(f, "tessellation control"),
+            ShaderStage::TessEvaluation => write!(f, "tessellation evaluation"),
+            ShaderStage::Compute => write!(f, "compute"),
+        }
+    }
+}
 
 /*  This is the declaration of the Shader Class.
 *   In order to make this work we only need the program id.
 *   All the other information are stored in the gpu.
+*   The program id is shared between the clones, so if the shader is reloaded every clone (for example the one in a Material) uses the new program.
+*   The builder is kept to recompile the shader and watched_files contains the last modification time of every file when the shader is watched.
+*   The reflection contains the active uniforms and attributes of the program, so the uniform locations are asked only once.
+*   warned_uniforms contains the uniforms that have already been reported as wrong, so the warning is printed only once.
 */
 #[derive(Debug, Clone)]
 pub struct Shader {
-    program_id: GLuint,
+    program_id: Rc<Cell<GLuint>>,
+    builder: ShaderBuilder,
+    watched_files: Rc<RefCell<Option<WatchedFiles>>>,
+    reflection: Rc<RefCell<ProgramReflection>>,
+    warned_uniforms: Rc<RefCell<HashSet<String>>>,
 }
 
 #[allow(dead_code)]
 impl Shader {
     /*  The constructor of the class.
     *   It takes the path to the vertex shader and the path to the fragment path.
-    *   It automatically parses, compile and link the shaders. It puts the error log in the console if there is a error in the shader's code.
-    */
-    pub fn new(vertex_path: &str, fragment_path: &str) -> Shader {
-        let mut v_shader_file = File::open(vertex_path)
-            .unwrap_or_else(|_| panic!("Failed to open {}", vertex_path));
-        let mut f_shader_file = File::open(fragment_path)
-            .unwrap_or_else(|_| panic!("Failed to open {}", fragment_path));
-        let mut vertex_code = String::new();
-        let mut fragment_code = String::new();
-        v_shader_file
-            .read_to_string(&mut vertex_code)
-            .expect("Failed to read vertex shader");
-        f_shader_file
-            .read_to_string(&mut fragment_code)
-            .expect("Failed to read fragment shader");
-
-        let shader_program = unsafe {
-            let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-            let c_str_source = CString::new(vertex_code.as_bytes()).unwrap();
-            gl::ShaderSource(vertex_shader, 1, &c_str_source.as_ptr(), ptr::null());
-            gl::CompileShader(vertex_shader);
-
-            let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-            let c_str_source = CString::new(fragment_code.as_bytes()).unwrap();
-            gl::ShaderSource(fragment_shader, 1, &c_str_source.as_ptr(), ptr::null());
-            gl::CompileShader(fragment_shader);
-
-            let shader_program = gl::CreateProgram();
-            gl::AttachShader(shader_program, vertex_shader);
-            gl::AttachShader(shader_program, fragment_shader);
-            gl::LinkProgram(shader_program);
-
-            let mut success = 0;
-            let mut info_log: Vec::<u8> = Vec::with_capacity(512);
-            info_log.set_len(512 - 1);
-            gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                gl::GetProgramInfoLog(shader_program, 512, ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-                println!("ERROR::SHADER::PROGRAM::LINK_FAILED\n{}", str::from_utf8(&info_log).unwrap());
-            } else {
-                println!("SHADERS SUCCESSFULLY COMPILED AND LINKED");
-            }
-
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
-
-            shader_program
-        };
+    *   It automatically parses, compile and link the shaders.
+    *   If a file cannot be read, a stage does not compile or the program does not link it returns a ShaderError with the full log.
+    *   Note: this is a shortcut for ShaderBuilder. Use it directly to attach other stages.
+    */
+    pub fn new(vertex_path: &str, fragment_path: &str) -> Result<Shader, ShaderError> {
+        ShaderBuilder::new()
+            .with_file(ShaderStage::Vertex, vertex_path)
+            .with_file(ShaderStage::Fragment, fragment_path)
+            .build()
+    }
 
+    /*  This constructor builds a separable program with a single stage, to be used in a ProgramPipeline.
+    *   Note: this is a shortcut for ShaderBuilder::with_separable.
+    */
+    pub fn separable(stage: ShaderStage, path: &str) -> Result<Shader, ShaderError> {
+        ShaderBuilder::new()
+            .with_file(stage, path)
+            .with_separable()
+            .build()
+    }
+
+    /*  This function wraps an already linked program.
+    *   It is used by the ShaderBuilder.
+    */
+    pub(crate) fn from_program_id(program_id: GLuint, builder: ShaderBuilder) -> Shader {
         Shader {
-            program_id: shader_program,
+            program_id: Rc::new(Cell::new(program_id)),
+            builder,
+            watched_files: Rc::new(RefCell::new(None)),
+            reflection: Rc::new(RefCell::new(ProgramReflection::from_program(program_id))),
+            warned_uniforms: Rc::new(RefCell::new(HashSet::new())),
+        }
+    }
+
+    /*  This function returns the builder used to build the shader.
+    */
+    pub fn get_builder_ref(&self) -> &ShaderBuilder {
+        &self.builder
+    }
+
+    /*  This function checks if the program has an active uniform, without printing warnings.
+    */
+    pub fn has_uniform(&self, uniform_name: &str) -> bool {
+        self.reflection.borrow().get_uniform(uniform_name).is_some()
+    }
+
+    /*  This function returns the active uniforms and attributes of the program.
+    */
+    pub fn get_reflection(&self) -> ProgramReflection {
+        self.reflection.borrow().clone()
+    }
+
+    /*  This function compares the vertex inputs of the shader with the layout of a vao (location, component count and base type).
+    *   It returns the missing, unused and mismatched attributes. An empty vector means that the layout is right.
+    */
+    pub fn check_layout<L: VaoLayoutTrait + ?Sized>(&self, vao: &L) -> Vec::<LayoutMismatch> {
+        let inputs = vertexInput::get_vertex_inputs(&self.reflection.borrow());
+        vertexInput::check_vertex_layout(&inputs, vao.get_layout_ref())
+    }
+
+    /*  This function starts watching the source files of the shader and the files they include.
+    *   After this reload_if_changed() will recompile the shader when a file is modified.
+    */
+    pub fn watch(&self) {
+        let files = self.builder.get_source_files().into_iter()
+            .map(|path| {
+                let modified = get_modified_time(&path);
+                (path, modified)
+            })
+            .collect();
+
+        *self.watched_files.borrow_mut() = Some(files);
+    }
+
+    /*  This function stops watching the source files of the shader.
+    */
+    pub fn unwatch(&self) {
+        *self.watched_files.borrow_mut() = None;
+    }
+
+    /*  This function checks if a watched file has been modified and reloads the shader if needed.
+    *   It should be called once per frame. It returns true if the shader has been reloaded.
+    *   If the new version does not compile the last good program is kept and the error is returned.
+    *   Note: the watched files are refreshed anyway (an include could have been added), so a broken file is not recompiled every frame.
+    */
+    pub fn reload_if_changed(&self) -> Result<bool, ShaderError> {
+        let changed = match self.watched_files.borrow().as_ref() {
+            Some(files) => files.iter().any(|(path, modified)| {
+                let new_modified = get_modified_time(path);
+                new_modified.is_some() && new_modified != *modified
+            }),
+            None => false,
+        };
+
+        if changed {
+            self.watch();
+            self.reload()?;
         }
+
+        Ok(changed)
+    }
+
+    /*  This function recompiles and relinks the shader.
+    *   If it succeeds the old program is deleted and replaced in every clone of the shader. The uniforms are enumerated again.
+    *   If it fails the last good program is kept.
+    */
+    pub fn reload(&self) -> Result<(), ShaderError> {
+        let program_id = self.builder.build_program()?;
+
+        unsafe {
+            gl::DeleteProgram(self.program_id.get());
+        }
+        self.program_id.set(program_id);
+        *self.reflection.borrow_mut() = ProgramReflection::from_program(program_id);
+        self.warned_uniforms.borrow_mut().clear();
+
+        Ok(())
     }
 /*
     /*  This is a simple function that binds the shader
@@ -133,6 +271,31 @@ impl Shader {
 
 impl ShaderTrait for Shader {
     fn get_program_id(&self) -> u32 {
-        self.program_id
+        self.program_id.get()
     }
-}
\ No newline at end of file
+
+    /*  This function returns the cached location of a uniform.
+    *   If the uniform does not exist or its type does not match the value it prints a warning (only the first time) and returns -1, so nothing is written.
+    */
+    fn get_uniform_location(&self, uniform_name: &str, value_type: GLenum) -> GLint {
+        let reflection = self.reflection.borrow();
+        let message = match reflection.get_uniform(uniform_name) {
+            Some(uniform) if reflection::uniform_type_matches(value_type, uniform.gl_type) => return uniform.location,
+            Some(uniform) => format!("Wrong type for uniform {} in shader {}: expected 0x{:X}, got 0x{:X}.", uniform_name, self.program_id.get(), uniform.gl_type, value_type),
+            None => format!("Uniform {} not found in shader {}.", uniform_name, self.program_id.get()),
+        };
+
+        if self.warned_uniforms.borrow_mut().insert(uniform_name.to_string()) {
+            println!("{}", message);
+        }
+
+        -1
+    }
+}
+
+/*  This function returns the last modification time of a file.
+*   It returns None if the file cannot be read (for example while an editor is saving it).
+*/
+fn get_modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
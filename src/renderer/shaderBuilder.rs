@@ -0,0 +1,462 @@
+/*  File: renderer/shaderBuilder.rs
+*   Author: Vicix
+*
+*   This file contains the ShaderBuilder class.
+*   The class ShaderBuilder collects the sources of every stage of a program and then compiles and links them into a Shader.
+*   It supports vertex, fragment, geometry, tessellation and compute stages.
+*   Every source goes through the Preprocessor, so the stages can use #include and the defines of the builder.
+*   Optionally the linked programs are saved in a ProgramCache and loaded from it in the next runs.
+*   The outputs captured by transform feedback and the separable flag are set here too, because they must be known before linking.
+*   A stage can also be a spir-v module (opengl 4.6 or ARB_gl_spirv), for example one written by the translate_shaders command. It is not preprocessed.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::shader::{Shader, ShaderStage};
+use crate::renderer::errors::ShaderError;
+use crate::renderer::preprocessor::{Preprocessor, PreprocessedSource};
+use crate::renderer::programCache::ProgramCache;
+use crate::renderer::capabilities;
+use crate::renderer::spirv;
+
+use std::borrow::Cow;
+use std::ffi::CString;
+use std::ptr;
+
+/*  This enum tells where the code of a stage comes from.
+*   File contains the path of the file, Code contains the glsl code itself.
+*   SpirvFile contains the path of a spir-v file, Spirv contains the spir-v words themselves.
+*/
+#[derive(Debug, Clone)]
+pub enum ShaderSource {
+    File(String),
+    Code(String),
+    SpirvFile(String),
+    Spirv(Vec::<u32>),
+}
+
+/*  This enum is a stage ready to be compiled: preprocessed glsl, or spir-v words with the path used in the errors.
+*/
+enum PreparedSource {
+    Glsl(PreprocessedSource),
+    Spirv(String, Vec::<u32>),
+}
+
+impl PreparedSource {
+    /*  This function returns the bytes hashed in the cache key.
+    */
+    fn get_bytes(&self) -> Cow<'_, [u8]> {
+        match self {
+            PreparedSource::Glsl(processed) => Cow::Borrowed(processed.code.as_bytes()),
+            PreparedSource::Spirv(_, words) => Cow::Owned(spirv::words_to_bytes(words)),
+        }
+    }
+}
+
+/*  This is the declaration of the class.
+*   It holds the list of the stages with their source, the preprocessor with the defines and the optional program binary cache.
+*   feedback_varyings contains the outputs captured by transform feedback and the buffer mode (gl::INTERLEAVED_ATTRIBS or gl::SEPARATE_ATTRIBS).
+*   separable is true if the program is linked to be used in a ProgramPipeline.
+*   Nothing is sent to opengl until build() is called.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ShaderBuilder {
+    stages: Vec::<(ShaderStage, ShaderSource)>,
+    preprocessor: Preprocessor,
+    binary_cache: Option<ProgramCache>,
+    feedback_varyings: Option<(Vec::<String>, GLenum)>,
+    separable: bool,
+}
+
+#[allow(dead_code)]
+impl ShaderBuilder {
+    /*  The constructor of the class.
+    *   It creates a builder without stages.
+    */
+    pub fn new() -> ShaderBuilder {
+        ShaderBuilder {
+            stages: Vec::<(ShaderStage, ShaderSource)>::new(),
+            preprocessor: Preprocessor::new(),
+            binary_cache: None,
+            feedback_varyings: None,
+            separable: false,
+        }
+    }
+
+    /*  This function attaches a stage whose code is read from a file.
+    */
+    pub fn with_file(mut self, stage: ShaderStage, path: &str) -> ShaderBuilder {
+        self.stages.push((stage, ShaderSource::File(path.to_string())));
+        self
+    }
+
+    /*  This function attaches a stage whose code is given as a string.
+    */
+    pub fn with_source(mut self, stage: ShaderStage, code: &str) -> ShaderBuilder {
+        self.stages.push((stage, ShaderSource::Code(code.to_string())));
+        self
+    }
+
+    /*  This function attaches a stage whose code is a spir-v file. The entry point must be called main.
+    *   Every stage of the program must be spir-v, opengl cannot link spir-v and glsl stages together.
+    */
+    pub fn with_spirv_file(mut self, stage: ShaderStage, path: &str) -> ShaderBuilder {
+        self.stages.push((stage, ShaderSource::SpirvFile(path.to_string())));
+        self
+    }
+
+    /*  This function attaches a stage whose code is given as spir-v words (for example the output of shaderTranslator::translate).
+    */
+    pub fn with_spirv(mut self, stage: ShaderStage, words: &[u32]) -> ShaderBuilder {
+        self.stages.push((stage, ShaderSource::Spirv(words.to_vec())));
+        self
+    }
+
+    /*  This function adds a define injected after the #version line of every stage.
+    */
+    pub fn with_define(mut self, name: &str, value: &str) -> ShaderBuilder {
+        self.preprocessor.define(name, value);
+        self
+    }
+
+    /*  This function enables the program binary cache.
+    *   The linked program is saved in the directory and loaded from it when the sources and the driver are the same.
+    *   If the driver does not support program binaries the shader is simply compiled.
+    */
+    pub fn with_binary_cache(mut self, directory: &str) -> ShaderBuilder {
+        self.binary_cache = Some(ProgramCache::new(directory));
+        self
+    }
+
+    /*  This function declares the outputs of the last vertex processing stage captured by transform feedback.
+    *   With gl::INTERLEAVED_ATTRIBS every varying is written in the buffer bound at index 0, one vertex after the other.
+    *   With gl::SEPARATE_ATTRIBS the i-th varying is written in the buffer bound at index i.
+    */
+    pub fn with_feedback_varyings(mut self, varyings: &[&str], buffer_mode: GLenum) -> ShaderBuilder {
+        self.feedback_varyings = Some((varyings.iter().map(|varying| varying.to_string()).collect(), buffer_mode));
+        self
+    }
+
+    /*  This function links the program as separable, so its stages can be mixed with the stages of other programs in a ProgramPipeline.
+    *   It needs opengl 4.1 or ARB_separate_shader_objects, otherwise build() returns ShaderError::Unsupported.
+    */
+    pub fn with_separable(mut self) -> ShaderBuilder {
+        self.separable = true;
+        self
+    }
+
+    /*  This function checks if the program is linked as separable.
+    */
+    pub fn is_separable(&self) -> bool {
+        self.separable
+    }
+
+    /*  This function returns the captured varyings and the buffer mode, if any.
+    */
+    pub fn get_feedback_varyings_ref(&self) -> &Option<(Vec::<String>, GLenum)> {
+        &self.feedback_varyings
+    }
+
+    /*  This function returns the attached stages.
+    */
+    pub fn get_stages_ref(&self) -> &Vec::<(ShaderStage, ShaderSource)> {
+        &self.stages
+    }
+
+    /*  This function returns the paths of the files used by the stages, included files too.
+    *   Those are the files watched by the shader hot reloading.
+    *   If a stage cannot be preprocessed only its own file is returned. The spir-v files are returned as they are.
+    */
+    pub fn get_source_files(&self) -> Vec::<String> {
+        let mut files = Vec::<String>::new();
+        for (stage, source) in &self.stages {
+            let stage_files = match source {
+                ShaderSource::File(path) => self.preprocessor.process_file(path)
+                    .map(|processed| processed.files)
+                    .unwrap_or_else(|_| vec![path.clone()]),
+                ShaderSource::Code(code) => self.preprocessor.process_code(code, &format!("<{} source>", stage))
+                    .map(|processed| processed.files.into_iter().skip(1).collect())
+                    .unwrap_or_default(),
+                ShaderSource::SpirvFile(path) => vec![path.clone()],
+                ShaderSource::Spirv(_) => Vec::<String>::new(),
+            };
+
+            for file in stage_files {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+
+        files
+    }
+
+    /*  This function compiles every stage and links them in a new Shader.
+    *   The Shader keeps a copy of the builder, so it can be rebuilt when reloaded.
+    */
+    pub fn build(&self) -> Result<Shader, ShaderError> {
+        let program_id = self.build_program()?;
+
+        Ok(Shader::from_program_id(program_id, self.clone()))
+    }
+
+    /*  This function compiles every stage and links them in a new opengl program, returning its id.
+    *   It checks that the stages make sense before touching opengl: there must be at least one stage, every stage can be attached only once and a compute stage cannot be mixed with the others.
+    *   If the binary cache is enabled the program is loaded from it when possible, otherwise it is compiled and saved.
+    *   The shaders are always deleted, even if a stage fails to compile.
+    */
+    pub(crate) fn build_program(&self) -> Result<GLuint, ShaderError> {
+        self.check_stages()?;
+
+        let mut sources = Vec::<(ShaderStage, PreparedSource)>::with_capacity(self.stages.len());
+        for (stage, source) in &self.stages {
+            sources.push((*stage, prepare_source(*stage, source, &self.preprocessor)?));
+        }
+
+        let cache = self.binary_cache.as_ref().filter(|_| ProgramCache::is_supported());
+        let cache_key = cache.map(|_| {
+            let bytes: Vec::<(ShaderStage, Cow<[u8]>)> = sources.iter().map(|(stage, source)| (*stage, source.get_bytes())).collect();
+            let codes: Vec::<(ShaderStage, &[u8])> = bytes.iter().map(|(stage, bytes)| (*stage, bytes.as_ref())).collect();
+            ProgramCache::get_key(&codes, &self.get_link_parameters())
+        });
+        if let (Some(cache), Some(cache_key)) = (cache, cache_key) {
+            if let Some(program) = cache.load(cache_key) {
+                return Ok(program);
+            }
+        }
+
+        let mut shaders = Vec::<GLuint>::with_capacity(sources.len());
+        let mut result = Ok(());
+        for (stage, source) in &sources {
+            let compiled = match source {
+                PreparedSource::Glsl(processed) => compile_preprocessed(*stage, processed),
+                PreparedSource::Spirv(path, words) => compile_spirv(*stage, words, path),
+            };
+            match compiled {
+                Ok(shader) => shaders.push(shader),
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                },
+            }
+        }
+
+        let program = result.and_then(|_| link_program(&shaders, cache.is_some(), self.separable, &self.feedback_varyings));
+
+        for shader in shaders {
+            unsafe {
+                gl::DeleteShader(shader);
+            }
+        }
+
+        if let (Ok(program), Some(cache), Some(cache_key)) = (&program, cache, cache_key) {
+            cache.save(cache_key, *program);
+        }
+
+        program
+    }
+
+    /*  This function describes the state set before linking, so programs linked differently have different cache keys.
+    */
+    fn get_link_parameters(&self) -> String {
+        let mut parameters = String::new();
+        if self.separable {
+            parameters.push_str("separable;");
+        }
+        if let Some((varyings, buffer_mode)) = &self.feedback_varyings {
+            parameters.push_str(&format!("feedback {} {};", buffer_mode, varyings.join(",")));
+        }
+
+        parameters
+    }
+
+    /*  This function checks the combination of stages.
+    */
+    fn check_stages(&self) -> Result<(), ShaderError> {
+        if self.stages.is_empty() {
+            return Err(ShaderError::InvalidStages("No stage attached to the shader.".to_string()));
+        }
+
+        for (i, (stage, _)) in self.stages.iter().enumerate() {
+            if self.stages[..i].iter().any(|(other, _)| other == stage) {
+                return Err(ShaderError::InvalidStages(format!("The {} stage is attached more than once.", stage)));
+            }
+        }
+
+        let has_compute = self.stages.iter().any(|(stage, _)| *stage == ShaderStage::Compute);
+        if has_compute && self.stages.len() > 1 {
+            return Err(ShaderError::InvalidStages("A compute stage cannot be linked with other stages.".to_string()));
+        }
+
+        let spirv_count = self.stages.iter().filter(|(_, source)| matches!(source, ShaderSource::SpirvFile(_) | ShaderSource::Spirv(_))).count();
+        if spirv_count > 0 && spirv_count < self.stages.len() {
+            return Err(ShaderError::InvalidStages("Spir-v stages cannot be linked with glsl stages.".to_string()));
+        }
+        if spirv_count > 0 && !capabilities::is_spirv_supported() {
+            let (major, minor) = capabilities::get_gl_version();
+            return Err(ShaderError::Unsupported(format!("Spir-v shaders need opengl 4.6 or ARB_gl_spirv, the context is {}.{}.", major, minor)));
+        }
+
+        if self.separable && !capabilities::is_separate_shader_supported() {
+            let (major, minor) = capabilities::get_gl_version();
+            return Err(ShaderError::Unsupported(format!("Separable programs need opengl 4.1 or ARB_separate_shader_objects, the context is {}.{}.", major, minor)));
+        }
+
+        Ok(())
+    }
+}
+
+/*  This function reads the code of a stage (if needed) and preprocesses it. The spir-v stages are only read.
+*   Code sources are reported as "<stage source>" in the errors.
+*/
+fn prepare_source(stage: ShaderStage, source: &ShaderSource, preprocessor: &Preprocessor) -> Result<PreparedSource, ShaderError> {
+    match source {
+        ShaderSource::File(path) => preprocessor.process_file(path).map(PreparedSource::Glsl),
+        ShaderSource::Code(code) => preprocessor.process_code(code, &format!("<{} source>", stage)).map(PreparedSource::Glsl),
+        ShaderSource::SpirvFile(path) => spirv::read_file(path).map(|words| PreparedSource::Spirv(path.clone(), words)),
+        ShaderSource::Spirv(words) => Ok(PreparedSource::Spirv(format!("<{} spir-v>", stage), words.clone())),
+    }
+}
+
+/*  This function compiles a preprocessed stage.
+*   The source string numbers in the compile log are replaced with the file paths.
+*/
+fn compile_preprocessed(stage: ShaderStage, processed: &PreprocessedSource) -> Result<GLuint, ShaderError> {
+    compile_shader(stage, &processed.code, &processed.files[0]).map_err(|error| match error {
+        ShaderError::Compile { stage, path, log } => ShaderError::Compile { stage, path, log: processed.resolve_log(&log) },
+        error => error,
+    })
+}
+
+/*  This function creates a shader of the given stage from spir-v words and specializes its main entry point.
+*   If the specialization fails the shader is deleted and the full info log is returned in the error.
+*/
+fn compile_spirv(stage: ShaderStage, words: &[u32], path: &str) -> Result<GLuint, ShaderError> {
+    unsafe {
+        let shader = gl::CreateShader(stage.gl_type());
+        spirv::specialize_shader(shader, words, "main");
+
+        let mut success = 0;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+        if success != gl::TRUE as GLint {
+            let mut log_length = 0;
+            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_length);
+            let mut info_log: Vec::<u8> = vec![0; log_length.max(1) as usize];
+            gl::GetShaderInfoLog(shader, log_length, ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
+            gl::DeleteShader(shader);
+
+            return Err(ShaderError::Compile {
+                stage,
+                path: path.to_string(),
+                log: info_log_to_string(info_log),
+            });
+        }
+
+        Ok(shader)
+    }
+}
+
+/*  This function creates and compiles a shader of the given stage.
+*   If the compilation fails the shader is deleted and the full info log is returned in the error.
+*   The path is only used to make the error readable.
+*/
+fn compile_shader(stage: ShaderStage, source: &str, path: &str) -> Result<GLuint, ShaderError> {
+    let c_str_source = CString::new(source.as_bytes()).map_err(|_| ShaderError::Compile {
+        stage,
+        path: path.to_string(),
+        log: "The shader source contains a nul byte.".to_string(),
+    })?;
+
+    unsafe {
+        let shader = gl::CreateShader(stage.gl_type());
+        gl::ShaderSource(shader, 1, &c_str_source.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut success = 0;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+        if success != gl::TRUE as GLint {
+            let mut log_length = 0;
+            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_length);
+            let mut info_log: Vec::<u8> = vec![0; log_length.max(1) as usize];
+            gl::GetShaderInfoLog(shader, log_length, ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
+            gl::DeleteShader(shader);
+
+            return Err(ShaderError::Compile {
+                stage,
+                path: path.to_string(),
+                log: info_log_to_string(info_log),
+            });
+        }
+
+        Ok(shader)
+    }
+}
+
+/*  This function links the compiled shaders in a new program.
+*   If retrievable is true the driver is asked to keep the program binary (used by the ProgramCache).
+*   If separable is true the program can be used in a ProgramPipeline.
+*   The transform feedback varyings, if any, are declared before linking.
+*   If the link fails the program is deleted and the full info log is returned in the error.
+*   Note: the shaders are only attached, the caller still owns them.
+*/
+fn link_program(shaders: &[GLuint], retrievable: bool, separable: bool, feedback_varyings: &Option<(Vec::<String>, GLenum)>) -> Result<GLuint, ShaderError> {
+    unsafe {
+        let shader_program = gl::CreateProgram();
+        for shader in shaders {
+            gl::AttachShader(shader_program, *shader);
+        }
+        if retrievable {
+            gl::ProgramParameteri(shader_program, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+        }
+        if separable {
+            gl::ProgramParameteri(shader_program, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint);
+        }
+        if let Some((varyings, buffer_mode)) = feedback_varyings {
+            let c_varyings = varyings.iter()
+                .map(|varying| CString::new(varying.as_bytes()))
+                .collect::<Result<Vec::<CString>, _>>()
+                .map_err(|_| ShaderError::Link { log: "A transform feedback varying contains a nul byte.".to_string() });
+            let c_varyings = match c_varyings {
+                Ok(c_varyings) => c_varyings,
+                Err(error) => {
+                    gl::DeleteProgram(shader_program);
+                    return Err(error);
+                },
+            };
+            let pointers: Vec::<*const GLchar> = c_varyings.iter().map(|varying| varying.as_ptr()).collect();
+            gl::TransformFeedbackVaryings(shader_program, pointers.len() as GLsizei, pointers.as_ptr(), *buffer_mode);
+        }
+        gl::LinkProgram(shader_program);
+
+        for shader in shaders {
+            gl::DetachShader(shader_program, *shader);
+        }
+
+        let mut success = 0;
+        gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut success);
+        if success != gl::TRUE as GLint {
+            let mut log_length = 0;
+            gl::GetProgramiv(shader_program, gl::INFO_LOG_LENGTH, &mut log_length);
+            let mut info_log: Vec::<u8> = vec![0; log_length.max(1) as usize];
+            gl::GetProgramInfoLog(shader_program, log_length, ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
+            gl::DeleteProgram(shader_program);
+
+            return Err(ShaderError::Link {
+                log: info_log_to_string(info_log),
+            });
+        }
+
+        Ok(shader_program)
+    }
+}
+
+/*  This function converts an opengl info log in a string, removing the nul terminator.
+*/
+fn info_log_to_string(mut info_log: Vec::<u8>) -> String {
+    while info_log.last() == Some(&0) {
+        info_log.pop();
+    }
+
+    String::from_utf8_lossy(&info_log).into_owned()
+}
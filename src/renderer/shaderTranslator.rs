@@ -0,0 +1,787 @@
+/*  File: renderer/shaderTranslator.rs
+*   Author: Vicix
+*
+*   This file contains the offline shader translator. It is only compiled with the shader_tools feature.
+*   It translates the opengl glsl of the shaders in another dialect with naga, without an opengl context:
+*   glsl 330 for opengl 3.3, spir-v for opengl 4.6 (loaded with ShaderBuilder::with_spirv) and glsl 300 es for opengl es 3.0.
+*   So the shaders are written once, and the translation can be checked by comparing the output, without a gpu.
+*
+*   naga writes its own names, so the glsl output is fixed to keep working with Shader and Material:
+*   the textures, the loose uniforms, the vertex inputs, the outputs and the inputs between the stages and the fragment outputs keep their original names,
+*   so a translated stage can be linked with an untranslated one. The names used inside the stage are changed instead (TexCoord can become TexCoord_2).
+*
+*   The spir-v output reads the loose uniforms from a uniform block (see get_spirv_globals_binding).
+*   naga writes separate images and samplers, but opengl only supports combined samplers in spir-v, so they are merged back (see combine_samplers).
+*   The textures are bound to the texture units in declaration order: the first one reads the unit 0, the second the unit 1...
+*
+*   Example:
+*       let processed = Preprocessor::new().process_file("./src/shaders/vert2.glsl")?;
+*       let code = shaderTranslator::translate(ShaderStage::Vertex, &processed, ShaderTarget::Gles30)?;
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::shader::ShaderStage;
+use crate::renderer::errors::ShaderError;
+use crate::renderer::preprocessor::PreprocessedSource;
+use crate::renderer::glslFrontend::{self, GLOBALS_BLOCK_NAME};
+
+use naga::back::{glsl, spv};
+use naga::valid::ModuleInfo;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/*  This is the first binding point of the uniform blocks with the loose uniforms in the spir-v output.
+*   Every stage has its own block, at this binding point plus the index of the stage, so the blocks of two stages do not overlap.
+*/
+pub const SPIRV_GLOBALS_BINDING: GLuint = 16;
+
+/*  This enum lists the dialects a shader can be translated to.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderTarget {
+    Gl33,
+    Gl46Spirv,
+    Gles30,
+}
+
+impl ShaderTarget {
+    /*  This function returns the extension of the files written for the target (see the translate_shaders command).
+    */
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ShaderTarget::Gl33 => "glsl",
+            ShaderTarget::Gl46Spirv => "spv",
+            ShaderTarget::Gles30 => "es.glsl",
+        }
+    }
+}
+
+impl fmt::Display for ShaderTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ShaderTarget::Gl33 => "opengl 3.3",
+            ShaderTarget::Gl46Spirv => "opengl 4.6 spir-v",
+            ShaderTarget::Gles30 => "opengl es 3.0",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/*  This enum is the translated code: glsl code or spir-v words.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranslatedShader {
+    Glsl(String),
+    Spirv(Vec::<u32>),
+}
+
+/*  This function translates a preprocessed stage to a target.
+*   The stage is parsed and validated first (see glslFrontend::parse_and_validate), so the same errors are returned.
+*   If naga cannot write the stage for the target it returns ShaderError::Unsupported.
+*/
+pub fn translate(stage: ShaderStage, processed: &PreprocessedSource, target: ShaderTarget) -> Result<TranslatedShader, ShaderError> {
+    let (module, info) = glslFrontend::parse_and_validate(stage, processed)?;
+    let naga_stage = module.entry_points.first().map(|entry_point| entry_point.stage).unwrap_or(naga::ShaderStage::Vertex);
+    let unsupported = |error: String| ShaderError::Unsupported(format!("{} cannot be translated to {}: {}", processed.files[0], target, error));
+
+    match target {
+        ShaderTarget::Gl33 => write_glsl(&module, &info, naga_stage, glsl::Version::Desktop(330)).map(TranslatedShader::Glsl).map_err(unsupported),
+        ShaderTarget::Gles30 => write_glsl(&module, &info, naga_stage, glsl::Version::Embedded { version: 300, is_webgl: false }).map(TranslatedShader::Glsl).map_err(unsupported),
+        ShaderTarget::Gl46Spirv => write_spirv(&module, &info, stage, naga_stage).map(TranslatedShader::Spirv).map_err(unsupported),
+    }
+}
+
+/*  This function returns the binding point of the uniform block with the loose uniforms of a stage in the spir-v output.
+*   A Ubo with the same members (in the same order) must be bound there.
+*/
+pub fn get_spirv_globals_binding(stage: ShaderStage) -> GLuint {
+    let index = match stage {
+        ShaderStage::Vertex | ShaderStage::Compute => 0,
+        ShaderStage::Fragment => 1,
+        ShaderStage::Geometry => 2,
+        ShaderStage::TessControl => 3,
+        ShaderStage::TessEvaluation => 4,
+    };
+
+    SPIRV_GLOBALS_BINDING + index
+}
+
+/*  This function writes a module as glsl and gives back the original names of the textures and of the loose uniforms.
+*   The coordinate space is not changed, because the input is already opengl glsl.
+*/
+fn write_glsl(module: &naga::Module, info: &ModuleInfo, naga_stage: naga::ShaderStage, version: glsl::Version) -> Result<String, String> {
+    let options = glsl::Options {
+        version,
+        writer_flags: glsl::WriterFlags::empty(),
+        ..Default::default()
+    };
+    let pipeline_options = glsl::PipelineOptions {
+        shader_stage: naga_stage,
+        entry_point: "main".to_string(),
+        multiview: None,
+    };
+
+    let mut code = String::new();
+    let reflection = glsl::Writer::new(&mut code, module, info, &options, &pipeline_options, naga::proc::BoundsCheckPolicies::default())
+        .and_then(|mut writer| writer.write())
+        .map_err(|error| error.to_string())?;
+
+    let mut array_sizes = HashMap::<String, u32>::new();
+    for (name, mapping) in &reflection.texture_mapping {
+        if let Some(original_name) = &module.global_variables[mapping.texture].name {
+            code = code.lines().map(|line| glslFrontend::replace_identifier(line, name, original_name) + "\n").collect();
+
+            if let Some((array_name, index)) = split_element_name(original_name) {
+                let size = array_sizes.entry(array_name.to_string()).or_insert(0);
+                *size = (*size).max(index + 1);
+            }
+        }
+    }
+    if !array_sizes.is_empty() {
+        code = restore_opaque_arrays(&code, &array_sizes);
+    }
+
+    let globals_block = reflection.uniforms.iter()
+        .find(|(handle, _)| is_globals_block(module, &module.global_variables[**handle]))
+        .map(|(_, block_name)| block_name.clone());
+    if let Some(block_name) = globals_block {
+        code = unwrap_globals_block(&code, &block_name)?;
+    }
+
+    Ok(restore_interface_names(&code, &get_interface_names(module, naga_stage)))
+}
+
+/*  This function splits the name of an element of an array of textures (tex[1]) in the name of the array and the index (see glslFrontend::split_opaque_arrays).
+*/
+fn split_element_name(name: &str) -> Option<(&str, u32)> {
+    let (array_name, index) = name.strip_suffix(']')?.split_once('[')?;
+
+    index.parse().ok().map(|index| (array_name, index))
+}
+
+/*  This function declares the arrays of textures again: the element 0 becomes the array (uniform sampler2D tex[2];) and the other elements are removed.
+*   The uses (tex[1]) are already the original ones.
+*/
+fn restore_opaque_arrays(code: &str, array_sizes: &HashMap<String, u32>) -> String {
+    let mut result = String::with_capacity(code.len());
+    for line in code.lines() {
+        let element = line.trim().strip_prefix("uniform ")
+            .and_then(|declaration| declaration.strip_suffix(';'))
+            .and_then(|declaration| declaration.rsplit(' ').next())
+            .and_then(split_element_name)
+            .filter(|(array_name, _)| array_sizes.contains_key(*array_name));
+
+        match element {
+            Some((array_name, 0)) => result.push_str(&line.replace(&format!("{}[0];", array_name), &format!("{}[{}];", array_name, array_sizes[array_name]))),
+            Some(_) => continue,
+            None => result.push_str(line),
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+/*  This function returns the names written by naga and the original names of the inputs and of the outputs of a vertex or a fragment stage.
+*   naga calls them _p2vs_locationN (vertex inputs), _vs2fs_locationN (between the stages) and _fs2p_locationN (fragment outputs).
+*/
+fn get_interface_names(module: &naga::Module, naga_stage: naga::ShaderStage) -> Vec::<(String, String)> {
+    let (input_prefix, output_prefix) = match naga_stage {
+        naga::ShaderStage::Vertex => ("_p2vs", "_vs2fs"),
+        naga::ShaderStage::Fragment => ("_vs2fs", "_fs2p"),
+        _ => return Vec::<(String, String)>::new(),
+    };
+
+    let mut names = Vec::<(String, String)>::new();
+    let mut add_names = |prefix: &str, binding: &Option<naga::Binding>, name: &Option<String>, ty: naga::Handle<naga::Type>| {
+        match (binding, &module.types[ty].inner) {
+            (Some(naga::Binding::Location { location, .. }), _) => names.push((format!("{}_location{}", prefix, location), name.clone().unwrap_or_default())),
+            (None, naga::TypeInner::Struct { members, .. }) => {
+                for member in members {
+                    if let Some(naga::Binding::Location { location, .. }) = member.binding {
+                        names.push((format!("{}_location{}", prefix, location), member.name.clone().unwrap_or_default()));
+                    }
+                }
+            },
+            _ => (),
+        }
+    };
+
+    for entry_point in module.entry_points.iter().filter(|entry_point| entry_point.stage == naga_stage) {
+        for argument in &entry_point.function.arguments {
+            add_names(input_prefix, &argument.binding, &argument.name, argument.ty);
+        }
+        if let Some(result) = &entry_point.function.result {
+            add_names(output_prefix, &result.binding, &None, result.ty);
+        }
+    }
+
+    names.retain(|(_, original_name)| !original_name.is_empty());
+    names
+}
+
+/*  This function gives back the original names of the inputs and of the outputs.
+*   naga already uses those names inside the stage (for the private copies and the struct members), so they are renamed first:
+*   the original name followed by a number not used in the code.
+*/
+fn restore_interface_names(code: &str, names: &[(String, String)]) -> String {
+    let rename = |code: &str, name: &str, new_name: &str| -> String {
+        code.lines().map(|line| glslFrontend::replace_identifier(line, name, new_name) + "\n").collect()
+    };
+    let is_used = |code: &str, name: &str| rename(code, name, "") != code;
+
+    let mut code = code.to_string();
+    for (_, original_name) in names {
+        let free_name = (1..).map(|i| format!("{}_{}", original_name, i))
+            .find(|free_name| !is_used(&code, free_name) && names.iter().all(|(_, other)| other != free_name))
+            .unwrap_or_default();
+        code = rename(&code, original_name, &free_name);
+    }
+    for (name, original_name) in names {
+        code = rename(&code, name, original_name);
+    }
+
+    code
+}
+
+/*  This function replaces the uniform block with the loose uniforms with the loose uniforms themselves.
+*   naga writes the block as "layout(std140) uniform Block { Struct instance; };", with the members declared in the struct,
+*   so every member becomes a uniform and every instance.member becomes member.
+*/
+fn unwrap_globals_block(code: &str, block_name: &str) -> Result<String, String> {
+    let declaration_start = format!("uniform {} {{", block_name);
+    let declaration = code.lines().find(|line| line.contains(&declaration_start))
+        .ok_or_else(|| format!("The uniform block {} was not found in the output.", block_name))?;
+    let mut words = declaration[declaration.find('{').unwrap_or(0) + 1..].split_whitespace();
+    let struct_name = words.next().unwrap_or("").to_string();
+    let instance = words.next().unwrap_or("").trim_end_matches(';').to_string();
+
+    let struct_start = format!("struct {} {{", struct_name);
+    let mut output = Vec::<String>::new();
+    let mut members = Vec::<String>::new();
+    let mut in_struct = false;
+    for line in code.lines() {
+        if line.trim() == struct_start {
+            in_struct = true;
+        } else if in_struct {
+            if line.trim() == "};" {
+                in_struct = false;
+            } else if !line.trim().starts_with("_pad") {
+                members.push(format!("uniform {}", line.trim()));
+            }
+        } else if line.contains(&declaration_start) {
+            output.append(&mut members);
+        } else {
+            output.push(line.replace(&format!("{}.", instance), ""));
+        }
+    }
+
+    let mut result = output.join("\n");
+    result.push('\n');
+
+    Ok(result)
+}
+
+/*  This function writes a module as spir-v 1.0 for opengl.
+*   The loose uniforms block is moved to the binding point of the stage, the textures to the texture units in declaration order
+*   and the coordinate space is not changed. The textures and their samplers are merged in combined samplers.
+*/
+fn write_spirv(module: &naga::Module, info: &ModuleInfo, stage: ShaderStage, naga_stage: naga::ShaderStage) -> Result<Vec::<u32>, String> {
+    let mut binding_map = spv::BindingMap::default();
+    let mut texture_unit = 0;
+    for (_, global) in get_declaration_order(module) {
+        let binding = match &global.binding {
+            Some(binding) => *binding,
+            None => continue,
+        };
+
+        let new_binding = match module.types[global.ty].inner {
+            _ if is_globals_block(module, global) => get_spirv_globals_binding(stage),
+            naga::TypeInner::Image { class: naga::ImageClass::Sampled { .. }, .. } | naga::TypeInner::Image { class: naga::ImageClass::Depth { .. }, .. } => {
+                texture_unit += 1;
+                texture_unit - 1
+            },
+            _ => continue,
+        };
+        binding_map.insert(binding, spv::BindingInfo {
+            descriptor_set: 0,
+            binding: new_binding,
+            binding_array_size: None,
+        });
+    }
+
+    let options = spv::Options {
+        lang_version: (1, 0),
+        flags: spv::WriterFlags::empty(),
+        binding_map,
+        ..Default::default()
+    };
+    let pipeline_options = spv::PipelineOptions {
+        shader_stage: naga_stage,
+        entry_point: "main".to_string(),
+    };
+
+    let words = spv::write_vec(module, info, &options, Some(&pipeline_options)).map_err(|error| error.to_string())?;
+
+    combine_samplers(&words)
+}
+
+/*  This function returns the global variables in declaration order.
+*   The elements of the arrays of textures are added at the end by glslFrontend::split_opaque_arrays, so they are sorted back where the array is declared.
+*/
+fn get_declaration_order(module: &naga::Module) -> Vec::<(naga::Handle<naga::GlobalVariable>, &naga::GlobalVariable)> {
+    let mut globals = module.global_variables.iter().collect::<Vec::<_>>();
+    globals.sort_by_key(|(handle, _)| module.global_variables.get_span(*handle).to_range().map_or(0, |range| range.start));
+
+    globals
+}
+
+/*  These are the spir-v opcodes and the storage class used by combine_samplers.
+*/
+const OP_SOURCE: u32 = 3;
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_STRING: u32 = 7;
+const OP_LINE: u32 = 8;
+const OP_EXTENSION: u32 = 10;
+const OP_EXT_INST_IMPORT: u32 = 11;
+const OP_MEMORY_MODEL: u32 = 14;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_EXECUTION_MODE: u32 = 16;
+const OP_CAPABILITY: u32 = 17;
+const OP_TYPE_VOID: u32 = 19;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_TYPE_FUNCTION: u32 = 33;
+const OP_TYPE_FORWARD_POINTER: u32 = 39;
+const OP_FUNCTION: u32 = 54;
+const OP_FUNCTION_PARAMETER: u32 = 55;
+const OP_FUNCTION_END: u32 = 56;
+const OP_VARIABLE: u32 = 59;
+const OP_LOAD: u32 = 61;
+const OP_STORE: u32 = 62;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const OP_SAMPLED_IMAGE: u32 = 86;
+const OP_IMAGE: u32 = 100;
+const OP_LOOP_MERGE: u32 = 246;
+const OP_RETURN_VALUE: u32 = 254;
+const UNIFORM_CONSTANT: u32 = 0;
+
+/*  This function merges the separate textures and samplers written by naga in combined samplers, the only ones supported by opengl.
+*   Every sampled texture variable becomes a combined sampler (the image is read back with OpImage where naga used it alone),
+*   every OpSampledImage becomes a load of the combined sampler and the sampler variables are removed.
+*   The sampling parameters come from the texture (or from the Sampler bound to its unit), like in opengl glsl.
+*/
+fn combine_samplers(words: &[u32]) -> Result<Vec::<u32>, String> {
+    if words.len() < 5 {
+        return Err("The spir-v module has no header.".to_string());
+    }
+
+    let mut instructions = Vec::<Vec::<u32>>::new();
+    let mut offset = 5;
+    while offset < words.len() {
+        let word_count = (words[offset] >> 16) as usize;
+        if word_count == 0 || offset + word_count > words.len() {
+            return Err(format!("The spir-v module has a broken instruction at word {}.", offset));
+        }
+        instructions.push(words[offset..offset + word_count].to_vec());
+        offset += word_count;
+    }
+    let opcode = |instruction: &Vec::<u32>| instruction[0] & 0xFFFF;
+
+    //the types and the variables: a texture is a variable of a sampled image (not a storage image).
+    let mut sampled_images = HashMap::<u32, u32>::new();
+    let mut pointers = HashMap::<(u32, u32), u32>::new();
+    let mut pointees = HashMap::<u32, u32>::new();
+    let mut sampled_image_types = HashSet::<u32>::new();
+    let mut sampler_types = HashSet::<u32>::new();
+    let mut textures = HashMap::<u32, u32>::new();
+    let mut samplers = HashSet::<u32>::new();
+    for instruction in &instructions {
+        match opcode(instruction) {
+            OP_TYPE_IMAGE if instruction.len() > 7 && instruction[7] == 1 => {
+                sampled_image_types.insert(instruction[1]);
+            },
+            OP_TYPE_SAMPLER => {
+                sampler_types.insert(instruction[1]);
+            },
+            OP_TYPE_SAMPLED_IMAGE => {
+                sampled_images.insert(instruction[2], instruction[1]);
+            },
+            OP_TYPE_POINTER => {
+                pointers.insert((instruction[2], instruction[3]), instruction[1]);
+                if instruction[2] == UNIFORM_CONSTANT {
+                    pointees.insert(instruction[1], instruction[3]);
+                }
+            },
+            OP_VARIABLE if instruction[3] == UNIFORM_CONSTANT => {
+                match pointees.get(&instruction[1]) {
+                    Some(image_type) if sampled_image_types.contains(image_type) => {
+                        textures.insert(instruction[2], *image_type);
+                    },
+                    Some(sampler_type) if sampler_types.contains(sampler_type) => {
+                        samplers.insert(instruction[2]);
+                    },
+                    _ => (),
+                }
+            },
+            OP_FUNCTION_PARAMETER if sampled_image_types.contains(&instruction[1]) || sampler_types.contains(&instruction[1]) => {
+                return Err("A texture or a sampler is passed to a function, it cannot be merged in a combined sampler.".to_string());
+            },
+            _ => (),
+        }
+    }
+    if textures.is_empty() && samplers.is_empty() {
+        return Ok(words.to_vec());
+    }
+
+    //the combined sampler type and its pointer of every texture, declared if naga did not need them.
+    let mut next_id = words[3];
+    let mut new_types = Vec::<Vec::<u32>>::new();
+    let mut combined_types = HashMap::<u32, (u32, u32)>::new();
+    for image_type in textures.values() {
+        if combined_types.contains_key(image_type) {
+            continue;
+        }
+
+        let sampled_image = *sampled_images.entry(*image_type).or_insert_with(|| {
+            new_types.push(vec![(3 << 16) | OP_TYPE_SAMPLED_IMAGE, next_id, *image_type]);
+            next_id += 1;
+            next_id - 1
+        });
+        let pointer = *pointers.entry((UNIFORM_CONSTANT, sampled_image)).or_insert_with(|| {
+            new_types.push(vec![(4 << 16) | OP_TYPE_POINTER, next_id, UNIFORM_CONSTANT, sampled_image]);
+            next_id += 1;
+            next_id - 1
+        });
+        combined_types.insert(*image_type, (sampled_image, pointer));
+    }
+
+    //the texture variables are declared again after the new types, before the first function.
+    let mut declarations = new_types;
+    let mut texture_ids = textures.keys().copied().collect::<Vec::<u32>>();
+    texture_ids.sort_unstable();
+    for texture in texture_ids {
+        declarations.push(vec![(4 << 16) | OP_VARIABLE, combined_types[&textures[&texture]].1, texture, UNIFORM_CONSTANT]);
+    }
+
+    let mut output = Vec::<Vec::<u32>>::with_capacity(instructions.len() + declarations.len());
+    let mut image_loads = HashMap::<u32, u32>::new();
+    let mut sampler_loads = HashSet::<u32>::new();
+    for instruction in instructions {
+        match opcode(&instruction) {
+            OP_NAME | OP_DECORATE if samplers.contains(&instruction[1]) => (),
+            OP_VARIABLE if samplers.contains(&instruction[2]) || textures.contains_key(&instruction[2]) => (),
+            OP_FUNCTION if !declarations.is_empty() => {
+                output.append(&mut declarations);
+                output.push(instruction);
+            },
+            OP_LOAD if samplers.contains(&instruction[3]) => {
+                sampler_loads.insert(instruction[2]);
+            },
+            OP_LOAD if textures.contains_key(&instruction[3]) => {
+                let sampled_image = combined_types[&textures[&instruction[3]]].0;
+                output.push(vec![(4 << 16) | OP_LOAD, sampled_image, next_id, instruction[3]]);
+                output.push(vec![(4 << 16) | OP_IMAGE, instruction[1], instruction[2], next_id]);
+                image_loads.insert(instruction[2], instruction[3]);
+                next_id += 1;
+            },
+            OP_SAMPLED_IMAGE => {
+                match (image_loads.get(&instruction[3]), sampler_loads.contains(&instruction[4])) {
+                    (Some(texture), true) => output.push(vec![(4 << 16) | OP_LOAD, instruction[1], instruction[2], *texture]),
+                    _ => return Err("A texture is sampled with an image or a sampler that is not a global variable, it cannot be merged in a combined sampler.".to_string()),
+                }
+            },
+            _ => output.push(instruction),
+        }
+    }
+
+    //the sampler types and the pointers left without users are removed too.
+    let mut unused_types = pointers.iter()
+        .filter(|((storage, pointee), _)| *storage == UNIFORM_CONSTANT && (sampler_types.contains(pointee) || textures.values().any(|image_type| image_type == pointee)))
+        .map(|(_, pointer)| *pointer)
+        .collect::<Vec::<u32>>();
+    unused_types.sort_unstable();
+    unused_types.extend(sampler_types);
+    for type_id in unused_types {
+        if !output.iter().any(|instruction| uses_type(instruction, type_id)) {
+            output.retain(|instruction| !(matches!(opcode(instruction), OP_NAME | OP_DECORATE | OP_TYPE_SAMPLER | OP_TYPE_POINTER) && instruction[1] == type_id));
+        }
+    }
+
+    let mut result = words[..5].to_vec();
+    result[3] = next_id;
+    for instruction in output {
+        result.extend(instruction);
+    }
+
+    Ok(result)
+}
+
+/*  This function checks if an instruction uses a type: a type declared with it or a value of that type.
+*   The names, the decorations and the instructions without a result type (where the second word is not a type) are not uses.
+*   Only the id operands of the type declarations are checked, their literals (the storage class of a pointer, the size of an int...) can have the same value as the id.
+*/
+fn uses_type(instruction: &[u32], type_id: u32) -> bool {
+    match instruction[0] & 0xFFFF {
+        OP_TYPE_POINTER => instruction.get(3) == Some(&type_id),
+        OP_TYPE_VECTOR | OP_TYPE_MATRIX | OP_TYPE_IMAGE | OP_TYPE_SAMPLED_IMAGE | OP_TYPE_RUNTIME_ARRAY => instruction.get(2) == Some(&type_id),
+        OP_TYPE_ARRAY | OP_TYPE_STRUCT | OP_TYPE_FUNCTION => instruction[2..].contains(&type_id),
+        OP_TYPE_VOID..=OP_TYPE_FORWARD_POINTER => false,
+        OP_SOURCE | OP_NAME | OP_MEMBER_NAME | OP_STRING | OP_LINE | OP_EXTENSION | OP_EXT_INST_IMPORT | OP_MEMORY_MODEL | OP_ENTRY_POINT | OP_EXECUTION_MODE | OP_CAPABILITY
+            | OP_FUNCTION_END | OP_STORE | OP_DECORATE | OP_MEMBER_DECORATE | OP_LOOP_MERGE..=OP_RETURN_VALUE => false,
+        _ => instruction.get(1) == Some(&type_id),
+    }
+}
+
+/*  This function checks if a global variable is the uniform block with the loose uniforms created by glslFrontend::to_naga_glsl.
+*   The block has no instance name, so naga can give its name to the variable or only to its type.
+*/
+fn is_globals_block(module: &naga::Module, global: &naga::GlobalVariable) -> bool {
+    global.name.as_deref() == Some(GLOBALS_BLOCK_NAME) || module.types[global.ty].name.as_deref() == Some(GLOBALS_BLOCK_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::preprocessor::Preprocessor;
+    use crate::renderer::spirv::SPIR_V_MAGIC;
+
+    const VERTEX: &str = "#version 330 core
+layout (location = 0) in vec2 aPos;
+layout (location = 1) in vec2 aTexCoord;
+out vec2 TexCoord;
+uniform float offset;
+void main() {
+    gl_Position = vec4(aPos.x + offset, aPos.y, 0.0, 1.0);
+    TexCoord = aTexCoord;
+}
+";
+
+    const FRAGMENT: &str = "#version 330 core
+in vec2 TexCoord;
+out vec4 FragColor;
+uniform sampler2D diffuse;
+uniform sampler2DShadow shadow_map;
+uniform float strength;
+void main() {
+    float lit = texture(shadow_map, vec3(TexCoord, 0.5));
+    FragColor = texture(diffuse, TexCoord) * strength * lit + texelFetch(diffuse, ivec2(0), 0) * float(textureSize(diffuse, 0).x);
+}
+";
+
+    /*  This function translates an inline shader.
+    */
+    fn translate_code(stage: ShaderStage, code: &str, target: ShaderTarget) -> TranslatedShader {
+        let processed = Preprocessor::new().process_code(code, "test.glsl").unwrap();
+        translate(stage, &processed, target).unwrap()
+    }
+
+    fn get_glsl(shader: TranslatedShader) -> String {
+        match shader {
+            TranslatedShader::Glsl(code) => code,
+            TranslatedShader::Spirv(_) => panic!("Expected glsl code."),
+        }
+    }
+
+    fn get_instructions(shader: TranslatedShader) -> Vec::<Vec::<u32>> {
+        let words = match shader {
+            TranslatedShader::Spirv(words) => words,
+            TranslatedShader::Glsl(_) => panic!("Expected spir-v words."),
+        };
+        assert_eq!(words[0], SPIR_V_MAGIC);
+
+        let mut instructions = Vec::<Vec::<u32>>::new();
+        let mut offset = 5;
+        while offset < words.len() {
+            let word_count = (words[offset] >> 16) as usize;
+            assert!(word_count > 0 && offset + word_count <= words.len());
+            assert!(words[offset + 1..offset + word_count].iter().all(|word| *word < words[3] || words[offset] & 0xFFFF != OP_LOAD));
+            instructions.push(words[offset..offset + word_count].to_vec());
+            offset += word_count;
+        }
+
+        instructions
+    }
+
+    fn count_opcode(instructions: &[Vec::<u32>], opcode: u32) -> usize {
+        instructions.iter().filter(|instruction| instruction[0] & 0xFFFF == opcode).count()
+    }
+
+    #[test]
+    fn gl33_keeps_the_interface_names() {
+        let vertex = get_glsl(translate_code(ShaderStage::Vertex, VERTEX, ShaderTarget::Gl33));
+        assert!(vertex.starts_with("#version 330 core"));
+        assert!(vertex.contains("layout(location = 0) in vec2 aPos;"));
+        assert!(vertex.contains("layout(location = 1) in vec2 aTexCoord;"));
+        assert!(vertex.contains("out vec2 TexCoord;"));
+        assert!(vertex.contains("uniform float offset;"));
+        assert!(!vertex.contains("_location"));
+        assert!(!vertex.contains(GLOBALS_BLOCK_NAME));
+
+        let fragment = get_glsl(translate_code(ShaderStage::Fragment, FRAGMENT, ShaderTarget::Gl33));
+        assert!(fragment.contains("in vec2 TexCoord;"));
+        assert!(fragment.contains("out vec4 FragColor;"));
+        assert!(fragment.contains("uniform sampler2D diffuse;"));
+        assert!(fragment.contains("uniform sampler2DShadow shadow_map;"));
+        assert!(fragment.contains("uniform float strength;"));
+        assert!(!fragment.contains("_location"));
+    }
+
+    #[test]
+    fn gles30_writes_es_glsl() {
+        let vertex = get_glsl(translate_code(ShaderStage::Vertex, VERTEX, ShaderTarget::Gles30));
+        assert!(vertex.starts_with("#version 300 es"));
+        assert!(vertex.contains("out vec2 TexCoord;"));
+
+        let fragment = get_glsl(translate_code(ShaderStage::Fragment, FRAGMENT, ShaderTarget::Gles30));
+        assert!(fragment.starts_with("#version 300 es"));
+        assert!(fragment.contains("precision highp float;"));
+        assert!(fragment.contains("uniform highp sampler2D diffuse;"));
+        assert!(fragment.contains("in vec2 TexCoord;"));
+    }
+
+    #[test]
+    fn interface_names_do_not_collide() {
+        let code = "struct Output { vec2 TexCoord; };\nvec2 TexCoord = vec2(0.0);\nvec2 TexCoord_1;\nout vec2 _vs2fs_location0;\nvoid main() { _vs2fs_location0 = Output(TexCoord).TexCoord; }\n";
+        let names = [("_vs2fs_location0".to_string(), "TexCoord".to_string())];
+
+        assert_eq!(restore_interface_names(code, &names),
+            "struct Output { vec2 TexCoord_2; };\nvec2 TexCoord_2 = vec2(0.0);\nvec2 TexCoord_1;\nout vec2 TexCoord;\nvoid main() { TexCoord = Output(TexCoord_2).TexCoord_2; }\n");
+    }
+
+    #[test]
+    fn spirv_uses_combined_samplers() {
+        let instructions = get_instructions(translate_code(ShaderStage::Fragment, FRAGMENT, ShaderTarget::Gl46Spirv));
+
+        assert_eq!(count_opcode(&instructions, OP_TYPE_SAMPLER), 0);
+        assert_eq!(count_opcode(&instructions, OP_SAMPLED_IMAGE), 0);
+        assert_eq!(count_opcode(&instructions, OP_TYPE_SAMPLED_IMAGE), 2);
+
+        //the two textures are combined samplers on the units 0 and 1, the loose uniforms are in the block of the fragment stage.
+        let combined_pointers: Vec::<u32> = instructions.iter()
+            .filter(|instruction| instruction[0] & 0xFFFF == OP_TYPE_POINTER && instruction[2] == UNIFORM_CONSTANT)
+            .map(|instruction| instruction[1])
+            .collect();
+        let textures: Vec::<u32> = instructions.iter()
+            .filter(|instruction| instruction[0] & 0xFFFF == OP_VARIABLE && combined_pointers.contains(&instruction[1]))
+            .map(|instruction| instruction[2])
+            .collect();
+        assert_eq!(textures.len(), 2);
+
+        let mut bindings: Vec::<u32> = instructions.iter()
+            .filter(|instruction| instruction[0] & 0xFFFF == OP_DECORATE && instruction[2] == 33)
+            .map(|instruction| instruction[3])
+            .collect();
+        bindings.sort_unstable();
+        assert_eq!(bindings, vec![0, 1, get_spirv_globals_binding(ShaderStage::Fragment)]);
+
+        //every variable is declared before it is used and every id is below the bound.
+        let first_function = instructions.iter().position(|instruction| instruction[0] & 0xFFFF == OP_FUNCTION).unwrap();
+        for texture in textures {
+            let declaration = instructions.iter().position(|instruction| instruction[0] & 0xFFFF == OP_VARIABLE && instruction[2] == texture).unwrap();
+            assert!(declaration < first_function);
+        }
+    }
+
+    const SAMPLER_ARRAY: &str = "#version 330 core
+in vec2 TexCoord;
+out vec4 FragColor;
+uniform sampler2D layers[2];
+uniform sampler2D mask;
+void main() {
+    FragColor = mix(texture(layers[0], TexCoord), texture(layers[1], TexCoord), texture(mask, TexCoord).r);
+}
+";
+
+    const STRUCT_UNIFORM: &str = "#version 330 core
+out vec4 FragColor;
+uniform float exposure = 1.0;
+struct Light {
+    vec3 color;
+    float intensity;
+};
+uniform Light light;
+void main() {
+    FragColor = vec4(light.color * light.intensity * exposure, 1.0);
+}
+";
+
+    #[test]
+    fn sampler_arrays_are_translated() {
+        let code = get_glsl(translate_code(ShaderStage::Fragment, SAMPLER_ARRAY, ShaderTarget::Gl33));
+        assert!(code.contains("uniform sampler2D layers[2];"));
+        assert!(code.contains("uniform sampler2D mask;"));
+        assert!(code.contains("texture(layers[0], "));
+        assert!(code.contains("texture(layers[1], "));
+        assert!(!code.contains("layers[1];"));
+
+        let code = get_glsl(translate_code(ShaderStage::Fragment, SAMPLER_ARRAY, ShaderTarget::Gles30));
+        assert!(code.contains("uniform highp sampler2D layers[2];"));
+
+        let instructions = get_instructions(translate_code(ShaderStage::Fragment, SAMPLER_ARRAY, ShaderTarget::Gl46Spirv));
+        assert_eq!(count_opcode(&instructions, OP_TYPE_SAMPLER), 0);
+        let mut bindings: Vec::<u32> = instructions.iter()
+            .filter(|instruction| instruction[0] & 0xFFFF == OP_DECORATE && instruction[2] == 33)
+            .map(|instruction| instruction[3])
+            .collect();
+        bindings.sort_unstable();
+        assert_eq!(bindings, vec![0, 1, 2]);
+
+        //the textures get the units in this order: the elements of the array use the units 0 and 1, the texture declared after it the unit 2.
+        let processed = Preprocessor::new().process_code(SAMPLER_ARRAY, "test.glsl").unwrap();
+        let (module, _) = glslFrontend::parse_and_validate(ShaderStage::Fragment, &processed).unwrap();
+        let textures: Vec::<&str> = get_declaration_order(&module).into_iter()
+            .filter(|(_, global)| matches!(module.types[global.ty].inner, naga::TypeInner::Image { .. }))
+            .filter_map(|(_, global)| global.name.as_deref())
+            .collect();
+        assert_eq!(textures, vec!["layers[0]", "layers[1]", "mask"]);
+    }
+
+    #[test]
+    fn struct_uniforms_are_translated() {
+        let code = get_glsl(translate_code(ShaderStage::Fragment, STRUCT_UNIFORM, ShaderTarget::Gl33));
+        assert!(code.contains("struct Light {"));
+        assert!(code.contains("uniform float exposure;"));
+        assert!(code.contains("uniform Light light;"));
+        assert!(code.find("struct Light {") < code.find("uniform Light light;"));
+
+        let code = get_glsl(translate_code(ShaderStage::Fragment, STRUCT_UNIFORM, ShaderTarget::Gles30));
+        assert!(code.contains("uniform Light light;"));
+
+        let instructions = get_instructions(translate_code(ShaderStage::Fragment, STRUCT_UNIFORM, ShaderTarget::Gl46Spirv));
+        assert!(instructions.iter().any(|instruction| instruction[0] & 0xFFFF == OP_DECORATE && instruction[2] == 33 && instruction[3] == get_spirv_globals_binding(ShaderStage::Fragment)));
+    }
+
+    #[test]
+    fn spirv_vertex_has_no_textures() {
+        let instructions = get_instructions(translate_code(ShaderStage::Vertex, VERTEX, ShaderTarget::Gl46Spirv));
+
+        assert_eq!(count_opcode(&instructions, OP_TYPE_SAMPLER), 0);
+        assert!(instructions.iter().any(|instruction| instruction[0] & 0xFFFF == OP_DECORATE && instruction[2] == 33 && instruction[3] == get_spirv_globals_binding(ShaderStage::Vertex)));
+    }
+
+    #[test]
+    fn repository_shaders_translate_to_every_target() {
+        let preprocessor = Preprocessor::new();
+        for (path, stage) in [("src/shaders/vert.glsl", ShaderStage::Vertex), ("src/shaders/frag.glsl", ShaderStage::Fragment)] {
+            let processed = preprocessor.process_file(path).unwrap();
+            for target in [ShaderTarget::Gl33, ShaderTarget::Gl46Spirv, ShaderTarget::Gles30] {
+                if let Err(error) = translate(stage, &processed, target) {
+                    panic!("{}", error);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+/*  File: renderer/shaderVariants.rs
+*   Author: Vicix
+*
+*   This file contains the ShaderVariants class.
+*   The class ShaderVariants builds many versions (variants) of the same shader, one for every combination of keywords.
+*   A keyword is a define: a toggle keyword (USE_VERTEX_COLOR) is defined or not, an enum keyword (LIGHTING_NONE, LIGHTING_PHONG) defines exactly one of its values.
+*   The glsl code chooses with #ifdef, so near-identical files are not needed anymore.
+*   A variant is compiled only the first time it is requested and then it is cached.
+*
+*   Example:
+*       let variants = ShaderVariants::new(ShaderBuilder::new().with_file(ShaderStage::Vertex, "vert.glsl").with_file(ShaderStage::Fragment, "frag.glsl"))
+*           .with_keyword("ALPHA_TEST")
+*           .with_enum_keyword(&["LIGHTING_NONE", "LIGHTING_PHONG"]);
+*       let shader = variants.get_variant(&["ALPHA_TEST", "LIGHTING_PHONG"])?;
+*/
+
+use crate::renderer::shader::Shader;
+use crate::renderer::shaderBuilder::ShaderBuilder;
+use crate::renderer::errors::ShaderError;
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/*  This enum is a keyword of the variants.
+*   Toggle is defined only when it is in the keyword set.
+*   Enum contains the possible values. Only one of them is defined: the one in the keyword set, or the first one if none is given.
+*/
+#[derive(Debug, Clone)]
+pub enum ShaderKeyword {
+    Toggle(String),
+    Enum(Vec::<String>),
+}
+
+/*  This is the declaration of the class.
+*   It holds the builder shared by every variant, the keywords and the compiled variants.
+*   A variant is identified by the index of the chosen value of every keyword (0 or 1 for a toggle).
+*   The cache is shared between the clones, like the program of a Shader, so a variant is compiled only once.
+*   watching is true after watch(): the variants compiled after it are watched too.
+*/
+#[derive(Debug, Clone)]
+pub struct ShaderVariants {
+    builder: ShaderBuilder,
+    keywords: Vec::<ShaderKeyword>,
+    variants: Rc<RefCell<HashMap<Vec::<usize>, Shader>>>,
+    watching: Rc<Cell<bool>>,
+}
+
+#[allow(dead_code)]
+impl ShaderVariants {
+    /*  The constructor of the class.
+    *   It takes the builder with the stages (and the defines common to every variant). Nothing is compiled here.
+    */
+    pub fn new(builder: ShaderBuilder) -> ShaderVariants {
+        ShaderVariants {
+            builder,
+            keywords: Vec::<ShaderKeyword>::new(),
+            variants: Rc::new(RefCell::new(HashMap::new())),
+            watching: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /*  This function adds a toggle keyword.
+    */
+    pub fn with_keyword(mut self, name: &str) -> ShaderVariants {
+        self.keywords.push(ShaderKeyword::Toggle(name.to_string()));
+        self
+    }
+
+    /*  This function adds an enum keyword. The first value is the default one.
+    *   An enum without values is ignored.
+    */
+    pub fn with_enum_keyword(mut self, values: &[&str]) -> ShaderVariants {
+        if !values.is_empty() {
+            self.keywords.push(ShaderKeyword::Enum(values.iter().map(|value| value.to_string()).collect()));
+        }
+        self
+    }
+
+    /*  This function returns the keywords.
+    */
+    pub fn get_keywords_ref(&self) -> &Vec::<ShaderKeyword> {
+        &self.keywords
+    }
+
+    /*  This function returns the variant of a keyword set, compiling it if it is requested for the first time.
+    *   The returned Shader is a clone of the cached one, so it shares the program with it.
+    *   If the keyword set is invalid it returns ShaderError::InvalidKeywords. A variant that fails to compile is not cached.
+    */
+    pub fn get_variant(&self, keywords: &[&str]) -> Result<Shader, ShaderError> {
+        let key = self.get_key(keywords)?;
+        if let Some(shader) = self.variants.borrow().get(&key) {
+            return Ok(shader.clone());
+        }
+
+        let mut builder = self.builder.clone();
+        for (keyword, value) in self.keywords.iter().zip(&key) {
+            match keyword {
+                ShaderKeyword::Toggle(name) if *value == 1 => builder = builder.with_define(name, "1"),
+                ShaderKeyword::Toggle(_) => {},
+                ShaderKeyword::Enum(values) => builder = builder.with_define(&values[*value], "1"),
+            }
+        }
+
+        let shader = builder.build()?;
+        if self.watching.get() {
+            shader.watch();
+        }
+        self.variants.borrow_mut().insert(key, shader.clone());
+
+        Ok(shader)
+    }
+
+    /*  This function returns the number of compiled variants.
+    */
+    pub fn get_variant_count(&self) -> usize {
+        self.variants.borrow().len()
+    }
+
+    /*  This function starts watching the files of every variant, the already compiled ones and the next ones.
+    */
+    pub fn watch(&self) {
+        self.watching.set(true);
+        for shader in self.variants.borrow().values() {
+            shader.watch();
+        }
+    }
+
+    /*  This function stops watching the files of every variant.
+    */
+    pub fn unwatch(&self) {
+        self.watching.set(false);
+        for shader in self.variants.borrow().values() {
+            shader.unwatch();
+        }
+    }
+
+    /*  This function reloads the compiled variants whose files changed. It should be called once per frame.
+    *   It returns true if a variant has been reloaded. Every variant is checked even if one fails, then the first error is returned.
+    */
+    pub fn reload_if_changed(&self) -> Result<bool, ShaderError> {
+        let mut reloaded = false;
+        let mut first_error = None;
+        for shader in self.variants.borrow().values() {
+            match shader.reload_if_changed() {
+                Ok(changed) => reloaded |= changed,
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                },
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(reloaded),
+        }
+    }
+
+    /*  This function converts a keyword set in the key of a variant.
+    *   Every name must be a toggle or a value of an enum keyword, and an enum keyword can have only one value in the set.
+    */
+    fn get_key(&self, keywords: &[&str]) -> Result<Vec::<usize>, ShaderError> {
+        let mut key = vec![0; self.keywords.len()];
+        let mut chosen = vec![false; self.keywords.len()];
+
+        for name in keywords {
+            let found = self.keywords.iter().enumerate().find_map(|(i, keyword)| match keyword {
+                ShaderKeyword::Toggle(toggle) if toggle == name => Some((i, 1)),
+                ShaderKeyword::Toggle(_) => None,
+                ShaderKeyword::Enum(values) => values.iter().position(|value| value == name).map(|value| (i, value)),
+            });
+
+            match found {
+                Some((i, value)) if chosen[i] && key[i] != value =>
+                    return Err(ShaderError::InvalidKeywords(format!("{} and {} cannot be used together.", get_value_name(&self.keywords[i], key[i]), name))),
+                Some((i, value)) => {
+                    key[i] = value;
+                    chosen[i] = true;
+                },
+                None => return Err(ShaderError::InvalidKeywords(format!("Unknown keyword {}.", name))),
+            }
+        }
+
+        Ok(key)
+    }
+}
+
+/*  This function returns the name of a value of a keyword.
+*/
+fn get_value_name(keyword: &ShaderKeyword, value: usize) -> &str {
+    match keyword {
+        ShaderKeyword::Toggle(name) => name,
+        ShaderKeyword::Enum(values) => &values[value],
+    }
+}
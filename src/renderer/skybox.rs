@@ -0,0 +1,170 @@
+/*  File: renderer/skybox.rs
+*   Author: Vicix
+*
+*   This file contains the Skybox class.
+*   The class Skybox draws a cubemap texture around the camera, behind everything else.
+*   It has its own cube GlObject and its own shader, so only the cubemap and the camera matrices are needed.
+*
+*   Example:
+*       let cubemap = texture::Texture::cubemap_from_cross("./src/resources/sky.png", 0)?;
+*       cubemap.gen_texture()?;
+*       let skybox = skybox::Skybox::new(cubemap)?;
+*       ...
+*       skybox.draw(view, projection);
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::traits::vaoTrait::VaoLayoutTrait;
+use crate::renderer::traits::shaderTrait::ShaderTrait;
+use crate::renderer::traits::textureTrait::TextureTrait;
+use crate::renderer::traits::glObjectTrait::GlObjectTrait;
+
+use crate::renderer::shader::{Shader, ShaderStage};
+use crate::renderer::shaderBuilder::ShaderBuilder;
+use crate::renderer::texture::Texture;
+use crate::renderer::globject::GlObject;
+use crate::renderer::errors::ShaderError;
+use crate::renderer::uniform::TextureUnit;
+
+use std::ffi::c_void;
+
+/*  This is the vertex shader of the skybox.
+*   The translation of the view is removed, so the sky never gets closer, and the depth is always 1 (z = w), the farthest possible.
+*/
+const SKYBOX_VERTEX_SHADER: &str = "#version 330 core
+layout (location = 0) in vec3 aPos;
+
+out vec3 Direction_;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    Direction_ = aPos;
+    vec4 position = projection * mat4(mat3(view)) * vec4(aPos, 1.0);
+    gl_Position = position.xyww;
+}
+";
+
+/*  This is the fragment shader of the skybox.
+*/
+const SKYBOX_FRAGMENT_SHADER: &str = "#version 330 core
+in vec3 Direction_;
+
+out vec4 FragColor;
+
+uniform samplerCube skybox;
+
+void main() {
+    FragColor = texture(skybox, Direction_);
+}
+";
+
+/*  These are the corners of the cube and the triangles of its faces.
+*/
+const CUBE_VERTICES: [GLfloat; 24] = [
+    -1.0, -1.0, -1.0,
+     1.0, -1.0, -1.0,
+     1.0,  1.0, -1.0,
+    -1.0,  1.0, -1.0,
+    -1.0, -1.0,  1.0,
+     1.0, -1.0,  1.0,
+     1.0,  1.0,  1.0,
+    -1.0,  1.0,  1.0,
+];
+
+const CUBE_INDICES: [GLuint; 36] = [
+    0, 1, 2,  2, 3, 0,
+    4, 6, 5,  6, 4, 7,
+    0, 3, 7,  7, 4, 0,
+    1, 5, 6,  6, 2, 1,
+    3, 2, 6,  6, 7, 3,
+    0, 4, 5,  5, 1, 0,
+];
+
+/*  This is the declaration of the class.
+*   The texture must be a cubemap (see Texture::cubemap_from_faces, cubemap_from_cross and cubemap_from_equirectangular).
+*/
+pub struct Skybox {
+    texture: Texture,
+    cube: GlObject,
+    shader: Shader,
+}
+
+#[allow(dead_code)]
+impl Skybox {
+    /*  This is the constructor of the class.
+    *   It takes the cubemap, creates the cube and compiles the shader.
+    *   Note: the texture is not generated here, gen_texture must be called before or after.
+    */
+    pub fn new(texture: Texture) -> Result<Skybox, ShaderError> {
+        if texture.get_gl_properties_ref().texture_type != gl::TEXTURE_CUBE_MAP {
+            println!("The texture {} of the skybox is not a cubemap.", texture.get_gl_properties_ref().texture_uniform_name);
+        }
+
+        let shader = ShaderBuilder::new()
+            .with_source(ShaderStage::Vertex, SKYBOX_VERTEX_SHADER)
+            .with_source(ShaderStage::Fragment, SKYBOX_FRAGMENT_SHADER)
+            .build()?;
+
+        let mut cube = GlObject::with_ebo();
+        cube.add_vertex_data::<GLfloat>(CUBE_VERTICES.len(), CUBE_VERTICES.as_ptr() as *const c_void, gl::STATIC_DRAW);
+        cube.add_index_data::<GLuint>(CUBE_INDICES.len(), CUBE_INDICES.as_ptr() as *const c_void, gl::STATIC_DRAW);
+        cube.push_layout_element(gl::FLOAT, gl::FALSE, 3);
+        cube.write_layout();
+
+        Ok(Skybox {
+            texture,
+            cube,
+            shader,
+        })
+    }
+
+    /*  This function draws the skybox with the view and the projection of the camera (column-major, like the mat4 uniforms).
+    *   The sky is drawn at the farthest depth with gl::LEQUAL and without writing the depth, so it stays behind the scene.
+    *   It is faster to draw it after the opaque objects, where the depth test skips the covered pixels.
+    *   The depth function, the depth mask and the face culling are restored after the draw.
+    */
+    pub fn draw(&self, view: [[f32; 4]; 4], projection: [[f32; 4]; 4]) {
+        let mut depth_function = gl::LESS as GLint;
+        let mut depth_mask = gl::TRUE;
+        let cull_face;
+        unsafe {
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut depth_function);
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut depth_mask);
+            cull_face = gl::IsEnabled(gl::CULL_FACE);
+
+            gl::DepthFunc(gl::LEQUAL);
+            gl::DepthMask(gl::FALSE);
+            gl::Disable(gl::CULL_FACE);
+        }
+
+        self.texture.bind();
+        self.shader.set_uniform("view", view);
+        self.shader.set_uniform("projection", projection);
+        self.shader.set_uniform("skybox", TextureUnit(self.texture.get_gl_properties_ref().active_texture_number as GLint));
+        self.cube.draw(CUBE_INDICES.len() as GLint);
+
+        unsafe {
+            gl::DepthFunc(depth_function as GLenum);
+            gl::DepthMask(depth_mask);
+            if cull_face == gl::TRUE {
+                gl::Enable(gl::CULL_FACE);
+            }
+        }
+    }
+
+    /*  This function returns the cubemap.
+    */
+    pub fn get_texture_ref(&self) -> &Texture {
+        &self.texture
+    }
+
+    /*  This function returns the cubemap, so its properties can be changed (call gen_texture again after changing the images).
+    */
+    pub fn get_mut_texture_ref(&mut self) -> &mut Texture {
+        &mut self.texture
+    }
+}
@@ -0,0 +1,114 @@
+/*  File: renderer/spirv.rs
+*   Author: Vicix
+*
+*   This file contains the functions used to load spir-v shaders (see ShaderBuilder::with_spirv and with_spirv_file).
+*   A spir-v shader is given to opengl with gl::ShaderBinary and then compiled with glSpecializeShader.
+*   The gl crate is generated for opengl 4.5, so glSpecializeShader is not in it: it is loaded by load_with, that must be called next to gl::load_with.
+*   It needs opengl 4.6 or ARB_gl_spirv (see capabilities::is_spirv_supported).
+*
+*   Example:
+*       gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+*       spirv::load_with(|symbol| window.get_proc_address(symbol) as *const _);
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::errors::ShaderError;
+
+use std::ffi::{c_void, CString};
+use std::fs;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/*  These are the opengl 4.6 constants missing in the gl crate.
+*/
+pub const SHADER_BINARY_FORMAT_SPIR_V: GLenum = 0x9551;
+pub const SPIR_V_BINARY: GLenum = 0x9552;
+
+/*  This is the magic number at the start of every spir-v module.
+*/
+pub const SPIR_V_MAGIC: u32 = 0x0723_0203;
+
+/*  This is the signature of glSpecializeShader.
+*/
+type SpecializeShaderFn = extern "system" fn(GLuint, *const GLchar, GLuint, *const GLuint, *const GLuint);
+
+/*  This is the address of glSpecializeShader (or glSpecializeShaderARB), 0 if it is not loaded.
+*/
+static SPECIALIZE_SHADER: AtomicUsize = AtomicUsize::new(0);
+
+/*  This function loads glSpecializeShader with the same loader given to gl::load_with.
+*   If the core function is missing the ARB one is used.
+*/
+pub fn load_with<F: FnMut(&'static str) -> *const c_void>(mut loader: F) {
+    let mut address = loader("glSpecializeShader");
+    if address.is_null() {
+        address = loader("glSpecializeShaderARB");
+    }
+
+    SPECIALIZE_SHADER.store(address as usize, Ordering::SeqCst);
+}
+
+/*  This function checks if glSpecializeShader has been loaded.
+*/
+pub fn is_loaded() -> bool {
+    SPECIALIZE_SHADER.load(Ordering::SeqCst) != 0
+}
+
+/*  This function gives a spir-v module to a shader and specializes its entry point.
+*   The compile status of the shader tells if it worked, like after gl::CompileShader.
+*   Note: load_with must have been called, otherwise nothing is done and the shader stays not compiled.
+*/
+pub fn specialize_shader(shader: GLuint, words: &[u32], entry_point: &str) {
+    let address = SPECIALIZE_SHADER.load(Ordering::SeqCst);
+    if address == 0 {
+        println!("glSpecializeShader is not loaded, call spirv::load_with after gl::load_with.");
+        return;
+    }
+
+    let c_entry_point = CString::new(entry_point.as_bytes()).unwrap_or_default();
+    unsafe {
+        let specialize: SpecializeShaderFn = mem::transmute(address);
+        gl::ShaderBinary(1, &shader, SHADER_BINARY_FORMAT_SPIR_V, words.as_ptr() as *const c_void, mem::size_of_val(words) as GLsizei);
+        specialize(shader, c_entry_point.as_ptr(), 0, ptr::null(), ptr::null());
+    }
+}
+
+/*  This function converts the bytes of a spir-v file in words.
+*   The byte order is found from the magic number, so files written on big endian machines work too.
+*   It returns None if the bytes are not a spir-v module.
+*/
+pub fn words_from_bytes(bytes: &[u8]) -> Option<Vec::<u32>> {
+    if bytes.len() < 4 || bytes.len() % 4 != 0 {
+        return None;
+    }
+
+    let little_endian = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) == SPIR_V_MAGIC;
+    if !little_endian && u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) != SPIR_V_MAGIC {
+        return None;
+    }
+
+    Some(bytes.chunks_exact(4).map(|word| {
+        let word = [word[0], word[1], word[2], word[3]];
+        if little_endian { u32::from_le_bytes(word) } else { u32::from_be_bytes(word) }
+    }).collect())
+}
+
+/*  This function converts spir-v words in little endian bytes, the order used by the spir-v files.
+*/
+pub fn words_to_bytes(words: &[u32]) -> Vec::<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/*  This function reads a spir-v file (for example one written by the translate_shaders command).
+*/
+pub fn read_file(path: &str) -> Result<Vec::<u32>, ShaderError> {
+    let bytes = fs::read(path).map_err(|error| ShaderError::Io {
+        path: path.to_string(),
+        error,
+    })?;
+
+    words_from_bytes(&bytes).ok_or_else(|| ShaderError::Unsupported(format!("{} is not a spir-v module.", path)))
+}
@@ -0,0 +1,145 @@
+/*  File: renderer/ssbo.rs
+*   Author: Vicix
+*
+*   This file contains the Ssbo class.
+*   The class Ssbo is a simple abstaction of the opengl shader storage buffer.
+*   It holds an array of T that the shaders can read and write (for example the particles of a simulation) and that can be read back by the cpu.
+*   The data is copied as it is, so T should be #[repr(C)] and match the std430 layout of the glsl block (vec3 members must be padded to 16 bytes).
+*   It needs opengl 4.3 or ARB_shader_storage_buffer_object: the constructors return ShaderError::Unsupported on older contexts (see capabilities::is_storage_buffer_supported).
+*/
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::errors::ShaderError;
+use crate::renderer::capabilities;
+
+use std::ffi::c_void;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
+
+/*  The declaration of the class.
+*   It contains the ssbo id, the number of elements and the usage (gl::DYNAMIC_COPY, gl::STATIC_DRAW...) used when the data is replaced.
+*/
+#[derive(Debug)]
+pub struct Ssbo<T: Copy> {
+    ssbo_id: GLuint,
+    len: usize,
+    usage: GLenum,
+    marker: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: Copy> Ssbo<T> {
+    /*  The constructor of the class.
+    *   It generates a storage buffer and writes the data in it.
+    */
+    pub fn new(data: &[T], usage: GLenum) -> Result<Ssbo<T>, ShaderError> {
+        let mut ssbo = Ssbo::<T>::with_len(0, usage)?;
+        ssbo.update(data);
+
+        Ok(ssbo)
+    }
+
+    /*  This constructor creates a storage buffer for len elements, without writing them.
+    *   It is used when the buffer is filled by a shader.
+    */
+    pub fn with_len(len: usize, usage: GLenum) -> Result<Ssbo<T>, ShaderError> {
+        if !capabilities::is_storage_buffer_supported() {
+            let (major, minor) = capabilities::get_gl_version();
+            return Err(ShaderError::Unsupported(format!("Shader storage buffers need opengl 4.3 or ARB_shader_storage_buffer_object, the context is {}.{}.", major, minor)));
+        }
+
+        let mut ssbo = 1;
+        unsafe {
+            gl::GenBuffers(1, &mut ssbo);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, ssbo);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, (len * mem::size_of::<T>()) as GLsizeiptr, ptr::null(), usage);
+        }
+
+        Ok(Ssbo {
+            ssbo_id: ssbo,
+            len,
+            usage,
+            marker: PhantomData,
+        })
+    }
+
+    /*  This function returns the ssbo id.
+    */
+    pub fn get_ssbo_id(&self) -> GLuint {
+        self.ssbo_id
+    }
+
+    /*  This function returns the number of elements.
+    */
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /*  This function checks if the buffer has no elements.
+    */
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /*  A simple function to bind the buffer.
+    */
+    pub fn bind_ssbo(&self) {
+        unsafe {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.ssbo_id);
+        }
+    }
+
+    /*  This function binds the buffer to a binding point.
+    *   Every storage block bound to the same binding point (see ShaderTrait::bind_storage_block or layout(binding = N)) reads and writes this buffer.
+    */
+    pub fn bind_to(&self, binding_point: GLuint) {
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding_point, self.ssbo_id);
+        }
+    }
+
+    /*  This function replaces the data of the buffer. The number of elements can change.
+    */
+    pub fn update(&mut self, data: &[T]) {
+        self.bind_ssbo();
+        unsafe {
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, mem::size_of_val(data) as GLsizeiptr, data.as_ptr() as *const c_void, self.usage);
+        }
+        self.len = data.len();
+    }
+
+    /*  This function writes some elements starting from the element at offset.
+    *   The elements that do not fit in the buffer are ignored, with a warning.
+    */
+    pub fn update_range(&self, offset: usize, data: &[T]) {
+        let count = data.len().min(self.len.saturating_sub(offset));
+        if count < data.len() {
+            println!("Writing {} elements at {} in ssbo {} with {} elements. The last {} are ignored.", data.len(), offset, self.ssbo_id, self.len, data.len() - count);
+        }
+        if count == 0 {
+            return;
+        }
+
+        self.bind_ssbo();
+        unsafe {
+            gl::BufferSubData(gl::SHADER_STORAGE_BUFFER, (offset * mem::size_of::<T>()) as GLintptr, (count * mem::size_of::<T>()) as GLsizeiptr, data.as_ptr() as *const c_void);
+        }
+    }
+
+    /*  This function reads the whole buffer back to the cpu.
+    *   Note: if a shader wrote the buffer, call compute::memory_barrier(gl::BUFFER_UPDATE_BARRIER_BIT) before reading it.
+    */
+    pub fn read(&self) -> Vec::<T> {
+        let mut data = Vec::<T>::with_capacity(self.len);
+
+        self.bind_ssbo();
+        unsafe {
+            gl::GetBufferSubData(gl::SHADER_STORAGE_BUFFER, 0, (self.len * mem::size_of::<T>()) as GLsizeiptr, data.as_mut_ptr() as *mut c_void);
+            data.set_len(self.len);
+        }
+
+        data
+    }
+}
@@ -0,0 +1,341 @@
+/*  File: renderer/std140.rs
+*   Author: Vicix
+*
+*   This file contains the Std140 trait and the Std140Writer class.
+*   They are used to write a rust struct in the std140 layout used by the uniform blocks.
+*   A struct implements Std140 writing its fields in the same order as the glsl block, the writer takes care of the alignment and the padding.
+*   It does not use opengl.
+*
+*   Example:
+*       impl Std140 for Camera {
+*           fn write_std140(&self, writer: &mut Std140Writer) {
+*               writer.write(&self.view);       // mat4
+*               writer.write(&self.position);   // vec3
+*               writer.write(&self.time);       // float, packed after the vec3
+*           }
+*       }
+*/
+
+/*  This trait is implemented by every type that can be written in a std140 block.
+*   ALIGNMENT is the base alignment of the type. Structs are always aligned to 16 bytes.
+*   PADDED is true if the size of the type is rounded up to 16 bytes (structs and matrices), so the next member starts on a new vec4.
+*   The default values are the ones of a struct, so a user struct only needs write_std140.
+*/
+pub trait Std140 {
+    const ALIGNMENT: usize = 16;
+    const PADDED: bool = true;
+
+    fn write_std140(&self, writer: &mut Std140Writer);
+}
+
+/*  This is the declaration of the class.
+*   It only contains the written bytes.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Std140Writer {
+    data: Vec::<u8>,
+}
+
+#[allow(dead_code)]
+impl Std140Writer {
+    /*  The constructor of the class.
+    */
+    pub fn new() -> Std140Writer {
+        Std140Writer {
+            data: Vec::<u8>::new(),
+        }
+    }
+
+    /*  This function writes a whole value and returns the bytes.
+    *   The size is rounded up to 16 bytes, like the size of a block.
+    */
+    pub fn to_bytes<T: Std140>(value: &T) -> Vec::<u8> {
+        let mut writer = Std140Writer::new();
+        writer.write(value);
+        writer.align(16);
+
+        writer.data
+    }
+
+    /*  This function writes a member: it aligns the offset to the alignment of the type, writes the value and pads it if needed.
+    */
+    pub fn write<T: Std140>(&mut self, value: &T) {
+        self.align(T::ALIGNMENT);
+        value.write_std140(self);
+        if T::PADDED {
+            self.align(16);
+        }
+    }
+
+    /*  This function writes an array member.
+    *   In std140 every element of an array is aligned to 16 bytes and the stride is rounded up to 16 bytes, so a float[4] takes 64 bytes.
+    */
+    pub fn write_array<T: Std140>(&mut self, values: &[T]) {
+        for value in values {
+            self.align(T::ALIGNMENT.max(16));
+            value.write_std140(self);
+            self.align(16);
+        }
+    }
+
+    /*  This function writes raw bytes at the current offset. It is used by the scalar types.
+    */
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /*  This function adds zeros until the offset is a multiple of the alignment.
+    */
+    pub fn align(&mut self, alignment: usize) {
+        let padding = (alignment - self.data.len() % alignment) % alignment;
+        self.data.resize(self.data.len() + padding, 0);
+    }
+
+    /*  This function returns the current offset, which is the size of the written data.
+    */
+    pub fn get_offset(&self) -> usize {
+        self.data.len()
+    }
+
+    /*  This function returns the written bytes.
+    */
+    pub fn get_bytes_ref(&self) -> &Vec::<u8> {
+        &self.data
+    }
+}
+
+/*  This macro implements Std140 for the scalars. Every scalar takes 4 bytes.
+*/
+macro_rules! impl_scalar_std140 {
+    ($type:ty) => {
+        impl Std140 for $type {
+            const ALIGNMENT: usize = 4;
+            const PADDED: bool = false;
+
+            fn write_std140(&self, writer: &mut Std140Writer) {
+                writer.write_bytes(&self.to_ne_bytes());
+            }
+        }
+    };
+}
+
+/*  This macro implements Std140 for the vectors. A vec2 is aligned to 8 bytes, a vec3 and a vec4 to 16 bytes.
+*   Note: a vec3 takes only 12 bytes, so a scalar can be packed after it.
+*/
+macro_rules! impl_vector_std140 {
+    ($type:ty, $alignment:expr) => {
+        impl Std140 for $type {
+            const ALIGNMENT: usize = $alignment;
+            const PADDED: bool = false;
+
+            fn write_std140(&self, writer: &mut Std140Writer) {
+                for component in self {
+                    component.write_std140(writer);
+                }
+            }
+        }
+    };
+}
+
+/*  This macro implements Std140 for the matrices. A matrix is written as an array of columns, so every column takes 16 bytes.
+*/
+macro_rules! impl_matrix_std140 {
+    ($type:ty) => {
+        impl Std140 for $type {
+            fn write_std140(&self, writer: &mut Std140Writer) {
+                writer.write_array(self);
+            }
+        }
+    };
+}
+
+impl_scalar_std140!(f32);
+impl_scalar_std140!(i32);
+impl_scalar_std140!(u32);
+
+impl_vector_std140!([f32; 2], 8);
+impl_vector_std140!([f32; 3], 16);
+impl_vector_std140!([f32; 4], 16);
+impl_vector_std140!([i32; 2], 8);
+impl_vector_std140!([i32; 3], 16);
+impl_vector_std140!([i32; 4], 16);
+impl_vector_std140!([u32; 2], 8);
+impl_vector_std140!([u32; 3], 16);
+impl_vector_std140!([u32; 4], 16);
+
+impl_matrix_std140!([[f32; 2]; 2]);
+impl_matrix_std140!([[f32; 3]; 3]);
+impl_matrix_std140!([[f32; 4]; 4]);
+
+/*  A glsl bool takes 4 bytes in a block.
+*/
+impl Std140 for bool {
+    const ALIGNMENT: usize = 4;
+    const PADDED: bool = false;
+
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        (*self as u32).write_std140(writer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*  These structs are written like the glsl blocks in the comments.
+    */
+    struct Light {
+        intensity: f32,
+        color: [f32; 3],
+    }
+
+    impl Std140 for Light {
+        fn write_std140(&self, writer: &mut Std140Writer) {
+            writer.write(&self.intensity);      // float   offset 0
+            writer.write(&self.color);          // vec3    offset 16
+        }
+    }
+
+    struct Scene {
+        time: f32,
+        light: Light,
+        exposure: f32,
+    }
+
+    impl Std140 for Scene {
+        fn write_std140(&self, writer: &mut Std140Writer) {
+            writer.write(&self.time);           // float   offset 0
+            writer.write(&self.light);          // Light   offset 16
+            writer.write(&self.exposure);       // float   offset 48
+        }
+    }
+
+    /*  This function reads the float at an offset of the written bytes.
+    */
+    fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+        let mut float = [0; 4];
+        float.copy_from_slice(&bytes[offset..offset + 4]);
+        f32::from_ne_bytes(float)
+    }
+
+    /*  This function returns the offset of the second member written after the first one.
+    */
+    fn get_second_offset<A: Std140, B: Std140>(first: &A, second: &B) -> usize {
+        let mut writer = Std140Writer::new();
+        writer.write(first);
+        writer.align(B::ALIGNMENT);
+        let offset = writer.get_offset();
+        writer.write(second);
+
+        offset
+    }
+
+    #[test]
+    fn scalars_are_packed() {
+        let mut writer = Std140Writer::new();
+        writer.write(&1.0f32);
+        writer.write(&2i32);
+        writer.write(&3u32);
+        writer.write(&true);
+
+        assert_eq!(writer.get_offset(), 16);
+        assert_eq!(read_f32(writer.get_bytes_ref(), 0), 1.0);
+        assert_eq!(&writer.get_bytes_ref()[4..8], &2i32.to_ne_bytes());
+        assert_eq!(&writer.get_bytes_ref()[8..12], &3u32.to_ne_bytes());
+        assert_eq!(&writer.get_bytes_ref()[12..16], &1u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn vectors_are_aligned() {
+        assert_eq!(get_second_offset(&1.0f32, &[2.0f32; 2]), 8);
+        assert_eq!(get_second_offset(&1.0f32, &[2.0f32; 3]), 16);
+        assert_eq!(get_second_offset(&1.0f32, &[2.0f32; 4]), 16);
+        assert_eq!(get_second_offset(&[1.0f32; 2], &[2i32; 2]), 8);
+    }
+
+    #[test]
+    fn scalar_is_packed_after_vec3() {
+        let mut writer = Std140Writer::new();
+        writer.write(&[1.0f32, 2.0, 3.0]);
+        writer.write(&4.0f32);
+
+        assert_eq!(writer.get_offset(), 16);
+        assert_eq!(read_f32(writer.get_bytes_ref(), 12), 4.0);
+        assert_eq!(get_second_offset(&[1.0f32; 3], &[2.0f32; 3]), 16);
+    }
+
+    #[test]
+    fn matrix_columns_are_padded() {
+        let mut identity = [[0.0f32; 4]; 4];
+        for (index, column) in identity.iter_mut().enumerate() {
+            column[index] = 1.0;
+        }
+
+        let mut writer = Std140Writer::new();
+        writer.write(&1.0f32);
+        writer.write(&identity);
+        writer.write(&2.0f32);
+
+        let bytes = writer.get_bytes_ref();
+        assert_eq!(read_f32(bytes, 16), 1.0);
+        assert_eq!(read_f32(bytes, 16 + 16 + 4), 1.0);
+        assert_eq!(read_f32(bytes, 16 + 48 + 12), 1.0);
+        assert_eq!(read_f32(bytes, 80), 2.0);
+        assert_eq!(writer.get_offset(), 84);
+
+        //every column of a mat3 takes a vec4.
+        assert_eq!(Std140Writer::to_bytes(&[[1.0f32; 3]; 3]).len(), 48);
+        assert_eq!(Std140Writer::to_bytes(&[[1.0f32; 2]; 2]).len(), 32);
+    }
+
+    #[test]
+    fn array_stride_is_16() {
+        let mut writer = Std140Writer::new();
+        writer.write_array(&[1.0f32, 2.0, 3.0, 4.0]);
+        assert_eq!(writer.get_offset(), 64);
+        for index in 0..4 {
+            assert_eq!(read_f32(writer.get_bytes_ref(), index * 16), index as f32 + 1.0);
+        }
+
+        let mut writer = Std140Writer::new();
+        writer.write(&1.0f32);
+        writer.write_array(&[[2.0f32; 3], [3.0f32; 3]]);
+        writer.write(&4.0f32);
+        assert_eq!(read_f32(writer.get_bytes_ref(), 16), 2.0);
+        assert_eq!(read_f32(writer.get_bytes_ref(), 32), 3.0);
+        assert_eq!(read_f32(writer.get_bytes_ref(), 48), 4.0);
+    }
+
+    #[test]
+    fn nested_struct_is_aligned_and_padded() {
+        let scene = Scene {
+            time: 1.0,
+            light: Light {
+                intensity: 2.0,
+                color: [3.0, 4.0, 5.0],
+            },
+            exposure: 6.0,
+        };
+
+        let bytes = Std140Writer::to_bytes(&scene);
+        assert_eq!(bytes.len(), 64);
+        assert_eq!(read_f32(&bytes, 0), 1.0);
+        assert_eq!(read_f32(&bytes, 16), 2.0);
+        assert_eq!(read_f32(&bytes, 32), 3.0);
+        assert_eq!(read_f32(&bytes, 40), 5.0);
+        assert_eq!(read_f32(&bytes, 48), 6.0);
+    }
+
+    #[test]
+    fn struct_array_stride_is_rounded() {
+        let lights = [
+            Light { intensity: 1.0, color: [0.0; 3] },
+            Light { intensity: 2.0, color: [0.0; 3] },
+        ];
+
+        let mut writer = Std140Writer::new();
+        writer.write_array(&lights);
+        assert_eq!(writer.get_offset(), 64);
+        assert_eq!(read_f32(writer.get_bytes_ref(), 32), 2.0);
+    }
+}
@@ -4,13 +4,33 @@
 *   This file contains the Texture class.
 *   The class Shader is a simple abstaction of the opengl texture. 
 *   It helps creating textures from files and to create uniforms.
+*   A texture can also be a cubemap, made from six images, a cross or a panorama (see renderer::cubemap).
+*   Array textures (gl::TEXTURE_2D_ARRAY) and 3d textures (gl::TEXTURE_3D) are made from a list of images, a sprite sheet or raw pixels.
+*   A 2d texture can also be made from raw pixels or as empty storage, and its pixels can be replaced later (see TextureTrait::update_region).
+*   A 2d texture can also be loaded from a KTX2 or a DDS file, already compressed for the gpu (see renderer::compressedImage).
+*   A 2d texture can also be a texture atlas, many small images packed together (see renderer::textureAtlas).
 */
 
 extern crate gl;
 use self::gl::types::*;
 
-use crate::renderer::properties::{TextureGlProperties, TextureImageProperties};
+use crate::renderer::properties::{TextureGlProperties, TextureImageProperties, ImageLayout};
+use crate::renderer::errors::TextureError;
 use crate::renderer::traits::textureTrait::TextureTrait;
+use crate::renderer::imageData::{self, ImageData};
+use crate::renderer::cubemap;
+use crate::renderer::textureAtlas::TextureAtlas;
+use crate::renderer::compressedImage;
+
+extern crate image;
+use image::ColorType;
+
+use std::fs;
+
+/*  These are the size of the "missing texture" checkerboard and of its squares, in pixels.
+*/
+pub const PLACEHOLDER_SIZE: usize = 64;
+pub const PLACEHOLDER_CELL_SIZE: usize = 8;
 
 /*  This is the declaration on the class.
 *   It contains the texture_id (the only this that is strictly necessary).
@@ -28,13 +48,178 @@ impl Texture {
     /*  This is the constructor of the class.
     *   It takes the texture type, the image path the internal format, the normal format and the active texture number.
     *   It also create an opengl texture.
+    *   The formats override the ones found from the image (see from_file).
+    *   It returns TextureError::Io if the image file cannot be found, so a missing asset can be replaced with Texture::missing.
     *   Note: The default uniform name is set to the image path.
     */
-    pub fn new(texture_type: GLenum, image_path: &str, internal_format: GLenum, format: GLenum, active_texture_number: GLuint) -> Texture {
+    pub fn new(texture_type: GLenum, image_path: &str, internal_format: GLenum, format: GLenum, active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let mut texture = Texture::from_file(texture_type, image_path, active_texture_number)?;
+        texture.image_properties.internal_format = Some(internal_format);
+        texture.image_properties.format = Some(format);
+
+        Ok(texture)
+    }
+
+    /*  This constructor is like new, but the formats are found from the color type of the image when it is loaded (see textureFormat::get_texture_format).
+    */
+    pub fn from_file(texture_type: GLenum, image_path: &str, active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        check_files(&[image_path])?;
+
+        Ok(Texture::with_path(texture_type, image_path, active_texture_number))
+    }
+
+    /*  This constructor creates an array texture (gl::TEXTURE_2D_ARRAY) or a 3d texture (gl::TEXTURE_3D) from a list of images.
+    *   Every image is a layer of the array or a slice of the 3d texture, so they must have the same size and color type.
+    *   It returns TextureError::Io if an image file cannot be found.
+    */
+    pub fn from_layer_files(texture_type: GLenum, image_paths: &[&str], active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        check_files(image_paths)?;
+
+        let mut texture = Texture::with_path(texture_type, image_paths.first().copied().unwrap_or_default(), active_texture_number);
+        texture.image_properties.layout = ImageLayout::Layers(image_paths.iter().map(|path| path.to_string()).collect());
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates an array texture or a 3d texture from a sprite sheet cut in columns * rows images of the same size.
+    *   The layers (or slices) are taken from left to right and from top to bottom, for example a 256x16 color grading lut is 16 columns and 1 row.
+    */
+    pub fn from_sheet(texture_type: GLenum, image_path: &str, columns: u32, rows: u32, active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let mut texture = Texture::from_file(texture_type, image_path, active_texture_number)?;
+        texture.image_properties.layout = ImageLayout::Sheet { columns, rows };
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a 3d texture (or an array texture) from raw pixels: depth slices of width * height pixels of a color type, one after the other.
+    *   The pixels are given to opengl immediately, gen_texture then only applies the gl properties.
+    *   It returns an error if the size of the pixels is wrong or opengl rejects them.
+    */
+    pub fn from_voxels(texture_type: GLenum, width: u32, height: u32, depth: u32, color: ColorType, pixels: &[u8], active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let mut texture = Texture::with_path(texture_type, "", active_texture_number);
+        texture.image_properties.layout = ImageLayout::Memory;
+        texture.upload_voxels(width, height, depth, color, pixels)?;
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a 2d texture from raw pixels of a color type (16 bit channels in the native byte order), with the first row at the top.
+    *   The pixels are given to opengl immediately, gen_texture then only applies the gl properties.
+    *   It returns an error if the size of the pixels is wrong or opengl rejects them.
+    */
+    pub fn from_pixels(width: u32, height: u32, color: ColorType, pixels: &[u8], active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let size = width as usize * height as usize * imageData::get_bytes_per_pixel(color);
+        if pixels.len() != size || size == 0 {
+            return Err(TextureError::Decode {
+                path: String::new(),
+                message: format!("{}x{} pixels of {:?} need {} bytes, but {} were given.", width, height, color, size, pixels.len()),
+            });
+        }
+
+        let mut texture = Texture::with_path(gl::TEXTURE_2D, "", active_texture_number);
+        texture.image_properties.layout = ImageLayout::Memory;
+        texture.upload_image(&ImageData {
+            width,
+            height,
+            color,
+            pixels: pixels.to_vec(),
+        })?;
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a texture with empty storage of a size and an internal format (for example gl::RGBA8, gl::RGBA16F or gl::DEPTH_COMPONENT24).
+    *   The texture type can be gl::TEXTURE_2D, gl::TEXTURE_RECTANGLE or gl::TEXTURE_CUBE_MAP (every face gets the size).
+    *   It is meant for the render targets and for the textures filled later with update_region or by a shader.
+    */
+    pub fn empty(texture_type: GLenum, width: u32, height: u32, internal_format: GLenum, active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let mut texture = Texture::with_path(texture_type, "", active_texture_number);
+        texture.image_properties.layout = ImageLayout::Memory;
+        texture.image_properties.internal_format = Some(internal_format);
+        texture.allocate(width, height, internal_format)?;
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a 2d texture from a texture atlas (see renderer::textureAtlas).
+    *   The packed image is given to opengl immediately, gen_texture then only applies the gl properties.
+    *   Note: call generate_mipmap after this to use the mipmaps, the extrusion of the atlas keeps the images apart in the smaller levels.
+    */
+    pub fn from_atlas(atlas: &TextureAtlas, active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let mut texture = Texture::with_path(gl::TEXTURE_2D, "", active_texture_number);
+        texture.image_properties.layout = ImageLayout::Memory;
+        texture.upload_image(atlas.get_image_ref())?;
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a cubemap from six images, in the opengl order: +x, -x, +y, -y, +z, -z (right, left, top, bottom, front, back).
+    *   The faces must be squares of the same size. It returns TextureError::Io if an image file cannot be found.
+    *   Note: the wrap is set to gl::CLAMP_TO_EDGE and the filter to gl::LINEAR, so the edges of the faces don't show.
+    */
+    pub fn cubemap_from_faces(face_paths: &[&str; 6], active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        check_files(face_paths)?;
+
+        let mut texture = Texture::cubemap_with_path(face_paths[0], active_texture_number);
+        texture.image_properties.layout = ImageLayout::CubemapFaces(face_paths.iter().map(|path| path.to_string()).collect());
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a cubemap from a single image with the cube unfolded in a horizontal or vertical cross (see cubemap::split_cross).
+    */
+    pub fn cubemap_from_cross(image_path: &str, active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let mut texture = Texture::from_file(gl::TEXTURE_CUBE_MAP, image_path, active_texture_number)?;
+        texture.set_cubemap_properties();
+        texture.image_properties.layout = ImageLayout::CubemapCross;
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a cubemap from an equirectangular panorama (see cubemap::project_equirectangular).
+    *   face_size is the size of a face in pixels, 0 uses a quarter of the width of the panorama.
+    */
+    pub fn cubemap_from_equirectangular(image_path: &str, face_size: u32, active_texture_number: GLuint) -> Result<Texture, TextureError> {
+        let mut texture = Texture::from_file(gl::TEXTURE_CUBE_MAP, image_path, active_texture_number)?;
+        texture.set_cubemap_properties();
+        texture.image_properties.layout = ImageLayout::CubemapEquirectangular { face_size };
+
+        Ok(texture)
+    }
+
+    /*  This constructor creates a texture without an image: gen_texture gives it the "missing texture" checkerboard.
+    *   It is used in place of a texture that cannot be loaded.
+    */
+    pub fn missing(texture_type: GLenum, active_texture_number: GLuint) -> Texture {
+        Texture::with_path(texture_type, "", active_texture_number)
+    }
+
+    /*  This function creates a cubemap with the cubemap properties.
+    */
+    fn cubemap_with_path(image_path: &str, active_texture_number: GLuint) -> Texture {
+        let mut texture = Texture::with_path(gl::TEXTURE_CUBE_MAP, image_path, active_texture_number);
+        texture.set_cubemap_properties();
+
+        texture
+    }
+
+    /*  This function sets the wrap and the filter that a cubemap needs to hide the edges of the faces.
+    */
+    fn set_cubemap_properties(&mut self) {
+        self.gl_properties.texture_warp_s = gl::CLAMP_TO_EDGE;
+        self.gl_properties.texture_warp_t = gl::CLAMP_TO_EDGE;
+        self.gl_properties.texture_warp_r = gl::CLAMP_TO_EDGE;
+        self.gl_properties.texture_min_filter = gl::LINEAR;
+        self.gl_properties.texture_mag_filter = gl::LINEAR;
+    }
+
+    /*  This function creates the opengl texture and the properties.
+    */
+    fn with_path(texture_type: GLenum, image_path: &str, active_texture_number: GLuint) -> Texture {
         let texture_id = unsafe {
             let mut texture_id = 1;
             gl::GenTextures(1, &mut texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+            gl::BindTexture(texture_type, texture_id);
 
             texture_id
         };
@@ -46,6 +231,7 @@ impl Texture {
                 texture_type: texture_type,
                 texture_warp_s: gl::MIRRORED_REPEAT,
                 texture_warp_t: gl::MIRRORED_REPEAT,
+                texture_warp_r: gl::MIRRORED_REPEAT,
                 texture_min_filter: gl::NEAREST,
                 texture_mag_filter: gl::NEAREST,
                 texture_uniform_name: image_path.to_string(),
@@ -54,13 +240,89 @@ impl Texture {
                 path: image_path.to_string(),
                 fliph: false,
                 flipv: false,
-                internal_format: internal_format,
-                format: format,
+                internal_format: None,
+                format: None,
+                srgb: false,
+                layout: ImageLayout::Single,
             },
         }
     }
 }
 
+/*  This function reads the images of a texture, following its layout, and applies the flips to every image file.
+*   It returns a single image for ImageLayout::Single, the six faces in the opengl order for the cubemaps and the layers (or slices) for Layers and Sheet.
+*   ImageLayout::Memory has nothing to load, so no image is returned.
+*/
+pub fn load_images(image_properties: &TextureImageProperties) -> Result<Vec::<ImageData>, TextureError> {
+    let load = |path: &str| -> Result<ImageData, TextureError> {
+        let mut image = ImageData::load(path)?;
+        if image_properties.fliph {
+            image.flip_horizontal();
+        }
+        if image_properties.flipv {
+            image.flip_vertical();
+        }
+
+        Ok(image)
+    };
+
+    match &image_properties.layout {
+        ImageLayout::Single => Ok(vec![load(&image_properties.path)?]),
+        ImageLayout::CubemapFaces(face_paths) => {
+            let faces = face_paths.iter().map(|path| load(path)).collect::<Result<Vec::<ImageData>, TextureError>>()?;
+            cubemap::check_faces(&faces, &image_properties.path)?;
+
+            Ok(faces)
+        },
+        ImageLayout::CubemapCross => cubemap::split_cross(&load(&image_properties.path)?, &image_properties.path),
+        ImageLayout::CubemapEquirectangular { face_size } => Ok(cubemap::project_equirectangular(&load(&image_properties.path)?, *face_size)),
+        ImageLayout::Layers(image_paths) => image_paths.iter().map(|path| load(path)).collect(),
+        ImageLayout::Sheet { columns, rows } => {
+            let sheet = load(&image_properties.path)?;
+            sheet.split_sheet(*columns, *rows).ok_or_else(|| TextureError::Decode {
+                path: image_properties.path.clone(),
+                message: format!("A {}x{} image cannot be cut in {} columns and {} rows.", sheet.width, sheet.height, columns, rows),
+            })
+        },
+        ImageLayout::Memory => Ok(Vec::<ImageData>::new()),
+    }
+}
+
+/*  This function checks that the image files exist, so a missing asset is found when the texture is created.
+*/
+fn check_files(paths: &[&str]) -> Result<(), TextureError> {
+    match paths.iter().find_map(|path| fs::metadata(path).err().map(|error| (path, error))) {
+        Some((path, error)) => Err(TextureError::Io {
+            path: path.to_string(),
+            error,
+        }),
+        None => Ok(()),
+    }
+}
+
+/*  This function checks if a texture is loaded from a compressed file (see renderer::compressedImage): a single KTX2 or DDS image in a 2d texture.
+*/
+pub fn is_compressed_texture(texture_type: GLenum, image_properties: &TextureImageProperties) -> bool {
+    texture_type == gl::TEXTURE_2D && image_properties.layout == ImageLayout::Single && compressedImage::is_compressed_file(&image_properties.path)
+}
+
+/*  This function returns the rgba pixels of the "missing texture" checkerboard: magenta and black squares.
+*/
+pub fn get_placeholder_pixels() -> Vec::<u8> {
+    let mut pixels = Vec::<u8>::with_capacity(PLACEHOLDER_SIZE * PLACEHOLDER_SIZE * 4);
+    for y in 0..PLACEHOLDER_SIZE {
+        for x in 0..PLACEHOLDER_SIZE {
+            if (x / PLACEHOLDER_CELL_SIZE + y / PLACEHOLDER_CELL_SIZE) % 2 == 0 {
+                pixels.extend_from_slice(&[255, 0, 255, 255]);
+            } else {
+                pixels.extend_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+
+    pixels
+}
+
 impl TextureTrait for Texture {
     fn get_texture_id(&self) -> u32 {
         self.texture_id
@@ -0,0 +1,433 @@
+/*  File: renderer/textureAtlas.rs
+*   Author: Vicix
+*
+*   This file contains the AtlasBuilder, TextureAtlas and AtlasTable classes.
+*   The class AtlasBuilder collects many small images (sprites, icons, glyphs...) by name and packs them in a single image (see renderer::atlasPacker).
+*   Every image is surrounded by its edge pixels repeated (the extrusion) and by empty pixels (the padding), so the linear filter and the mipmaps don't mix it with its neighbours.
+*   The class TextureAtlas is the packed rgba image with its AtlasTable, the position of every image by name.
+*   The table can be saved in a text file next to the atlas image and loaded back without packing again.
+*   The atlas is given to opengl with Texture::from_atlas.
+*
+*   Example:
+*       let atlas = textureAtlas::AtlasBuilder::new()
+*           .with_file("player", "./src/resources/player.png")
+*           .with_file("coin", "./src/resources/coin.png")
+*           .with_padding(2)
+*           .with_extrusion(2)
+*           .build()?;
+*       let texture = texture::Texture::from_atlas(&atlas, 0)?;
+*       let uv = atlas.get_table_ref().get_uv("coin").unwrap();
+*/
+
+extern crate image;
+use image::ColorType;
+
+use crate::renderer::errors::TextureError;
+use crate::renderer::imageData::ImageData;
+use crate::renderer::atlasPacker::{self, PackedRect};
+
+use std::collections::HashMap;
+use std::fs;
+
+/*  These are the default padding, extrusion and maximum size of the atlases, in pixels.
+*/
+pub const DEFAULT_ATLAS_PADDING: u32 = 2;
+pub const DEFAULT_ATLAS_EXTRUSION: u32 = 1;
+pub const DEFAULT_ATLAS_MAX_SIZE: u32 = 4096;
+
+/*  This is the first word of the saved tables.
+*/
+const ATLAS_TABLE_HEADER: &str = "atlas";
+
+/*  This struct is the texture coordinates of an image in the atlas: the corner with the smallest coordinates and the one with the biggest.
+*   The v coordinate is 0 on the first row of the atlas image, like in the images given to opengl without flipping them.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub u_min: f32,
+    pub v_min: f32,
+    pub u_max: f32,
+    pub v_max: f32,
+}
+
+/*  This enum tells where an image of the atlas comes from: a file, read when the atlas is built, or an image already decoded.
+*/
+#[derive(Debug, Clone)]
+enum AtlasSource {
+    File(String),
+    Image(ImageData),
+}
+
+/*  This is the declaration of the AtlasBuilder class.
+*   It holds the images by name, the padding between them, the extrusion of their edges and the maximum size of the atlas.
+*   Nothing is loaded until build() is called.
+*/
+#[derive(Debug, Clone)]
+pub struct AtlasBuilder {
+    images: Vec::<(String, AtlasSource)>,
+    padding: u32,
+    extrusion: u32,
+    max_size: u32,
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> AtlasBuilder {
+        AtlasBuilder::new()
+    }
+}
+
+#[allow(dead_code)]
+impl AtlasBuilder {
+    /*  The constructor of the class.
+    *   It creates a builder without images and with the default padding, extrusion and maximum size.
+    */
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder {
+            images: Vec::<(String, AtlasSource)>::new(),
+            padding: DEFAULT_ATLAS_PADDING,
+            extrusion: DEFAULT_ATLAS_EXTRUSION,
+            max_size: DEFAULT_ATLAS_MAX_SIZE,
+        }
+    }
+
+    /*  This function adds an image read from a file when the atlas is built.
+    */
+    pub fn with_file(mut self, name: &str, path: &str) -> AtlasBuilder {
+        self.images.push((name.to_string(), AtlasSource::File(path.to_string())));
+        self
+    }
+
+    /*  This function adds an image already decoded.
+    */
+    pub fn with_image(mut self, name: &str, image: ImageData) -> AtlasBuilder {
+        self.images.push((name.to_string(), AtlasSource::Image(image)));
+        self
+    }
+
+    /*  This function sets the empty pixels between the images.
+    */
+    pub fn with_padding(mut self, padding: u32) -> AtlasBuilder {
+        self.padding = padding;
+        self
+    }
+
+    /*  This function sets how many times the edge pixels of every image are repeated around it.
+    *   The extrusion is not part of the uv rectangles, it is only sampled by the filters at the edges.
+    */
+    pub fn with_extrusion(mut self, extrusion: u32) -> AtlasBuilder {
+        self.extrusion = extrusion;
+        self
+    }
+
+    /*  This function sets the maximum width and height of the atlas.
+    */
+    pub fn with_max_size(mut self, max_size: u32) -> AtlasBuilder {
+        self.max_size = max_size;
+        self
+    }
+
+    /*  This function loads the images, packs them and copies them in the atlas.
+    *   Every image is converted to 8 bit rgba. It returns the errors of ImageData::load, TextureError::Decode if two images have the same name
+    *   or the images don't fit in the maximum size and TextureError::UnsupportedFormat if an image cannot be converted to rgba.
+    */
+    pub fn build(&self) -> Result<TextureAtlas, TextureError> {
+        let mut images = Vec::<(&str, ImageData)>::with_capacity(self.images.len());
+        for (name, source) in &self.images {
+            if images.iter().any(|(other, _)| *other == name.as_str()) {
+                return Err(TextureError::Decode {
+                    path: name.clone(),
+                    message: "Two images of the atlas have the same name.".to_string(),
+                });
+            }
+
+            let (path, image) = match source {
+                AtlasSource::File(path) => (path.as_str(), ImageData::load(path)?),
+                AtlasSource::Image(image) => (name.as_str(), image.clone()),
+            };
+            let image = image.to_rgba8().ok_or_else(|| TextureError::UnsupportedFormat {
+                path: path.to_string(),
+                message: format!("The color type {:?} cannot be converted to rgba.", image.color),
+            })?;
+            if image.is_empty() {
+                return Err(TextureError::Decode {
+                    path: path.to_string(),
+                    message: "The image is empty.".to_string(),
+                });
+            }
+
+            images.push((name, image));
+        }
+
+        //every cell is the image with its extrusion and the padding on its right and bottom, so the size of the atlas stays a power of two.
+        let border = self.extrusion * 2 + self.padding;
+        let sizes = images.iter().map(|(_, image)| (image.width + border, image.height + border)).collect::<Vec::<(u32, u32)>>();
+        let packing = atlasPacker::pack(&sizes, self.max_size).ok_or_else(|| TextureError::Decode {
+            path: String::new(),
+            message: format!("The {} images don't fit in a {}x{} atlas.", images.len(), self.max_size, self.max_size),
+        })?;
+
+        let (width, height) = (packing.width, packing.height);
+        let mut atlas_image = ImageData {
+            width,
+            height,
+            color: ColorType::RGBA(8),
+            pixels: vec![0; width as usize * height as usize * 4],
+        };
+        let mut rects = HashMap::<String, PackedRect>::with_capacity(images.len());
+        for ((name, image), cell) in images.iter().zip(&packing.rects) {
+            let rect = PackedRect {
+                x: cell.x + self.extrusion,
+                y: cell.y + self.extrusion,
+                width: image.width,
+                height: image.height,
+            };
+            copy_extruded(&mut atlas_image, image, rect.x, rect.y, self.extrusion);
+            rects.insert(name.to_string(), rect);
+        }
+
+        Ok(TextureAtlas {
+            image: atlas_image,
+            table: AtlasTable {
+                width,
+                height,
+                rects,
+            },
+        })
+    }
+}
+
+/*  This is the declaration of the TextureAtlas class: the packed image and its table.
+*/
+#[derive(Debug, Clone)]
+pub struct TextureAtlas {
+    image: ImageData,
+    table: AtlasTable,
+}
+
+#[allow(dead_code)]
+impl TextureAtlas {
+    /*  This function returns the packed rgba image.
+    */
+    pub fn get_image_ref(&self) -> &ImageData {
+        &self.image
+    }
+
+    /*  This function returns the positions of the images.
+    */
+    pub fn get_table_ref(&self) -> &AtlasTable {
+        &self.table
+    }
+}
+
+/*  This is the declaration of the AtlasTable class.
+*   It contains the size of the atlas and the rectangle of every image in pixels, by name. The extrusion and the padding are not part of the rectangles.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtlasTable {
+    width: u32,
+    height: u32,
+    rects: HashMap<String, PackedRect>,
+}
+
+#[allow(dead_code)]
+impl AtlasTable {
+    /*  This constructor reads a table saved with save.
+    *   It returns TextureError::Io if the file cannot be read and TextureError::Decode if it is malformed.
+    */
+    pub fn load(path: &str) -> Result<AtlasTable, TextureError> {
+        let text = fs::read_to_string(path).map_err(|error| TextureError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+
+        AtlasTable::parse(&text, path)
+    }
+
+    /*  This constructor parses the text of a saved table. path is only used in the errors.
+    *   The first line is "atlas width height", every other line is "x y width height name" (the name is the rest of the line, so it can contain spaces).
+    */
+    pub fn parse(text: &str, path: &str) -> Result<AtlasTable, TextureError> {
+        let malformed = |line: usize| TextureError::Decode {
+            path: path.to_string(),
+            message: format!("The line {} of the atlas table is malformed.", line + 1),
+        };
+
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let (header_line, header) = lines.next().ok_or_else(|| malformed(0))?;
+        let (width, height) = match header.split_whitespace().collect::<Vec::<&str>>().as_slice() {
+            [ATLAS_TABLE_HEADER, width, height] => (width.parse().map_err(|_| malformed(header_line))?, height.parse().map_err(|_| malformed(header_line))?),
+            _ => return Err(malformed(header_line)),
+        };
+
+        let mut rects = HashMap::<String, PackedRect>::new();
+        for (line_number, line) in lines {
+            let mut fields = line.trim().splitn(5, ' ');
+            let mut next_number = || fields.next().and_then(|field| field.parse::<u32>().ok()).ok_or_else(|| malformed(line_number));
+            let rect = PackedRect {
+                x: next_number()?,
+                y: next_number()?,
+                width: next_number()?,
+                height: next_number()?,
+            };
+            let name = fields.next().filter(|name| !name.is_empty()).ok_or_else(|| malformed(line_number))?;
+            rects.insert(name.to_string(), rect);
+        }
+
+        Ok(AtlasTable {
+            width,
+            height,
+            rects,
+        })
+    }
+
+    /*  This function writes the table as text (see parse). The images are sorted by name, so the same atlas always gives the same file.
+    */
+    pub fn to_text(&self) -> String {
+        let mut names = self.rects.keys().collect::<Vec::<&String>>();
+        names.sort();
+
+        let mut text = format!("{} {} {}\n", ATLAS_TABLE_HEADER, self.width, self.height);
+        for name in names {
+            let rect = &self.rects[name];
+            text.push_str(&format!("{} {} {} {} {}\n", rect.x, rect.y, rect.width, rect.height, name));
+        }
+
+        text
+    }
+
+    /*  This function saves the table in a text file (see parse).
+    */
+    pub fn save(&self, path: &str) -> Result<(), TextureError> {
+        fs::write(path, self.to_text()).map_err(|error| TextureError::Io {
+            path: path.to_string(),
+            error,
+        })
+    }
+
+    /*  This function returns the rectangle of an image in pixels, from the top left corner of the atlas.
+    */
+    pub fn get_rect(&self, name: &str) -> Option<PackedRect> {
+        self.rects.get(name).copied()
+    }
+
+    /*  This function returns the texture coordinates of an image (see UvRect).
+    */
+    pub fn get_uv(&self, name: &str) -> Option<UvRect> {
+        let rect = self.rects.get(name)?;
+        let (width, height) = (self.width as f32, self.height as f32);
+
+        Some(UvRect {
+            u_min: rect.x as f32 / width,
+            v_min: rect.y as f32 / height,
+            u_max: (rect.x + rect.width) as f32 / width,
+            v_max: (rect.y + rect.height) as f32 / height,
+        })
+    }
+
+    /*  This function returns the size of the atlas in pixels.
+    */
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /*  This function returns the names of the images.
+    */
+    pub fn get_names(&self) -> impl Iterator<Item = &String> {
+        self.rects.keys()
+    }
+}
+
+/*  This function copies an rgba image in the atlas with its top left corner in x, y, then repeats its edge pixels extrusion times around it.
+*   The pixels around the image must be inside the atlas.
+*/
+fn copy_extruded(atlas: &mut ImageData, image: &ImageData, x: u32, y: u32, extrusion: u32) {
+    let atlas_width = atlas.width as usize;
+    let (x, y, extrusion) = (x as usize, y as usize, extrusion as usize);
+    let (width, height) = (image.width as usize, image.height as usize);
+
+    for atlas_y in y - extrusion..y + height + extrusion {
+        let image_y = atlas_y.clamp(y, y + height - 1) - y;
+        for atlas_x in x - extrusion..x + width + extrusion {
+            let image_x = atlas_x.clamp(x, x + width - 1) - x;
+            let source = (image_y * width + image_x) * 4;
+            let destination = (atlas_y * atlas_width + atlas_x) * 4;
+            atlas.pixels[destination..destination + 4].copy_from_slice(&image.pixels[source..source + 4]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*  This function creates an rgba image where every pixel is (x, y, index, 255).
+    */
+    fn get_image(width: u32, height: u32, index: u8) -> ImageData {
+        let pixels = (0..height).flat_map(|y| (0..width).flat_map(move |x| [x as u8, y as u8, index, 255])).collect();
+
+        ImageData {
+            width,
+            height,
+            color: ColorType::RGBA(8),
+            pixels,
+        }
+    }
+
+    fn get_pixel(image: &ImageData, x: u32, y: u32) -> [u8; 4] {
+        let start = (y * image.width + x) as usize * 4;
+        [image.pixels[start], image.pixels[start + 1], image.pixels[start + 2], image.pixels[start + 3]]
+    }
+
+    #[test]
+    fn table_text_round_trip() {
+        let atlas = AtlasBuilder::new()
+            .with_image("player", get_image(5, 3, 1))
+            .with_image("coin icon", get_image(2, 2, 2))
+            .with_image("tile", get_image(4, 4, 3))
+            .build()
+            .unwrap();
+        let table = atlas.get_table_ref();
+
+        let text = table.to_text();
+        assert!(text.starts_with(&format!("atlas {} {}\n", table.get_size().0, table.get_size().1)));
+        assert_eq!(&AtlasTable::parse(&text, "test.atlas").unwrap(), table);
+        assert_eq!(table.get_rect("coin icon").map(|rect| (rect.width, rect.height)), Some((2, 2)));
+    }
+
+    #[test]
+    fn malformed_tables_are_rejected() {
+        assert!(AtlasTable::parse("", "test.atlas").is_err());
+        assert!(AtlasTable::parse("atlas 16\n", "test.atlas").is_err());
+        assert!(AtlasTable::parse("atlas 16 16\n0 0 4 4\n", "test.atlas").is_err());
+        assert!(AtlasTable::parse("atlas 16 16\n0 0 4 x coin\n", "test.atlas").is_err());
+        assert!(AtlasTable::parse("atlas 16 16\n\n0 0 4 4 coin\n", "test.atlas").is_ok());
+    }
+
+    #[test]
+    fn edges_are_extruded() {
+        let (extrusion, padding) = (2, 1);
+        let image = get_image(3, 2, 7);
+        let atlas = AtlasBuilder::new().with_image("sprite", image.clone()).with_extrusion(extrusion).with_padding(padding).build().unwrap();
+        let atlas_image = atlas.get_image_ref();
+        let rect = atlas.get_table_ref().get_rect("sprite").unwrap();
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (extrusion, extrusion, 3, 2));
+
+        //every pixel of the cell is the nearest pixel of the image, the padding is empty.
+        for y in 0..rect.height + extrusion * 2 {
+            for x in 0..rect.width + extrusion * 2 {
+                let image_x = x.clamp(extrusion, extrusion + rect.width - 1) - extrusion;
+                let image_y = y.clamp(extrusion, extrusion + rect.height - 1) - extrusion;
+                assert_eq!(get_pixel(atlas_image, x, y), get_pixel(&image, image_x, image_y), "pixel {}, {}", x, y);
+            }
+        }
+        assert_eq!(get_pixel(atlas_image, rect.width + extrusion * 2, 0), [0, 0, 0, 0]);
+        assert_eq!(get_pixel(atlas_image, 0, rect.height + extrusion * 2), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn same_names_are_rejected() {
+        let result = AtlasBuilder::new().with_image("coin", get_image(2, 2, 1)).with_image("coin", get_image(2, 2, 2)).build();
+
+        assert!(matches!(result, Err(TextureError::Decode { .. })));
+    }
+}
@@ -0,0 +1,139 @@
+/*  File: renderer/textureFormat.rs
+*   Author: Vicix
+*
+*   This file contains the functions that choose how an image is given to opengl.
+*   The format, the type and the internal format are found from the color type of the image, so the caller doesn't need to know what the file contains.
+*   Grey images are stored in the red channel (gl::RED and gl::RG), and the swizzle makes the shaders read them as grey.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+extern crate image;
+use image::ColorType;
+
+/*  This struct contains the opengl description of the pixels of an image.
+*   format and data_type describe the pixels given to opengl, internal_format describes how opengl stores them.
+*   swizzle, if any, is the TEXTURE_SWIZZLE_RGBA used to read the channels (for the grey images).
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureFormat {
+    pub internal_format: GLenum,
+    pub format: GLenum,
+    pub data_type: GLenum,
+    pub swizzle: Option<[GLenum; 4]>,
+}
+
+/*  This function returns the format of a color type, or None if opengl cannot read it (palettes and less than 8 bits).
+*   If srgb is true the color images use an srgb internal format, so the shaders read linear colors.
+*   Note: there are no srgb formats for the grey and the 16 bit images, so they are always linear.
+*/
+pub fn get_texture_format(color: ColorType, srgb: bool) -> Option<TextureFormat> {
+    let grey = Some([gl::RED, gl::RED, gl::RED, gl::ONE]);
+    let grey_alpha = Some([gl::RED, gl::RED, gl::RED, gl::GREEN]);
+
+    let (internal_format, format, data_type, swizzle) = match color {
+        ColorType::Gray(8) => (gl::R8, gl::RED, gl::UNSIGNED_BYTE, grey),
+        ColorType::GrayA(8) => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE, grey_alpha),
+        ColorType::RGB(8) if srgb => (gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE, None),
+        ColorType::RGB(8) => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE, None),
+        ColorType::RGBA(8) if srgb => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE, None),
+        ColorType::RGBA(8) => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, None),
+        ColorType::Gray(16) => (gl::R16, gl::RED, gl::UNSIGNED_SHORT, grey),
+        ColorType::GrayA(16) => (gl::RG16, gl::RG, gl::UNSIGNED_SHORT, grey_alpha),
+        ColorType::RGB(16) => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT, None),
+        ColorType::RGBA(16) => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT, None),
+        _ => return None,
+    };
+
+    Some(TextureFormat {
+        internal_format,
+        format,
+        data_type,
+        swizzle,
+    })
+}
+
+/*  This function returns the GL_UNPACK_ALIGNMENT of the rows of an image: the biggest of 8, 4, 2 and 1 that divides the size of a row.
+*   opengl expects every row to start at a multiple of 4 bytes by default, so for example an rgb image 3 pixels wide (9 bytes per row) needs 1.
+*/
+pub fn get_unpack_alignment(width: u32, bytes_per_pixel: usize) -> GLint {
+    let row_size = width as usize * bytes_per_pixel;
+
+    [8, 4, 2].iter().copied().find(|alignment| row_size % *alignment == 0).unwrap_or(1) as GLint
+}
+
+/*  This function returns the format and the type of the pixels used to write a texture of an internal format (see Texture::empty and TextureTrait::update_region).
+*   The integer formats use the *_INTEGER formats and the depth formats use gl::DEPTH_COMPONENT or gl::DEPTH_STENCIL.
+*   It returns None for the internal formats that are not listed here (for example the compressed ones).
+*/
+pub fn get_pixel_format(internal_format: GLenum) -> Option<TextureFormat> {
+    let (format, data_type) = match internal_format {
+        gl::RED | gl::R8 => (gl::RED, gl::UNSIGNED_BYTE),
+        gl::RG | gl::RG8 => (gl::RG, gl::UNSIGNED_BYTE),
+        gl::RGB | gl::RGB8 | gl::SRGB8 => (gl::RGB, gl::UNSIGNED_BYTE),
+        gl::RGBA | gl::RGBA8 | gl::SRGB8_ALPHA8 => (gl::RGBA, gl::UNSIGNED_BYTE),
+        gl::R16 => (gl::RED, gl::UNSIGNED_SHORT),
+        gl::RG16 => (gl::RG, gl::UNSIGNED_SHORT),
+        gl::RGB16 => (gl::RGB, gl::UNSIGNED_SHORT),
+        gl::RGBA16 => (gl::RGBA, gl::UNSIGNED_SHORT),
+        gl::R16F => (gl::RED, gl::HALF_FLOAT),
+        gl::RG16F => (gl::RG, gl::HALF_FLOAT),
+        gl::RGB16F => (gl::RGB, gl::HALF_FLOAT),
+        gl::RGBA16F => (gl::RGBA, gl::HALF_FLOAT),
+        gl::R32F => (gl::RED, gl::FLOAT),
+        gl::RG32F => (gl::RG, gl::FLOAT),
+        gl::RGB32F => (gl::RGB, gl::FLOAT),
+        gl::RGBA32F => (gl::RGBA, gl::FLOAT),
+        gl::R8UI => (gl::RED_INTEGER, gl::UNSIGNED_BYTE),
+        gl::RG8UI => (gl::RG_INTEGER, gl::UNSIGNED_BYTE),
+        gl::RGBA8UI => (gl::RGBA_INTEGER, gl::UNSIGNED_BYTE),
+        gl::R8I => (gl::RED_INTEGER, gl::BYTE),
+        gl::RG8I => (gl::RG_INTEGER, gl::BYTE),
+        gl::RGBA8I => (gl::RGBA_INTEGER, gl::BYTE),
+        gl::R16UI => (gl::RED_INTEGER, gl::UNSIGNED_SHORT),
+        gl::RG16UI => (gl::RG_INTEGER, gl::UNSIGNED_SHORT),
+        gl::RGBA16UI => (gl::RGBA_INTEGER, gl::UNSIGNED_SHORT),
+        gl::R16I => (gl::RED_INTEGER, gl::SHORT),
+        gl::RG16I => (gl::RG_INTEGER, gl::SHORT),
+        gl::RGBA16I => (gl::RGBA_INTEGER, gl::SHORT),
+        gl::R32UI => (gl::RED_INTEGER, gl::UNSIGNED_INT),
+        gl::RG32UI => (gl::RG_INTEGER, gl::UNSIGNED_INT),
+        gl::RGBA32UI => (gl::RGBA_INTEGER, gl::UNSIGNED_INT),
+        gl::R32I => (gl::RED_INTEGER, gl::INT),
+        gl::RG32I => (gl::RG_INTEGER, gl::INT),
+        gl::RGBA32I => (gl::RGBA_INTEGER, gl::INT),
+        gl::DEPTH_COMPONENT16 => (gl::DEPTH_COMPONENT, gl::UNSIGNED_SHORT),
+        gl::DEPTH_COMPONENT | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32 => (gl::DEPTH_COMPONENT, gl::UNSIGNED_INT),
+        gl::DEPTH_COMPONENT32F => (gl::DEPTH_COMPONENT, gl::FLOAT),
+        gl::DEPTH_STENCIL | gl::DEPTH24_STENCIL8 => (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+        gl::DEPTH32F_STENCIL8 => (gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV),
+        _ => return None,
+    };
+
+    Some(TextureFormat {
+        internal_format,
+        format,
+        data_type,
+        swizzle: None,
+    })
+}
+
+/*  This function returns the size in bytes of a pixel of a format and a type (the ones returned by get_pixel_format).
+*/
+pub fn get_pixel_size(format: GLenum, data_type: GLenum) -> usize {
+    let channel_count = match format {
+        gl::RED | gl::RED_INTEGER | gl::DEPTH_COMPONENT => 1,
+        gl::RG | gl::RG_INTEGER => 2,
+        gl::RGB | gl::RGB_INTEGER => 3,
+        _ => 4,
+    };
+
+    match data_type {
+        gl::UNSIGNED_INT_24_8 => 4,
+        gl::FLOAT_32_UNSIGNED_INT_24_8_REV => 8,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => channel_count * 2,
+        gl::UNSIGNED_INT | gl::INT | gl::FLOAT => channel_count * 4,
+        _ => channel_count,
+    }
+}
@@ -0,0 +1,315 @@
+/*  File: renderer/textureLoader.rs
+*   Author: Vicix
+*
+*   This file contains the TextureLoader and PendingTexture classes.
+*   The class TextureLoader decodes the images of the textures on a pool of worker threads, so the render thread doesn't stop while they are read.
+*   A texture given to the loader gets the "missing texture" placeholder immediately and is returned as a PendingTexture.
+*   The decoded images are given to opengl by poll, on the render thread (the only one with the opengl context), up to a number of bytes per call.
+*   The workers only read and decode the files (see texture::load_images and CompressedImage::load), they never use opengl.
+*
+*   Example:
+*       let mut loader = textureLoader::TextureLoader::new(0);
+*       let wall = loader.load_file(gl::TEXTURE_2D, "./src/resources/wall.jpg", 0)?;
+*       loop {
+*           loader.poll(textureLoader::DEFAULT_UPLOAD_BUDGET);
+*           wall.get_texture_ref().bind();
+*           ...
+*       }
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::traits::textureTrait::TextureTrait;
+use crate::renderer::properties::{TextureImageProperties, ImageLayout};
+use crate::renderer::errors::TextureError;
+use crate::renderer::texture::{self, Texture};
+use crate::renderer::imageData::ImageData;
+use crate::renderer::compressedImage::CompressedImage;
+
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/*  This is the default number of bytes given to opengl by a poll (16 MiB, a few large images per frame).
+*/
+pub const DEFAULT_UPLOAD_BUDGET: usize = 16 * 1024 * 1024;
+
+/*  This enum is the state of a PendingTexture.
+*   Pending: the image is being decoded or is waiting to be uploaded, the texture shows the placeholder.
+*   Ready: the image has been given to opengl.
+*   Failed: the image could not be loaded or uploaded, the texture keeps the placeholder (the error is returned by poll).
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadState {
+    Pending,
+    Ready,
+    Failed,
+}
+
+/*  This is the declaration of the PendingTexture class.
+*   The texture and the state are shared with the loader, so the clones of the handle see the uploaded image.
+*/
+#[derive(Debug, Clone)]
+pub struct PendingTexture {
+    texture: Rc<RefCell<Texture>>,
+    state: Rc<Cell<LoadState>>,
+}
+
+#[allow(dead_code)]
+impl PendingTexture {
+    /*  This function returns the texture. It can be bound while it is pending: it shows the placeholder.
+    */
+    pub fn get_texture_ref(&self) -> Ref<'_, Texture> {
+        self.texture.borrow()
+    }
+
+    /*  This function returns the texture, so its properties can be changed.
+    *   Note: the gl properties are applied again when the image is uploaded.
+    */
+    pub fn get_mut_texture_ref(&self) -> RefMut<'_, Texture> {
+        self.texture.borrow_mut()
+    }
+
+    /*  This function returns the state of the texture.
+    */
+    pub fn get_state(&self) -> LoadState {
+        self.state.get()
+    }
+
+    /*  This function checks if the image has been given to opengl.
+    */
+    pub fn is_ready(&self) -> bool {
+        self.state.get() == LoadState::Ready
+    }
+}
+
+/*  This enum is an image decoded by a worker: the images of texture::load_images or a compressed image.
+*/
+enum DecodedImage {
+    Images(Vec::<ImageData>),
+    Compressed(CompressedImage),
+}
+
+impl DecodedImage {
+    /*  This function returns the size of the pixels, counted in the upload budget.
+    */
+    fn get_size(&self) -> usize {
+        match self {
+            DecodedImage::Images(images) => images.iter().map(|image| image.pixels.len()).sum(),
+            DecodedImage::Compressed(image) => image.levels.iter().map(|level| level.len()).sum(),
+        }
+    }
+}
+
+/*  This struct is a texture to decode: its id in the loader, its type and its image properties.
+*/
+struct LoadJob {
+    id: u64,
+    texture_type: GLenum,
+    image_properties: TextureImageProperties,
+}
+
+/*  This is the declaration of the TextureLoader class.
+*   The jobs are sent to the workers through job_sender and the decoded images come back through result_receiver.
+*   pending contains the textures that have not been uploaded yet, decoded the images received but not uploaded because of the budget.
+*/
+pub struct TextureLoader {
+    job_sender: Option<Sender<LoadJob>>,
+    result_receiver: Receiver<(u64, Result<DecodedImage, TextureError>)>,
+    workers: Vec::<JoinHandle<()>>,
+    pending: HashMap<u64, PendingTexture>,
+    decoded: VecDeque<(u64, Result<DecodedImage, TextureError>)>,
+    next_id: u64,
+}
+
+#[allow(dead_code)]
+impl TextureLoader {
+    /*  The constructor of the class.
+    *   It starts thread_count worker threads. 0 uses one thread less than the cores of the cpu (at least one).
+    */
+    pub fn new(thread_count: usize) -> TextureLoader {
+        let thread_count = if thread_count == 0 {
+            thread::available_parallelism().map(|cores| cores.get().saturating_sub(1)).unwrap_or(1).max(1)
+        } else {
+            thread_count
+        };
+
+        let (job_sender, job_receiver) = mpsc::channel::<LoadJob>();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..thread_count).map(|_| {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+
+            thread::spawn(move || loop {
+                //the lock is released before decoding, so the other workers can take the next jobs.
+                let job = match job_receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return,
+                };
+                let job = match job {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+
+                if result_sender.send((job.id, decode(job.texture_type, &job.image_properties))).is_err() {
+                    return;
+                }
+            })
+        }).collect();
+
+        TextureLoader {
+            job_sender: Some(job_sender),
+            result_receiver,
+            workers,
+            pending: HashMap::new(),
+            decoded: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /*  This function gives a texture to the loader and returns its handle.
+    *   The texture gets the placeholder now and its image when it is decoded and a poll uploads it.
+    *   A texture without a path or made from memory has nothing to decode, so gen_texture is called immediately.
+    */
+    pub fn load(&mut self, texture: Texture) -> PendingTexture {
+        let image_properties = texture.get_image_properties_ref().clone();
+        let texture_type = texture.get_gl_properties_ref().texture_type;
+        let pending = PendingTexture {
+            texture: Rc::new(RefCell::new(texture)),
+            state: Rc::new(Cell::new(LoadState::Pending)),
+        };
+
+        if image_properties.path.is_empty() || image_properties.layout == ImageLayout::Memory {
+            let state = match pending.get_texture_ref().gen_texture() {
+                Ok(_) => LoadState::Ready,
+                Err(error) => {
+                    println!("{}", error);
+                    LoadState::Failed
+                },
+            };
+            pending.state.set(state);
+
+            return pending;
+        }
+
+        pending.get_texture_ref().gen_placeholder();
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let job = LoadJob {
+            id,
+            texture_type,
+            image_properties,
+        };
+        match &self.job_sender {
+            Some(job_sender) if job_sender.send(job).is_ok() => {
+                self.pending.insert(id, pending.clone());
+            },
+            _ => {
+                println!("The texture loader has no threads left, {} cannot be loaded.", pending.get_texture_ref().get_image_properties_ref().path);
+                pending.state.set(LoadState::Failed);
+            },
+        }
+
+        pending
+    }
+
+    /*  This function creates a texture from a file (see Texture::from_file) and gives it to the loader.
+    */
+    pub fn load_file(&mut self, texture_type: GLenum, image_path: &str, active_texture_number: GLuint) -> Result<PendingTexture, TextureError> {
+        let texture = Texture::from_file(texture_type, image_path, active_texture_number)?;
+
+        Ok(self.load(texture))
+    }
+
+    /*  This function gives the decoded images to opengl. It must be called on the render thread, for example once per frame.
+    *   It stops when byte_budget bytes have been uploaded (at least one image is uploaded, even if it is bigger), the others wait for the next poll.
+    *   It returns the errors of the textures that could not be loaded in this poll. Those textures keep the placeholder.
+    */
+    pub fn poll(&mut self, byte_budget: usize) -> Vec::<TextureError> {
+        self.decoded.extend(self.result_receiver.try_iter());
+
+        let mut errors = Vec::<TextureError>::new();
+        let mut uploaded_bytes = 0;
+        let mut uploaded_any = false;
+        //the first image is always uploaded, so even a budget of 0 (or smaller than the image) makes progress.
+        while !uploaded_any || uploaded_bytes < byte_budget {
+            let (id, result) = match self.decoded.pop_front() {
+                Some(decoded) => decoded,
+                None => break,
+            };
+            let pending = match self.pending.remove(&id) {
+                Some(pending) => pending,
+                None => continue,
+            };
+
+            //the handles have all been dropped, so nobody can see the texture.
+            if Rc::strong_count(&pending.texture) == 1 {
+                continue;
+            }
+
+            let result = result.and_then(|image| {
+                uploaded_bytes += image.get_size();
+                uploaded_any = true;
+
+                let texture = pending.get_texture_ref();
+                match &image {
+                    DecodedImage::Images(images) => texture.upload_images(images),
+                    DecodedImage::Compressed(image) => texture.upload_compressed(image),
+                }
+            });
+
+            match result {
+                Ok(_) => pending.state.set(LoadState::Ready),
+                Err(error) => {
+                    pending.state.set(LoadState::Failed);
+                    pending.get_texture_ref().gen_placeholder();
+                    errors.push(error);
+                },
+            }
+        }
+
+        errors
+    }
+
+    /*  This function returns the number of textures that have not been uploaded yet.
+    */
+    pub fn get_pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /*  This function checks if every texture has been uploaded (or has failed).
+    */
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl Drop for TextureLoader {
+    /*  The workers stop when the job channel is closed. They finish the image they are decoding first.
+    */
+    fn drop(&mut self) {
+        self.job_sender = None;
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                println!("A texture loader thread panicked.");
+            }
+        }
+    }
+}
+
+/*  This function reads and decodes the images of a texture. It runs on the worker threads.
+*/
+fn decode(texture_type: GLenum, image_properties: &TextureImageProperties) -> Result<DecodedImage, TextureError> {
+    if texture::is_compressed_texture(texture_type, image_properties) {
+        Ok(DecodedImage::Compressed(CompressedImage::load(&image_properties.path)?))
+    } else {
+        Ok(DecodedImage::Images(texture::load_images(image_properties)?))
+    }
+}
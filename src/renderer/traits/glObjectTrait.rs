@@ -6,6 +6,8 @@ use crate::renderer::traits::vboTrait::VboTrait;
 use crate::renderer::traits::eboTrait::OptionalEboTrait;
 use crate::renderer::constants;
 use crate::renderer::properties::GlObjectProperties;
+use crate::renderer::reflection::ProgramReflection;
+use crate::renderer::vertexInput;
 
 use std::ffi::c_void;
 use std::ptr;
@@ -53,6 +55,7 @@ pub trait GlObjectTrait: VaoLayoutTrait + VboTrait + OptionalEboTrait {
         match property_id {
             constants::DRAW_MODE_PROPERTY => properties.draw_mode = property,
             constants::EBO_TYPE_PROPERTY  => properties.ebo_type  = property,
+            constants::VALIDATE_LAYOUT_PROPERTY => properties.validate_layout = property != gl::FALSE as GLenum,
             _ => println!("Wrong property set on GlObject."),
         }
     }
@@ -62,8 +65,13 @@ pub trait GlObjectTrait: VaoLayoutTrait + VboTrait + OptionalEboTrait {
     *   Or it uses the function raw_draw_arrays if the ebo is not set.
     *   It takes the number of vertices to draw.
     *   Note: it does not draw things by itself, but it calls raw_draw_elements() and raw_draw_arrays().
+    *   In debug builds the first draw also checks the layout against the bound shader (see validate_layout_once()).
     */
     fn draw(&self, count: GLint) { //smart way to draw.
+        if cfg!(debug_assertions) {
+            self.validate_layout_once();
+        }
+
         match self.get_ebo_id() {
             Some(_) => { //We have a Element Buffer Object. We can use gl::DrawElements().
                 self.raw_draw_elements(self.get_properties_ref().draw_mode, count, self.get_properties_ref().ebo_type);
@@ -74,6 +82,31 @@ pub trait GlObjectTrait: VaoLayoutTrait + VboTrait + OptionalEboTrait {
         }
     }
 
+    /*  This function compares the layout with the inputs of the bound shader and prints the mismatches.
+    *   It does it only the first time it is called and only if the VALIDATE_LAYOUT_PROPERTY is set (it is by default).
+    *   Use Shader::check_layout() to get the mismatches instead of printing them.
+    */
+    fn validate_layout_once(&self) {
+        let properties = self.get_properties_ref();
+        if !properties.validate_layout || properties.layout_validated.replace(true) {
+            return;
+        }
+
+        let mut program = 0;
+        unsafe {
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program);
+        }
+        if program <= 0 {
+            return;
+        }
+
+        let inputs = vertexInput::get_vertex_inputs(&ProgramReflection::from_program(program as GLuint));
+        for mismatch in vertexInput::check_vertex_layout(&inputs, self.get_layout_ref()) {
+            let kind = if mismatch.is_error() { "Error" } else { "Warning" };
+            println!("{} in the layout of vao {} with shader {}: {}", kind, self.get_vao_id(), program, mismatch);
+        }
+    }
+
     /*  This function is used to draw elements on the screen.
     *   It binds the vao and the vbo and then calls gl::DrawElements().
     */
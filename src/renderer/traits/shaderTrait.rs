@@ -1,3 +1,8 @@
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::uniform::UniformValue;
+
 use std::ffi::CString;
 
 pub trait ShaderTrait {
@@ -11,43 +16,93 @@ pub trait ShaderTrait {
         }
     }
 
-    /*  This is a simple function that takes an uniform name and vaue and makes a boolean uniform.
+    /*  This function returns the programs used by the shader: one for a Shader, one for every stage for a ProgramPipeline.
+    *   If a program is reloaded its id changes, so it is used to know when the uniforms must be set again.
     */
-    fn set_bool_uniform(&self, uniform_name: &str, uniform_value: bool) {
+    fn get_program_ids(&self) -> Vec::<GLuint> {
+        vec![self.get_program_id()]
+    }
+
+    /*  This function returns the location of a uniform. The value_type is the opengl type of the value that will be written.
+    *   By default it asks opengl every time. The Shader overrides it using the cached locations and checks the type.
+    */
+    fn get_uniform_location(&self, uniform_name: &str, _value_type: GLenum) -> GLint {
         unsafe {
-            self.bind();
-            let uniform_location = gl::GetUniformLocation(self.get_program_id(), CString::new(uniform_name.as_bytes()).unwrap().as_ptr());
-            gl::Uniform1i(uniform_location, uniform_value as i32);
+            gl::GetUniformLocation(self.get_program_id(), CString::new(uniform_name.as_bytes()).unwrap().as_ptr())
         }
     }
 
-    /*  This is a simple function that takes an uniform name and vaue and makes an integer uniform.
+    /*  This function binds a uniform block of the shader to a binding point.
+    *   The block reads the data of the Ubo bound to the same binding point.
+    *   It prints a warning if the block does not exist.
     */
-    fn set_int_uniform(&self, uniform_name: &str, uniform_value: i32) {
+    #[allow(dead_code)]
+    fn bind_uniform_block(&self, block_name: &str, binding_point: GLuint) {
         unsafe {
-            self.bind();
-            let uniform_location = gl::GetUniformLocation(self.get_program_id(), CString::new(uniform_name.as_bytes()).unwrap().as_ptr());
-            gl::Uniform1i(uniform_location, uniform_value);
+            let block_index = gl::GetUniformBlockIndex(self.get_program_id(), CString::new(block_name.as_bytes()).unwrap().as_ptr());
+            if block_index == gl::INVALID_INDEX {
+                println!("Uniform block {} not found in shader {}.", block_name, self.get_program_id());
+            } else {
+                gl::UniformBlockBinding(self.get_program_id(), block_index, binding_point);
+            }
         }
     }
 
-    /*  This is a simple function that takes an uniform name and vaue and makes a float uniform.
+    /*  This function binds a shader storage block of the shader to a binding point.
+    *   The block reads and writes the data of the Ssbo bound to the same binding point.
+    *   It prints a warning if the block does not exist.
     */
-    fn set_float_uniform(&self, uniform_name: &str, uniform_value: f32) {
+    #[allow(dead_code)]
+    fn bind_storage_block(&self, block_name: &str, binding_point: GLuint) {
         unsafe {
-            self.bind();
-            let uniform_location = gl::GetUniformLocation(self.get_program_id(), CString::new(uniform_name.as_bytes()).unwrap().as_ptr());
-            gl::Uniform1f(uniform_location, uniform_value);
+            let block_index = gl::GetProgramResourceIndex(self.get_program_id(), gl::SHADER_STORAGE_BLOCK, CString::new(block_name.as_bytes()).unwrap().as_ptr());
+            if block_index == gl::INVALID_INDEX {
+                println!("Storage block {} not found in shader {}.", block_name, self.get_program_id());
+            } else {
+                gl::ShaderStorageBlockBinding(self.get_program_id(), block_index, binding_point);
+            }
         }
     }
 
+    /*  This function writes any UniformValue in a uniform.
+    *   The type of the value is checked by get_uniform_location. If the uniform is not found nothing is written.
+    *   Note: this function takes a trait object so it can be used on a &dyn ShaderTrait. Use set_uniform when possible.
+    */
+    fn set_uniform_value(&self, uniform_name: &str, uniform_value: &dyn UniformValue) {
+        self.bind();
+        let uniform_location = self.get_uniform_location(uniform_name, uniform_value.get_uniform_type());
+        if uniform_location >= 0 {
+            uniform_value.write_uniform(uniform_location);
+        }
+    }
+
+    /*  This function writes a uniform of any type: scalars, vectors ([f32; 3] is a vec3), matrices ([[f32; 4]; 4] is a mat4, Transposed for row-major), texture units and slices of them for arrays.
+    */
+    fn set_uniform<T: UniformValue>(&self, uniform_name: &str, uniform_value: T) where Self: Sized {
+        self.set_uniform_value(uniform_name, &uniform_value);
+    }
+
+    /*  This is a simple function that takes an uniform name and vaue and makes a boolean uniform.
+    */
+    fn set_bool_uniform(&self, uniform_name: &str, uniform_value: bool) {
+        self.set_uniform_value(uniform_name, &uniform_value);
+    }
+
+    /*  This is a simple function that takes an uniform name and vaue and makes an integer uniform.
+    */
+    fn set_int_uniform(&self, uniform_name: &str, uniform_value: i32) {
+        self.set_uniform_value(uniform_name, &uniform_value);
+    }
+
+    /*  This is a simple function that takes an uniform name and vaue and makes a float uniform.
+    */
+    fn set_float_uniform(&self, uniform_name: &str, uniform_value: f32) {
+        self.set_uniform_value(uniform_name, &uniform_value);
+    }
+
     /*  This is a simple function that takes an uniform name and vaue and makes a vec3 uniform.
     */
     fn set_3float_uniform(&self, uniform_name: &str, uniform_value1: f32, uniform_value2: f32, uniform_value3: f32) {
-        unsafe {
-            self.bind();
-            let uniform_location = gl::GetUniformLocation(self.get_program_id(), CString::new(uniform_name.as_bytes()).unwrap().as_ptr());
-            gl::Uniform3f(uniform_location, uniform_value1, uniform_value2, uniform_value3);
-        }
+        self.set_uniform_value(uniform_name, &[uniform_value1, uniform_value2, uniform_value3]);
     }
 }
\ No newline at end of file
@@ -1,14 +1,24 @@
-use crate::renderer::properties::{TextureGlProperties, TextureImageProperties};
+use crate::renderer::properties::{TextureGlProperties, TextureImageProperties, ImageLayout};
 use crate::renderer::traits::shaderTrait::ShaderTrait;
 use crate::renderer::constants;
-
-extern crate image;
-use image::GenericImage;
+use crate::renderer::errors::TextureError;
+use crate::renderer::texture;
+use crate::renderer::imageData::{self, ImageData};
+use crate::renderer::textureFormat::{self, TextureFormat};
+use crate::renderer::cubemap;
+use crate::renderer::capabilities;
+use crate::renderer::blockDecoder;
+use crate::renderer::compressedImage::CompressedImage;
+use crate::renderer::readback;
 
 extern crate gl;
 use self::gl::types::*;
 
+extern crate image;
+use image::{ColorType, DynamicImage};
+
 use std::ffi::c_void;
+use std::ptr;
 
 pub trait TextureTrait {
     fn get_texture_id(&self) -> u32;
@@ -19,34 +29,387 @@ pub trait TextureTrait {
 
     /*  This function is used to set the texture parameters, to open the image from the path and to give the texture to opengl
     *   It also checks if the texture should be flipped.
+    *   If the image cannot be loaded or opengl rejects it an error is returned and the texture is left empty: gen_placeholder can be used instead.
+    *   A texture without a path (see Texture::missing) gets the placeholder.
+    *   A cubemap gets its six faces, an array texture its layers and a 3d texture its slices (see texture::load_images).
+    *   A texture made from memory (see Texture::from_voxels, from_pixels and empty) already has its pixels, so only the gl properties are applied.
+    *   A 2d texture from a KTX2 or a DDS file is given to opengl still compressed, with all its mip levels (see upload_compressed).
     */
-    fn gen_texture(&self) {
-        let gl_properties = self.get_gl_properties_ref();
+    fn gen_texture(&self) -> Result<(), TextureError> {
+        let image_properties = self.get_image_properties_ref();
+
+        if image_properties.layout == ImageLayout::Memory {
+            self.apply_gl_properties();
+            return Ok(());
+        }
+        if image_properties.path.is_empty() {
+            self.gen_placeholder();
+            return Ok(());
+        }
+
+        if texture::is_compressed_texture(self.get_gl_properties_ref().texture_type, image_properties) {
+            return self.upload_compressed(&CompressedImage::load(&image_properties.path)?);
+        }
+
+        self.upload_images(&texture::load_images(image_properties)?)
+    }
+
+    /*  This function gives the decoded images of the texture (the ones returned by texture::load_images) to opengl.
+    *   It is used by gen_texture and by the TextureLoader, that decodes the images on other threads.
+    */
+    fn upload_images(&self, images: &[ImageData]) -> Result<(), TextureError> {
+        match self.get_gl_properties_ref().texture_type {
+            gl::TEXTURE_CUBE_MAP => {
+                self.apply_gl_properties();
+                for (target, face) in cubemap::FACE_TARGETS.iter().zip(images) {
+                    self.upload_image_to_target(*target, face)?;
+                }
+
+                Ok(())
+            },
+            gl::TEXTURE_2D_ARRAY | gl::TEXTURE_3D => self.upload_layers(images),
+            _ => match images.first() {
+                Some(image) => self.upload_image(image),
+                None => Err(TextureError::Decode {
+                    path: self.get_image_properties_ref().path.clone(),
+                    message: "The texture has no images.".to_string(),
+                }),
+            },
+        }
+    }
+
+    /*  This function sets the texture parameters and gives a decoded image to opengl.
+    */
+    fn upload_image(&self, image: &ImageData) -> Result<(), TextureError> {
+        self.apply_gl_properties();
+        self.upload_image_to_target(self.get_gl_properties_ref().texture_type, image)
+    }
+
+    /*  This function gives a decoded image to a target of the bound texture (the texture type, or a face for a cubemap).
+    *   The format, the type and the internal format are found from the color type of the image (see textureFormat::get_texture_format),
+    *   unless they are set with INTERNAL_FORMAT_PROPERTY and FORMAT_PROPERTY. The row alignment is set from the width of the image.
+    */
+    fn upload_image_to_target(&self, target: GLenum, image: &ImageData) -> Result<(), TextureError> {
+        let image_properties = self.get_image_properties_ref();
+        let texture_format = get_upload_format(image_properties, image.color)?;
+
+        unsafe {
+            clear_gl_errors();
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, textureFormat::get_unpack_alignment(image.width, image.get_bytes_per_pixel()));
+            gl::TexImage2D(target, 0, texture_format.internal_format as i32, image.width as i32, image.height as i32, 0, texture_format.format, texture_format.data_type, image.pixels.as_ptr() as *const c_void);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            finish_upload(self.get_gl_properties_ref().texture_type, image_properties, &texture_format)
+        }
+    }
+
+    /*  This function sets the texture parameters and gives a compressed image with all its mip levels to opengl.
+    *   If the gpu does not support the format (see capabilities::is_compressed_format_supported) the levels are decompressed in rgba on the cpu (see blockDecoder).
+    *   The formats that cannot be decompressed (BC6H, EAC and ASTC) return TextureError::UnsupportedFormat.
+    *   The mip levels of the file are used as they are, so generate_mipmap is not needed.
+    */
+    fn upload_compressed(&self, image: &CompressedImage) -> Result<(), TextureError> {
+        let image_properties = self.get_image_properties_ref();
+        let texture_type = self.get_gl_properties_ref().texture_type;
+        let srgb = image.srgb || image_properties.srgb;
+        if image_properties.fliph || image_properties.flipv {
+            println!("The compressed texture {} cannot be flipped.", image_properties.path);
+        }
+
+        let compressed = capabilities::is_compressed_format_supported(image.format, srgb);
+        let decompressed_levels = if compressed {
+            Vec::<Vec::<u8>>::new()
+        } else {
+            image.levels.iter().enumerate().map(|(level, data)| {
+                let (width, height) = image.get_level_size(level);
+                blockDecoder::decompress(image.format, width, height, data).ok_or_else(|| TextureError::UnsupportedFormat {
+                    path: image_properties.path.clone(),
+                    message: if blockDecoder::is_decompressible(image.format) {
+                        format!("The compressed format {:?} is not supported by the gpu and the level {} cannot be decompressed.", image.format, level)
+                    } else {
+                        format!("The compressed format {:?} is not supported by the gpu and has no cpu fallback: it needs the driver support.", image.format)
+                    },
+                })
+            }).collect::<Result<Vec::<Vec::<u8>>, TextureError>>()?
+        };
+        let levels = if compressed { &image.levels } else { &decompressed_levels };
+
+        self.apply_gl_properties();
+        unsafe {
+            clear_gl_errors();
+            gl::TexParameteri(texture_type, gl::TEXTURE_BASE_LEVEL, 0);
+            gl::TexParameteri(texture_type, gl::TEXTURE_MAX_LEVEL, levels.len() as i32 - 1);
+
+            for (level, data) in levels.iter().enumerate() {
+                let (width, height) = image.get_level_size(level);
+                if compressed {
+                    gl::CompressedTexImage2D(texture_type, level as i32, image.format.get_gl_format(srgb), width as i32, height as i32, 0, data.len() as i32, data.as_ptr() as *const c_void);
+                } else {
+                    let internal_format = if srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 };
+                    gl::TexImage2D(texture_type, level as i32, internal_format as i32, width as i32, height as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, data.as_ptr() as *const c_void);
+                }
+            }
+
+            check_upload_error(image_properties)
+        }
+    }
+
+    /*  This function sets the texture parameters and gives the layers of an array texture (or the slices of a 3d texture) to opengl.
+    *   The layers must have the same size and color type.
+    */
+    fn upload_layers(&self, layers: &[ImageData]) -> Result<(), TextureError> {
+        let path = &self.get_image_properties_ref().path;
+        let first = layers.first().ok_or_else(|| TextureError::Decode {
+            path: path.clone(),
+            message: "The texture has no layers.".to_string(),
+        })?;
+        if layers.iter().any(|layer| layer.width != first.width || layer.height != first.height || layer.color != first.color) {
+            return Err(TextureError::Decode {
+                path: path.clone(),
+                message: "The layers of a texture must have the same size and color type.".to_string(),
+            });
+        }
+
+        let pixels = layers.iter().flat_map(|layer| layer.pixels.iter().copied()).collect::<Vec::<u8>>();
+        self.upload_voxels(first.width, first.height, layers.len() as u32, first.color, &pixels)
+    }
+
+    /*  This function sets the texture parameters and gives raw pixels to an array texture or a 3d texture.
+    *   The pixels are depth slices of width * height pixels of a color type (16 bit channels in the native byte order), one after the other.
+    *   The formats are found like in upload_image_to_target.
+    */
+    fn upload_voxels(&self, width: u32, height: u32, depth: u32, color: ColorType, pixels: &[u8]) -> Result<(), TextureError> {
+        let image_properties = self.get_image_properties_ref();
+        let texture_type = self.get_gl_properties_ref().texture_type;
+        let bytes_per_pixel = imageData::get_bytes_per_pixel(color);
+        let size = width as usize * height as usize * depth as usize * bytes_per_pixel;
+        if pixels.len() != size || size == 0 {
+            return Err(TextureError::Decode {
+                path: image_properties.path.clone(),
+                message: format!("{}x{}x{} pixels of {:?} need {} bytes, but {} were given.", width, height, depth, color, size, pixels.len()),
+            });
+        }
+        let texture_format = get_upload_format(image_properties, color)?;
+
+        self.apply_gl_properties();
+        unsafe {
+            clear_gl_errors();
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, textureFormat::get_unpack_alignment(width, bytes_per_pixel));
+            gl::TexImage3D(texture_type, 0, texture_format.internal_format as i32, width as i32, height as i32, depth as i32, 0, texture_format.format, texture_format.data_type, pixels.as_ptr() as *const c_void);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            finish_upload(texture_type, image_properties, &texture_format)
+        }
+    }
+
+    /*  This function sets the texture parameters and gives the texture empty storage of a size, with undefined pixels.
+    *   A cubemap gets six empty faces. The format and the type given to opengl are found from the internal format (see textureFormat::get_pixel_format).
+    */
+    fn allocate(&self, width: u32, height: u32, internal_format: GLenum) -> Result<(), TextureError> {
+        let image_properties = self.get_image_properties_ref();
+        let texture_type = self.get_gl_properties_ref().texture_type;
+        let pixel_format = textureFormat::get_pixel_format(internal_format).ok_or_else(|| TextureError::UnsupportedFormat {
+            path: image_properties.path.clone(),
+            message: format!("The internal format 0x{:X} cannot be allocated.", internal_format),
+        })?;
+
+        self.apply_gl_properties();
+        unsafe {
+            clear_gl_errors();
+            let targets = if texture_type == gl::TEXTURE_CUBE_MAP { &cubemap::FACE_TARGETS[..] } else { &[texture_type][..] };
+            for target in targets {
+                gl::TexImage2D(*target, 0, internal_format as i32, width as i32, height as i32, 0, pixel_format.format, pixel_format.data_type, ptr::null());
+            }
+
+            check_upload_error(image_properties)
+        }
+    }
+
+    /*  This function replaces a rectangle of the pixels of a 2d texture (level 0), with the top left corner in x, y.
+    *   The pixels must be in the format of the texture: the format and the type are found from its internal format (see textureFormat::get_pixel_format),
+    *   so for example a gl::RGBA8 texture takes 4 bytes per pixel and a gl::R32F texture takes one f32 per pixel.
+    *   It is meant for procedural data, video frames and streaming updates: the texture is not allocated again.
+    */
+    fn update_region(&self, x: u32, y: u32, width: u32, height: u32, pixels: &[u8]) -> Result<(), TextureError> {
         let image_properties = self.get_image_properties_ref();
+        let texture_type = self.get_gl_properties_ref().texture_type;
+        if texture_type != gl::TEXTURE_2D && texture_type != gl::TEXTURE_RECTANGLE {
+            return Err(TextureError::UnsupportedFormat {
+                path: image_properties.path.clone(),
+                message: "Only the regions of the 2d textures can be updated.".to_string(),
+            });
+        }
+
+        let (mut internal_format, mut texture_width, mut texture_height) = (0, 0, 0);
+        unsafe {
+            gl::BindTexture(texture_type, self.get_texture_id());
+            gl::GetTexLevelParameteriv(texture_type, 0, gl::TEXTURE_INTERNAL_FORMAT, &mut internal_format);
+            gl::GetTexLevelParameteriv(texture_type, 0, gl::TEXTURE_WIDTH, &mut texture_width);
+            gl::GetTexLevelParameteriv(texture_type, 0, gl::TEXTURE_HEIGHT, &mut texture_height);
+        }
+
+        let pixel_format = textureFormat::get_pixel_format(internal_format as GLenum).ok_or_else(|| TextureError::UnsupportedFormat {
+            path: image_properties.path.clone(),
+            message: format!("The pixels of the internal format 0x{:X} cannot be updated.", internal_format),
+        })?;
+        let pixel_size = textureFormat::get_pixel_size(pixel_format.format, pixel_format.data_type);
+        let size = width as usize * height as usize * pixel_size;
+        if x as u64 + width as u64 > texture_width as u64 || y as u64 + height as u64 > texture_height as u64 {
+            return Err(TextureError::Decode {
+                path: image_properties.path.clone(),
+                message: format!("The region {}x{} at {}, {} is outside the {}x{} texture.", width, height, x, y, texture_width, texture_height),
+            });
+        }
+        if pixels.len() != size {
+            return Err(TextureError::Decode {
+                path: image_properties.path.clone(),
+                message: format!("A {}x{} region needs {} bytes, but {} were given.", width, height, size, pixels.len()),
+            });
+        }
+
+        unsafe {
+            clear_gl_errors();
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, textureFormat::get_unpack_alignment(width, pixel_size));
+            gl::TexSubImage2D(texture_type, 0, x as i32, y as i32, width as i32, height as i32, pixel_format.format, pixel_format.data_type, pixels.as_ptr() as *const c_void);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            check_upload_error(image_properties)
+        }
+    }
+
+    /*  This function reads a mip level of a 2d texture back from the gpu.
+    *   The pixels are read in the channels of the internal format: one channel as grey, two channels as grey and alpha (like the grey images), three as rgb and four as rgba.
+    *   The depth textures are read as grey. The channels are converted to 8 bits, the float channels are clamped between 0 and 1. The integer formats cannot be read.
+    *   The rows are returned in the order of the texture: the images loaded from files keep the order of the file,
+    *   but the textures rendered by opengl have the first row at the bottom, so flip_vertical should be true for them.
+    */
+    fn read_image(&self, level: GLint, flip_vertical: bool) -> Result<DynamicImage, TextureError> {
+        let image_properties = self.get_image_properties_ref();
+        let texture_type = self.get_gl_properties_ref().texture_type;
+        if texture_type != gl::TEXTURE_2D && texture_type != gl::TEXTURE_RECTANGLE {
+            return Err(TextureError::UnsupportedFormat {
+                path: image_properties.path.clone(),
+                message: "Only the 2d textures can be read back.".to_string(),
+            });
+        }
+
+        let (mut internal_format, mut width, mut height) = (0, 0, 0);
+        unsafe {
+            gl::BindTexture(texture_type, self.get_texture_id());
+            gl::GetTexLevelParameteriv(texture_type, level, gl::TEXTURE_INTERNAL_FORMAT, &mut internal_format);
+            gl::GetTexLevelParameteriv(texture_type, level, gl::TEXTURE_WIDTH, &mut width);
+            gl::GetTexLevelParameteriv(texture_type, level, gl::TEXTURE_HEIGHT, &mut height);
+        }
+        if width <= 0 || height <= 0 {
+            return Err(TextureError::Decode {
+                path: image_properties.path.clone(),
+                message: format!("The level {} of the texture is empty.", level),
+            });
+        }
+
+        //the compressed formats are not listed, opengl decompresses them in rgba.
+        let source_format = textureFormat::get_pixel_format(internal_format as GLenum).map(|format| format.format).unwrap_or(gl::RGBA);
+        let (format, color) = match source_format {
+            gl::RED => (gl::RED, ColorType::Gray(8)),
+            gl::RG => (gl::RG, ColorType::GrayA(8)),
+            gl::RGB => (gl::RGB, ColorType::RGB(8)),
+            gl::RGBA => (gl::RGBA, ColorType::RGBA(8)),
+            gl::DEPTH_COMPONENT | gl::DEPTH_STENCIL => (gl::DEPTH_COMPONENT, ColorType::Gray(8)),
+            _ => return Err(TextureError::UnsupportedFormat {
+                path: image_properties.path.clone(),
+                message: format!("The integer internal format 0x{:X} cannot be read back.", internal_format),
+            }),
+        };
+
+        let mut image = ImageData {
+            width: width as u32,
+            height: height as u32,
+            color,
+            pixels: vec![0; width as usize * height as usize * imageData::get_channel_count(color)],
+        };
+        unsafe {
+            clear_gl_errors();
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            if format == gl::DEPTH_COMPONENT {
+                let mut depths = vec![0.0f32; width as usize * height as usize];
+                gl::GetTexImage(texture_type, level, gl::DEPTH_COMPONENT, gl::FLOAT, depths.as_mut_ptr() as *mut c_void);
+                for (pixel, depth) in image.pixels.iter_mut().zip(&depths) {
+                    *pixel = (depth.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            } else {
+                gl::GetTexImage(texture_type, level, format, gl::UNSIGNED_BYTE, image.pixels.as_mut_ptr() as *mut c_void);
+            }
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+
+            let error = gl::GetError();
+            if error != gl::NO_ERROR {
+                return Err(TextureError::Readback {
+                    path: image_properties.path.clone(),
+                    error,
+                });
+            }
+        }
+
+        if flip_vertical {
+            image.flip_vertical();
+        }
+        image.to_dynamic_image().ok_or_else(|| TextureError::Decode {
+            path: image_properties.path.clone(),
+            message: "The pixels don't fill the image.".to_string(),
+        })
+    }
+
+    /*  This function reads a mip level of a 2d texture (see read_image) and saves it in a png file.
+    */
+    fn save_png(&self, level: GLint, flip_vertical: bool, path: &str) -> Result<(), TextureError> {
+        readback::save_png(&self.read_image(level, flip_vertical)?, path)
+    }
+
+    /*  This function gives opengl the "missing texture" checkerboard instead of the image.
+    *   It is used when the image cannot be loaded, so the missing asset is easy to see without stopping the program.
+    *   A cubemap gets the checkerboard on every face, an array texture or a 3d texture gets a single layer.
+    */
+    fn gen_placeholder(&self) {
+        let pixels = texture::get_placeholder_pixels();
+        let size = texture::PLACEHOLDER_SIZE as i32;
+        let texture_type = self.get_gl_properties_ref().texture_type;
+
+        self.apply_gl_properties();
+        unsafe {
+            match texture_type {
+                gl::TEXTURE_CUBE_MAP => for target in &cubemap::FACE_TARGETS {
+                    gl::TexImage2D(*target, 0, gl::RGBA as i32, size, size, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const c_void);
+                },
+                gl::TEXTURE_2D_ARRAY | gl::TEXTURE_3D => {
+                    gl::TexImage3D(texture_type, 0, gl::RGBA as i32, size, size, 1, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const c_void);
+                },
+                _ => {
+                    gl::TexImage2D(texture_type, 0, gl::RGBA as i32, size, size, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const c_void);
+                },
+            }
+        }
+    }
+
+    /*  This function binds the texture and sets the wrap and filter parameters.
+    */
+    fn apply_gl_properties(&self) {
+        let gl_properties = self.get_gl_properties_ref();
 
         unsafe {
             gl::BindTexture(gl_properties.texture_type, self.get_texture_id());
 
             gl::TexParameteri(gl_properties.texture_type, gl::TEXTURE_WRAP_S, gl_properties.texture_warp_s as i32);
             gl::TexParameteri(gl_properties.texture_type, gl::TEXTURE_WRAP_T, gl_properties.texture_warp_t as i32);
+            gl::TexParameteri(gl_properties.texture_type, gl::TEXTURE_WRAP_R, gl_properties.texture_warp_r as i32);
             gl::TexParameteri(gl_properties.texture_type, gl::TEXTURE_MIN_FILTER, gl_properties.texture_min_filter as i32);
             gl::TexParameteri(gl_properties.texture_type, gl::TEXTURE_MAG_FILTER, gl_properties.texture_mag_filter as i32);
-
-            let mut img = image::open(&image_properties.path).expect("Failed to open the texture {}");
-            if image_properties.fliph {
-                img = img.fliph();
-            }
-            if image_properties.flipv {
-                img = img.flipv();
-            }
-            let data = img.raw_pixels();
-            gl::TexImage2D(gl::TEXTURE_2D, 0, image_properties.internal_format as i32, img.width() as i32, img.height() as i32, 0, image_properties.format, gl::UNSIGNED_BYTE, &data[0] as *const u8 as  *const c_void);
         }
     }
 
     /*  This function is used to set a opengl property in the gl_properties struct inside the class.
     *   It takes the property_id that is a constant defined in renderer::constants and the value to set.
     *   It matches the property_id and sets the right property.
+    *   Note: the properties are given to opengl by gen_texture and apply_gl_properties. A Sampler (see renderer::sampler) can change the sampling at any time.
     *   ***I should really find a more dynamic way to set the property maybe using an array... idk...***
     */
     fn set_gl_property(&mut self, property: GLenum, value: GLenum) {
@@ -55,6 +418,7 @@ pub trait TextureTrait {
         match property {
             gl::TEXTURE_WRAP_S => gl_properties.texture_warp_s = value,
             gl::TEXTURE_WRAP_T => gl_properties.texture_warp_t = value,
+            gl::TEXTURE_WRAP_R => gl_properties.texture_warp_r = value,
             gl::TEXTURE_MIN_FILTER => gl_properties.texture_min_filter = value,
             gl::TEXTURE_MAG_FILTER => gl_properties.texture_mag_filter = value,
             _ => println!("Wrong gl property set on texture {} (id: {})", gl_properties.texture_uniform_name, texture_id),
@@ -104,6 +468,7 @@ pub trait TextureTrait {
     /*  This function is used to set a image property in the image_properties struct inside the class.
     *   It takes the property_id that is a constant defined in renderer::constants and the value to set.
     *   It matches the property_id and sets the right property.
+    *   The formats set here override the ones found from the image, 0 goes back to the found ones.
     *   ***I should really find a more dynamic way to set the property maybe using an array... idk...***
     */
     fn set_image_property(&mut self, property_id: u8, property: GLenum) {
@@ -113,12 +478,23 @@ pub trait TextureTrait {
         match property_id {
             constants::FLIP_H_PROPERTY => image_properties.fliph = property != 0, //weird cast to bool because rust
             constants::FLIP_V_PROPERTY => image_properties.flipv = property != 0,
-            constants::INTERNAL_FORMAT_PROPERTY => image_properties.internal_format = property,
-            constants::FORMAT_PROPERTY => image_properties.format = property,
+            constants::INTERNAL_FORMAT_PROPERTY => image_properties.internal_format = Some(property).filter(|format| *format != 0),
+            constants::FORMAT_PROPERTY => image_properties.format = Some(property).filter(|format| *format != 0),
+            constants::SRGB_PROPERTY => image_properties.srgb = property != 0,
             _ => println!("Wrong image property set on texture id: {}", texture_id),
         }
     }
 
+    /*  This function binds a level of the texture to an image unit, so a shader (for example a compute shader) can read and write it with imageLoad and imageStore.
+    *   access is gl::READ_ONLY, gl::WRITE_ONLY or gl::READ_WRITE and format is the format of the image in the shader (gl::RGBA8, gl::RGBA32F...).
+    *   Note: call compute::memory_barrier before sampling the texture after a write.
+    */
+    fn bind_image(&self, image_unit: GLuint, level: GLint, access: GLenum, format: GLenum) {
+        unsafe {
+            gl::BindImageTexture(image_unit, self.get_texture_id(), level, gl::TRUE, 0, access, format);
+        }
+    }
+
     /*  This is a simple function used to bind the texture to the right active texture.
     */
     fn bind(&self) {
@@ -151,4 +527,57 @@ pub trait TextureTrait {
             gl::BindTexture(gl_properties.texture_type, texture_id);
         }
     }
-}
\ No newline at end of file
+}
+
+/*  This function returns the format used to upload pixels of a color type: the one found from the color type, with the formats set in the image properties.
+*   The swizzle is used only when the format is found, because it depends on it.
+*/
+fn get_upload_format(image_properties: &TextureImageProperties, color: ColorType) -> Result<TextureFormat, TextureError> {
+    let found_format = textureFormat::get_texture_format(color, image_properties.srgb).ok_or_else(|| TextureError::UnsupportedFormat {
+        path: image_properties.path.clone(),
+        message: format!("The color type {:?} cannot be given to opengl.", color),
+    })?;
+
+    Ok(TextureFormat {
+        internal_format: image_properties.internal_format.unwrap_or(found_format.internal_format),
+        format: image_properties.format.unwrap_or(found_format.format),
+        data_type: found_format.data_type,
+        swizzle: found_format.swizzle.filter(|_| image_properties.format.is_none()),
+    })
+}
+
+/*  This function sets the swizzle of the bound texture after an upload and returns the opengl error of the upload, if any.
+*/
+unsafe fn finish_upload(texture_type: GLenum, image_properties: &TextureImageProperties, texture_format: &TextureFormat) -> Result<(), TextureError> {
+    if let Some(swizzle) = texture_format.swizzle {
+        let swizzle = swizzle.map(|channel| channel as GLint);
+        gl::TexParameteriv(texture_type, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+    }
+
+    check_upload_error(image_properties)
+}
+
+/*  This function returns the opengl error of an upload, if any.
+*/
+unsafe fn check_upload_error(image_properties: &TextureImageProperties) -> Result<(), TextureError> {
+    let error = gl::GetError();
+    if error != gl::NO_ERROR {
+        return Err(TextureError::Upload {
+            path: image_properties.path.clone(),
+            error,
+        });
+    }
+
+    Ok(())
+}
+
+/*  This function removes the previous opengl errors, so the error read after an upload (or a readback) belongs to it.
+*   The number of reads is limited, because some drivers keep returning an error when the context is lost.
+*/
+pub(crate) unsafe fn clear_gl_errors() {
+    for _ in 0..32 {
+        if gl::GetError() == gl::NO_ERROR {
+            break;
+        }
+    }
+}
@@ -0,0 +1,167 @@
+/*  File: renderer/transformFeedback.rs
+*   Author: Vicix
+*
+*   This file contains the TransformFeedback class.
+*   The class TransformFeedback records the outputs of the vertex (or geometry) shader of a draw into vbos.
+*   The captured varyings are declared when the program is linked, with ShaderBuilder::with_feedback_varyings.
+*   It only uses the default transform feedback state, so it works on opengl 3.3 (transform feedback objects need 4.0).
+*
+*   Example (particle ping-pong, the shader outputs the updated particle):
+*       let mut feedback = TransformFeedback::new();
+*       feedback.set_buffer(0, &vbos[1 - current]);
+*       update_shader.bind();
+*       feedback.capture_draw(&objects[current], particle_count);    // objects[i] = GlObject::with_vbo(&vbos[i]), drawn as gl::POINTS
+*       current = 1 - current;
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::vbo::Vbo;
+use crate::renderer::traits::vboTrait::VboTrait;
+use crate::renderer::traits::glObjectTrait::GlObjectTrait;
+
+use std::cell::Cell;
+
+/*  This is the declaration of the class.
+*   buffers contains the vbo bound to every transform feedback binding index (0 if none).
+*   query_id is the query counting the written primitives and has_result is true after the first capture.
+*   discard_rasterizer is true if nothing is drawn on the screen while capturing.
+*/
+#[derive(Debug)]
+pub struct TransformFeedback {
+    buffers: Vec::<GLuint>,
+    query_id: GLuint,
+    has_result: Cell<bool>,
+    discard_rasterizer: bool,
+}
+
+#[allow(dead_code)]
+impl TransformFeedback {
+    /*  The constructor of the class.
+    *   It creates the primitives written query. By default the rasterizer is discarded, because the capture is usually a simulation step.
+    */
+    pub fn new() -> TransformFeedback {
+        let mut query = 1;
+        unsafe {
+            gl::GenQueries(1, &mut query);
+        }
+
+        TransformFeedback {
+            buffers: Vec::<GLuint>::new(),
+            query_id: query,
+            has_result: Cell::new(false),
+            discard_rasterizer: true,
+        }
+    }
+
+    /*  This function sets the vbo written at a binding index.
+    *   With gl::INTERLEAVED_ATTRIBS only the index 0 is used, with gl::SEPARATE_ATTRIBS the i-th varying is written at the index i.
+    *   Note: the vbo must be big enough for the captured vertices (see Vbo::with_capacity), the exceeding primitives are not written.
+    */
+    pub fn set_buffer(&mut self, index: usize, vbo: &Vbo) {
+        if self.buffers.len() <= index {
+            self.buffers.resize(index + 1, 0);
+        }
+        self.buffers[index] = vbo.get_vbo_id();
+    }
+
+    /*  This function removes every vbo.
+    */
+    pub fn clear_buffers(&mut self) {
+        self.buffers.clear();
+    }
+
+    /*  This function chooses if the primitives are also drawn on the screen while capturing.
+    */
+    pub fn set_rasterizer_discard(&mut self, discard_rasterizer: bool) {
+        self.discard_rasterizer = discard_rasterizer;
+    }
+
+    /*  This function binds the vbos and starts capturing.
+    *   primitive_mode is gl::POINTS, gl::LINES or gl::TRIANGLES and must match the draw mode (strips and fans count as lines and triangles).
+    *   The shader with the captured varyings must be bound before calling this.
+    */
+    pub fn begin(&self, primitive_mode: GLenum) {
+        unsafe {
+            for (index, vbo) in self.buffers.iter().enumerate() {
+                if *vbo != 0 {
+                    gl::BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, index as GLuint, *vbo);
+                }
+            }
+
+            if self.discard_rasterizer {
+                gl::Enable(gl::RASTERIZER_DISCARD);
+            }
+            gl::BeginQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN, self.query_id);
+            gl::BeginTransformFeedback(primitive_mode);
+        }
+    }
+
+    /*  This function stops capturing and unbinds the vbos, so they can be used as vertex data.
+    */
+    pub fn end(&self) {
+        unsafe {
+            gl::EndTransformFeedback();
+            gl::EndQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN);
+            if self.discard_rasterizer {
+                gl::Disable(gl::RASTERIZER_DISCARD);
+            }
+
+            for index in 0..self.buffers.len() {
+                gl::BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, index as GLuint, 0);
+            }
+        }
+        self.has_result.set(true);
+    }
+
+    /*  This function draws an object and captures the outputs.
+    *   The primitive mode is found from the draw mode of the object.
+    */
+    pub fn capture_draw<G: GlObjectTrait + ?Sized>(&self, object: &G, count: GLint) {
+        let primitive_mode = match get_primitive_mode(object.get_properties_ref().draw_mode) {
+            Some(primitive_mode) => primitive_mode,
+            None => {
+                println!("Transform feedback cannot capture draw mode {}.", object.get_properties_ref().draw_mode);
+                return;
+            },
+        };
+
+        self.begin(primitive_mode);
+        object.draw(count);
+        self.end();
+    }
+
+    /*  This function returns the number of primitives written by the last capture (points, lines or triangles, not vertices).
+    *   Note: it waits for the gpu to finish the capture, so it should not be called every frame.
+    */
+    pub fn get_primitives_written(&self) -> GLuint {
+        if !self.has_result.get() {
+            return 0;
+        }
+
+        let mut primitives = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.query_id, gl::QUERY_RESULT, &mut primitives);
+        }
+
+        primitives
+    }
+}
+
+impl Default for TransformFeedback {
+    fn default() -> TransformFeedback {
+        TransformFeedback::new()
+    }
+}
+
+/*  This function returns the transform feedback primitive mode of a draw mode.
+*/
+pub fn get_primitive_mode(draw_mode: GLenum) -> Option<GLenum> {
+    match draw_mode {
+        gl::POINTS => Some(gl::POINTS),
+        gl::LINES | gl::LINE_STRIP | gl::LINE_LOOP => Some(gl::LINES),
+        gl::TRIANGLES | gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => Some(gl::TRIANGLES),
+        _ => None,
+    }
+}
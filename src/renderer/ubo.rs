@@ -0,0 +1,80 @@
+/*  File: renderer/ubo.rs
+*   Author: Vicix
+*
+*   This file contains the Ubo class.
+*   The class Ubo is a simple abstaction of the opengl uniform buffer.
+*   It holds a value of type T written in the std140 layout, so it can be shared between every shader using the same uniform block.
+*/
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::std140::{Std140, Std140Writer};
+
+use std::ffi::c_void;
+use std::marker::PhantomData;
+
+/*  The declaration of the class.
+*   It contains the ubo id and the usage (gl::DYNAMIC_DRAW, gl::STATIC_DRAW...) used when the data is updated.
+*/
+#[derive(Debug)]
+pub struct Ubo<T: Std140> {
+    ubo_id: GLuint,
+    usage: GLenum,
+    marker: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: Std140> Ubo<T> {
+    /*  The constructor of the class.
+    *   It generates a uniform buffer and writes the value in it.
+    */
+    pub fn new(value: &T, usage: GLenum) -> Ubo<T> {
+        let mut ubo = 1;
+        unsafe {
+            gl::GenBuffers(1, &mut ubo);
+        }
+
+        let ubo = Ubo {
+            ubo_id: ubo,
+            usage,
+            marker: PhantomData,
+        };
+        ubo.update(value);
+
+        ubo
+    }
+
+    /*  This function returns the ubo id.
+    */
+    pub fn get_ubo_id(&self) -> GLuint {
+        self.ubo_id
+    }
+
+    /*  A simple function to bind the buffer.
+    */
+    pub fn bind_ubo(&self) {
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.ubo_id);
+        }
+    }
+
+    /*  This function writes a new value in the buffer, in the std140 layout.
+    */
+    pub fn update(&self, value: &T) {
+        let data = Std140Writer::to_bytes(value);
+
+        self.bind_ubo();
+        unsafe {
+            gl::BufferData(gl::UNIFORM_BUFFER, data.len() as GLsizeiptr, data.as_ptr() as *const c_void, self.usage);
+        }
+    }
+
+    /*  This function binds the buffer to a binding point.
+    *   Every uniform block bound to the same binding point (see ShaderTrait::bind_uniform_block) reads this buffer.
+    */
+    pub fn bind_to(&self, binding_point: GLuint) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding_point, self.ubo_id);
+        }
+    }
+}
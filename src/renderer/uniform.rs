@@ -0,0 +1,147 @@
+/*  File: renderer/uniform.rs
+*   Author: Vicix
+*
+*   This file contains the values that can be written in a uniform.
+*   Every type implementing UniformElement (scalars, vectors, matrices and texture units) can be written alone or as an array using a slice.
+*   Vectors are arrays ([f32; 3] is a vec3), matrices are arrays of columns ([[f32; 4]; 4] is a mat4).
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use std::slice;
+
+/*  This trait is implemented by every value that can be written in a uniform.
+*   get_uniform_type returns the opengl type of the uniform (for arrays the type of an element).
+*/
+pub trait UniformValue {
+    fn get_uniform_type(&self) -> GLenum;
+    fn write_uniform(&self, location: GLint);
+}
+
+/*  This trait is implemented by the types that can be an element of a uniform array.
+*   write_uniform_array writes all the values starting from the location.
+*/
+pub trait UniformElement: Sized {
+    const UNIFORM_TYPE: GLenum;
+
+    fn write_uniform_array(values: &[Self], location: GLint);
+}
+
+impl<T: UniformElement> UniformValue for T {
+    fn get_uniform_type(&self) -> GLenum {
+        T::UNIFORM_TYPE
+    }
+
+    fn write_uniform(&self, location: GLint) {
+        T::write_uniform_array(slice::from_ref(self), location);
+    }
+}
+
+impl<T: UniformElement> UniformValue for &[T] {
+    fn get_uniform_type(&self) -> GLenum {
+        T::UNIFORM_TYPE
+    }
+
+    fn write_uniform(&self, location: GLint) {
+        T::write_uniform_array(self, location);
+    }
+}
+
+impl<T: UniformElement> UniformValue for Vec::<T> {
+    fn get_uniform_type(&self) -> GLenum {
+        T::UNIFORM_TYPE
+    }
+
+    fn write_uniform(&self, location: GLint) {
+        T::write_uniform_array(self, location);
+    }
+}
+
+/*  This is a matrix written with the transpose flag set, so it can be given in row-major order.
+*/
+#[allow(dead_code)]
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy)]
+pub struct Transposed<M>(pub M);
+
+/*  This is the number of a texture unit, used to set sampler uniforms.
+*/
+#[allow(dead_code)]
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy)]
+pub struct TextureUnit(pub GLint);
+
+/*  This macro implements UniformElement for vectors of floats, integers and unsigned integers.
+*   $scalar is the type of a component and $function is the opengl function that writes an array.
+*/
+macro_rules! impl_vector_uniform {
+    ($type:ty, $scalar:ty, $uniform_type:expr, $function:path) => {
+        impl UniformElement for $type {
+            const UNIFORM_TYPE: GLenum = $uniform_type;
+
+            fn write_uniform_array(values: &[Self], location: GLint) {
+                unsafe {
+                    $function(location, values.len() as GLsizei, values.as_ptr() as *const $scalar);
+                }
+            }
+        }
+    };
+}
+
+/*  This macro implements UniformElement for matrices and transposed matrices.
+*/
+macro_rules! impl_matrix_uniform {
+    ($type:ty, $uniform_type:expr, $function:path) => {
+        impl UniformElement for $type {
+            const UNIFORM_TYPE: GLenum = $uniform_type;
+
+            fn write_uniform_array(values: &[Self], location: GLint) {
+                unsafe {
+                    $function(location, values.len() as GLsizei, gl::FALSE, values.as_ptr() as *const GLfloat);
+                }
+            }
+        }
+
+        impl UniformElement for Transposed<$type> {
+            const UNIFORM_TYPE: GLenum = $uniform_type;
+
+            fn write_uniform_array(values: &[Self], location: GLint) {
+                unsafe {
+                    $function(location, values.len() as GLsizei, gl::TRUE, values.as_ptr() as *const GLfloat);
+                }
+            }
+        }
+    };
+}
+
+impl_vector_uniform!(f32, GLfloat, gl::FLOAT, gl::Uniform1fv);
+impl_vector_uniform!([f32; 2], GLfloat, gl::FLOAT_VEC2, gl::Uniform2fv);
+impl_vector_uniform!([f32; 3], GLfloat, gl::FLOAT_VEC3, gl::Uniform3fv);
+impl_vector_uniform!([f32; 4], GLfloat, gl::FLOAT_VEC4, gl::Uniform4fv);
+impl_vector_uniform!(i32, GLint, gl::INT, gl::Uniform1iv);
+impl_vector_uniform!([i32; 2], GLint, gl::INT_VEC2, gl::Uniform2iv);
+impl_vector_uniform!([i32; 3], GLint, gl::INT_VEC3, gl::Uniform3iv);
+impl_vector_uniform!([i32; 4], GLint, gl::INT_VEC4, gl::Uniform4iv);
+impl_vector_uniform!(u32, GLuint, gl::UNSIGNED_INT, gl::Uniform1uiv);
+impl_vector_uniform!([u32; 2], GLuint, gl::UNSIGNED_INT_VEC2, gl::Uniform2uiv);
+impl_vector_uniform!([u32; 3], GLuint, gl::UNSIGNED_INT_VEC3, gl::Uniform3uiv);
+impl_vector_uniform!([u32; 4], GLuint, gl::UNSIGNED_INT_VEC4, gl::Uniform4uiv);
+impl_vector_uniform!(TextureUnit, GLint, gl::INT, gl::Uniform1iv);
+
+impl_matrix_uniform!([[f32; 2]; 2], gl::FLOAT_MAT2, gl::UniformMatrix2fv);
+impl_matrix_uniform!([[f32; 3]; 3], gl::FLOAT_MAT3, gl::UniformMatrix3fv);
+impl_matrix_uniform!([[f32; 4]; 4], gl::FLOAT_MAT4, gl::UniformMatrix4fv);
+
+/*  Booleans are not stored as integers by rust, so they are converted before being written.
+*/
+impl UniformElement for bool {
+    const UNIFORM_TYPE: GLenum = gl::BOOL;
+
+    fn write_uniform_array(values: &[Self], location: GLint) {
+        let values: Vec::<GLint> = values.iter().map(|value| *value as GLint).collect();
+        unsafe {
+            gl::Uniform1iv(location, values.len() as GLsizei, values.as_ptr());
+        }
+    }
+}
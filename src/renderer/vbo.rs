@@ -9,6 +9,8 @@ use self::gl::types::*;
 
 use crate::renderer::traits::vboTrait::VboTrait;
 
+use std::ptr;
+
 
 /*  The declaration of the class.
 *   The only thing we need is the vbo id.
@@ -35,6 +37,16 @@ impl Vbo {
         }
     }
 
+    /*  This constructor creates a vbo with space for element_count elements of type T, without writing them.
+    *   It is used when the vbo is written by the gpu, for example by transform feedback.
+    */
+    pub fn with_capacity<T>(element_count: usize, usage: GLenum) -> Vbo {
+        let vbo = Vbo::new();
+        vbo.add_vbo_data::<T>(element_count, ptr::null(), usage);
+
+        vbo
+    }
+
 
     /*  A simple function to add the data to the buffer.
     *
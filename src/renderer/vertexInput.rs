@@ -0,0 +1,191 @@
+/*  File: renderer/vertexInput.rs
+*   Author: Vicix
+*
+*   This file contains the VertexInput struct and the function that compares the inputs of a vertex shader with a vao layout.
+*   It finds the inputs without a layout element, the layout elements not used by the shader and the elements with a different size or type.
+*   It does not use opengl, so it can be used both with a linked Shader and by the offline validation.
+*   The inputs of a linked program are read from its ProgramReflection with get_vertex_inputs.
+*/
+
+extern crate gl;
+use self::gl::types::*;
+
+use crate::renderer::vaoLayoutElement::VaoLayoutElement;
+use crate::renderer::reflection::ProgramReflection;
+
+use std::fmt;
+
+/*  This struct describes an input of a vertex shader.
+*   component_count is the number of components (2 for a vec2) and base_type is the opengl type of a component (gl::FLOAT, gl::INT, gl::UNSIGNED_INT).
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexInput {
+    pub name: String,
+    pub location: GLuint,
+    pub component_count: GLint,
+    pub base_type: GLenum,
+}
+
+/*  This enum is a difference between the inputs of a shader and a layout.
+*   Missing: the shader reads a location that is not in the layout. This is an error.
+*   Unused: the layout has an element that the shader does not read. This is only a warning.
+*   Mismatched: the shader and the layout don't agree on the component count or on the type. This is an error.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutMismatch {
+    Missing {
+        input: VertexInput,
+    },
+    Unused {
+        location: GLuint,
+    },
+    Mismatched {
+        input: VertexInput,
+        element_count: GLint,
+        base_type: GLenum,
+    },
+}
+
+#[allow(dead_code)]
+impl LayoutMismatch {
+    /*  This function returns true if the mismatch breaks the drawing. Unused elements are only a warning.
+    */
+    pub fn is_error(&self) -> bool {
+        !matches!(self, LayoutMismatch::Unused { .. })
+    }
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutMismatch::Missing { input } =>
+                write!(f, "Input {} (location {}) has no layout element.", input.name, input.location),
+            LayoutMismatch::Unused { location } =>
+                write!(f, "Layout element {} is not used by the shader.", location),
+            LayoutMismatch::Mismatched { input, element_count, base_type } =>
+                write!(f, "Input {} (location {}) has {} components of type {}, but the layout gives {} components of type {}.",
+                    input.name, input.location, input.component_count, get_type_name(input.base_type), element_count, get_type_name(*base_type)),
+        }
+    }
+}
+
+/*  This function compares the inputs of a vertex shader with a layout.
+*   The index of a layout element is its location, like in VaoLayoutTrait::write_layout.
+*   Note: write_layout uses gl::VertexAttribPointer, so the shader always receives floats, whatever the element type is. Integer inputs are reported as mismatched.
+*/
+pub fn check_vertex_layout(inputs: &[VertexInput], layout: &[VaoLayoutElement]) -> Vec::<LayoutMismatch> {
+    let mut mismatches = Vec::<LayoutMismatch>::new();
+
+    for input in inputs {
+        match layout.get(input.location as usize).filter(|element| element.used) {
+            Some(element) => {
+                let base_type = get_shader_base_type(element);
+                if element.element_count != input.component_count || base_type != input.base_type {
+                    mismatches.push(LayoutMismatch::Mismatched {
+                        input: input.clone(),
+                        element_count: element.element_count,
+                        base_type,
+                    });
+                }
+            },
+            None => mismatches.push(LayoutMismatch::Missing {
+                input: input.clone(),
+            }),
+        }
+    }
+
+    for (location, element) in layout.iter().enumerate() {
+        if element.used && !inputs.iter().any(|input| input.location as usize == location) {
+            mismatches.push(LayoutMismatch::Unused {
+                location: location as GLuint,
+            });
+        }
+    }
+
+    mismatches
+}
+
+/*  This function returns the inputs of a linked program, sorted by location.
+*   A matrix takes a location for every column and an array a location (or more) for every element, so they become more inputs named "name[i]".
+*   The built-in inputs (gl_VertexID...) and the types that cannot be an input are skipped.
+*/
+pub fn get_vertex_inputs(reflection: &ProgramReflection) -> Vec::<VertexInput> {
+    let mut inputs = Vec::<VertexInput>::new();
+
+    for attribute in reflection.get_attributes_ref().values() {
+        if attribute.location < 0 {
+            continue;
+        }
+        let (component_count, base_type, column_count) = match get_attribute_format(attribute.gl_type) {
+            Some(format) => format,
+            None => continue,
+        };
+
+        let location_count = attribute.size.max(1) * column_count;
+        let base_name = attribute.name.trim_end_matches("[0]");
+        for offset in 0..location_count {
+            inputs.push(VertexInput {
+                name: if location_count > 1 { format!("{}[{}]", base_name, offset) } else { base_name.to_string() },
+                location: (attribute.location + offset) as GLuint,
+                component_count,
+                base_type,
+            });
+        }
+    }
+    inputs.sort_by_key(|input| input.location);
+
+    inputs
+}
+
+/*  This function returns the component count, the base type and the number of columns (locations) of an attribute type.
+*/
+fn get_attribute_format(gl_type: GLenum) -> Option<(GLint, GLenum, GLint)> {
+    Some(match gl_type {
+        gl::FLOAT => (1, gl::FLOAT, 1),
+        gl::FLOAT_VEC2 => (2, gl::FLOAT, 1),
+        gl::FLOAT_VEC3 => (3, gl::FLOAT, 1),
+        gl::FLOAT_VEC4 => (4, gl::FLOAT, 1),
+        gl::INT => (1, gl::INT, 1),
+        gl::INT_VEC2 => (2, gl::INT, 1),
+        gl::INT_VEC3 => (3, gl::INT, 1),
+        gl::INT_VEC4 => (4, gl::INT, 1),
+        gl::UNSIGNED_INT => (1, gl::UNSIGNED_INT, 1),
+        gl::UNSIGNED_INT_VEC2 => (2, gl::UNSIGNED_INT, 1),
+        gl::UNSIGNED_INT_VEC3 => (3, gl::UNSIGNED_INT, 1),
+        gl::UNSIGNED_INT_VEC4 => (4, gl::UNSIGNED_INT, 1),
+        gl::DOUBLE => (1, gl::DOUBLE, 1),
+        gl::DOUBLE_VEC2 => (2, gl::DOUBLE, 1),
+        gl::DOUBLE_VEC3 => (3, gl::DOUBLE, 1),
+        gl::DOUBLE_VEC4 => (4, gl::DOUBLE, 1),
+        gl::FLOAT_MAT2 => (2, gl::FLOAT, 2),
+        gl::FLOAT_MAT3 => (3, gl::FLOAT, 3),
+        gl::FLOAT_MAT4 => (4, gl::FLOAT, 4),
+        gl::FLOAT_MAT2x3 => (3, gl::FLOAT, 2),
+        gl::FLOAT_MAT2x4 => (4, gl::FLOAT, 2),
+        gl::FLOAT_MAT3x2 => (2, gl::FLOAT, 3),
+        gl::FLOAT_MAT3x4 => (4, gl::FLOAT, 3),
+        gl::FLOAT_MAT4x2 => (2, gl::FLOAT, 4),
+        gl::FLOAT_MAT4x3 => (3, gl::FLOAT, 4),
+        _ => return None,
+    })
+}
+
+/*  This function returns the type received by the shader for a layout element.
+*   It is always gl::FLOAT, because the layout is written with gl::VertexAttribPointer.
+*/
+fn get_shader_base_type(_element: &VaoLayoutElement) -> GLenum {
+    gl::FLOAT
+}
+
+/*  This function returns the glsl name of a base type.
+*/
+fn get_type_name(base_type: GLenum) -> &'static str {
+    match base_type {
+        gl::FLOAT => "float",
+        gl::INT => "int",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::DOUBLE => "double",
+        _ => "unknown",
+    }
+}
@@ -1,13 +1,16 @@
 #version 330 core
 out vec4 FragColor;
 
+#ifdef USE_VERTEX_COLOR
 in vec3 ourColor;
+#endif
 in vec2 TexCoord;
 
-uniform sampler2D texture1;
-uniform sampler2D texture2;
-uniform sampler2D texture3;
+#include "textures.glsl"
 
 void main() {
-    FragColor = mix(mix(texture(texture1, TexCoord), texture(texture2, TexCoord), 0.4), texture(texture3, TexCoord), 0.5) * vec4(ourColor, 1.0);
+    FragColor = mix(mix(texture(texture1, TexCoord), texture(texture2, TexCoord), 0.4), texture(texture3, TexCoord), 0.5);
+#ifdef USE_VERTEX_COLOR
+    FragColor *= vec4(ourColor, 1.0);
+#endif
 }
\ No newline at end of file
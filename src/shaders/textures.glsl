@@ -0,0 +1,3 @@
+uniform sampler2D texture1;
+uniform sampler2D texture2;
+uniform sampler2D texture3;